@@ -0,0 +1,185 @@
+//! Connect four game logic that can be shown on the display.
+
+use crate::{LedColor, SyncType};
+
+/// Directions a four in a row can run in, as steps in `x` and `y`.
+const LINES: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+/// Number of pieces in a row that win the game.
+const WIN_LEN: usize = 4;
+
+/// The column a piece was dropped in has no room left, or does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FullColumn;
+
+/// A connect four board `W` columns wide and `H` rows high, where every piece is a color.
+///
+/// Row `0` is the top of the board, pieces fall down to the highest free row.
+#[derive(Debug, Clone)]
+pub struct Board<const W: usize, const H: usize> {
+    cells: [[LedColor; W]; H],
+}
+
+impl<const W: usize, const H: usize> Default for Board<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> Board<W, H> {
+    /// Create an empty board.
+    pub fn new() -> Self {
+        Self {
+            cells: [[LedColor::Off; W]; H],
+        }
+    }
+
+    /// The color of the piece at `x`, `y`, or [LedColor::Off] if there is none.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> LedColor {
+        self.cells[y][x]
+    }
+
+    /// The row a piece dropped in `col` ends up in, if the column has room left.
+    pub fn landing_row(&self, col: usize) -> Option<usize> {
+        if col >= W {
+            return None;
+        }
+        (0..H)
+            .rev()
+            .find(|&y| matches!(self.cells[y][col], LedColor::Off))
+    }
+
+    /// Drop a piece of `color` in `col`, where it falls to the lowest empty row.
+    ///
+    /// # Errors
+    ///
+    /// Returns [FullColumn] if the column has no room left or does not exist.
+    pub fn drop_piece(&mut self, col: usize, color: LedColor) -> Result<(), FullColumn> {
+        let y = self.landing_row(col).ok_or(FullColumn)?;
+        self.cells[y][col] = color;
+        Ok(())
+    }
+
+    /// Check if any color has four or more pieces in a row, horizontally, vertically or
+    /// diagonally.
+    ///
+    /// Returns the winning color and the positions of every piece of the row as `(x, y)`.
+    pub fn check_win(&self) -> Option<(LedColor, Vec<(usize, usize)>)> {
+        for y in 0..H {
+            for x in 0..W {
+                let color = self.cells[y][x];
+                if matches!(color, LedColor::Off) {
+                    continue;
+                }
+                for (dx, dy) in LINES {
+                    // only count rows from their first piece
+                    if self.color_at(x as isize - dx, y as isize - dy) == Some(color as u8) {
+                        continue;
+                    }
+                    let run: Vec<(usize, usize)> = (0..)
+                        .map(|i| (x as isize + i * dx, y as isize + i * dy))
+                        .take_while(|&(rx, ry)| self.color_at(rx, ry) == Some(color as u8))
+                        .map(|(rx, ry)| (rx as usize, ry as usize))
+                        .collect();
+                    if run.len() >= WIN_LEN {
+                        return Some((color, run));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Check if every column is full.
+    pub fn is_full(&self) -> bool {
+        (0..W).all(|col| self.landing_row(col).is_none())
+    }
+
+    /// Sync showing the board, with empty cells turned off.
+    pub fn to_sync(&self) -> SyncType {
+        self.cells.into()
+    }
+
+    /// The encoded color at `x`, `y`, if the position is on the board.
+    fn color_at(&self, x: isize, y: isize) -> Option<u8> {
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) if x < W && y < H => Some(self.cells[y][x] as u8),
+            _ => None,
+        }
+    }
+}
+
+mod test_board {
+    #[allow(unused_imports)]
+    use super::{Board, FullColumn};
+    #[allow(unused_imports)]
+    use crate::{LedColor, SyncType};
+
+    #[test]
+    fn drop_stacks() {
+        let mut board = Board::<7, 6>::new();
+        board.drop_piece(3, LedColor::Red).unwrap();
+        board.drop_piece(3, LedColor::Yellow).unwrap();
+        assert!(matches!(board.get(3, 5), LedColor::Red));
+        assert!(matches!(board.get(3, 4), LedColor::Yellow));
+        assert_eq!(board.landing_row(3), Some(3));
+    }
+
+    #[test]
+    fn full_column() {
+        let mut board = Board::<2, 2>::new();
+        board.drop_piece(0, LedColor::Red).unwrap();
+        board.drop_piece(0, LedColor::Yellow).unwrap();
+        assert_eq!(board.drop_piece(0, LedColor::Red), Err(FullColumn));
+        assert_eq!(board.drop_piece(2, LedColor::Red), Err(FullColumn));
+        assert!(!board.is_full());
+    }
+
+    #[test]
+    fn vertical_win() {
+        let mut board = Board::<7, 6>::new();
+        for _ in 0..3 {
+            board.drop_piece(2, LedColor::Red).unwrap();
+            board.drop_piece(4, LedColor::Yellow).unwrap();
+        }
+        assert!(board.check_win().is_none());
+        board.drop_piece(2, LedColor::Red).unwrap();
+
+        let (color, run) = board.check_win().unwrap();
+        assert!(matches!(color, LedColor::Red));
+        assert_eq!(run, [(2, 2), (2, 3), (2, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn diagonal_win() {
+        let mut board = Board::<7, 6>::new();
+        // a staircase of yellow pieces under a rising diagonal of red ones
+        for (col, below) in [(0, 0), (1, 1), (2, 2), (3, 3)] {
+            for _ in 0..below {
+                board.drop_piece(col, LedColor::Yellow).unwrap();
+            }
+            board.drop_piece(col, LedColor::Red).unwrap();
+        }
+
+        let (color, run) = board.check_win().unwrap();
+        assert!(matches!(color, LedColor::Red));
+        assert_eq!(run, [(0, 5), (1, 4), (2, 3), (3, 2)]);
+    }
+
+    #[test]
+    fn renders_board() {
+        let mut board = Board::<7, 6>::new();
+        board.drop_piece(6, LedColor::Green).unwrap();
+        match board.to_sync() {
+            SyncType::All(rows) => {
+                assert_eq!(rows.len(), 6);
+                assert!(matches!(rows[5][6].color, LedColor::Green));
+                assert!(matches!(rows[0][0].color, LedColor::Off));
+            }
+            other => panic!("unexpected sync: {other:?}"),
+        }
+    }
+}
@@ -4,78 +4,326 @@
 // * option to keep the last frame permanently (ex. for placement dropdown)
 // ? option to reset a led to the state it was before it was affected by an animation
 
-// TODO create animation from text file (macro?)
-
 use std::{
+    fmt,
     str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 
 use crate::{BlinkInfo, DisplayResult, Error, LedColor, LedState};
 
+/// Line separating the animations of a file read by [Animation::many_from_file].
+const ANIMATION_SEPARATOR: &str = "---";
+
+/// Number of brightness steps of a pulse, see [Animation::pulse].
+const PULSE_STEPS: usize = 8;
+/// Number of brightness steps of a crossfade, see [Animation::crossfade].
+const FADE_STEPS: usize = 16;
+/// Shortest frame of a generated animation, the shortest frame an animation file can hold.
+const MIN_FRAME_DUR: Duration = Duration::from_millis(1);
+
 #[derive(Debug)]
 pub enum AnimationParseError {
     MissingParam,
     BadFormatting,
     MissingSeperator,
+    InvalidDuration, // frames have to last at least a millisecond
+}
+
+/// Identifies an animation added to a display, see
+/// [DisplayInterface::add_animation](crate::DisplayInterface::add_animation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnimationId(u64);
+
+impl AnimationId {
+    /// Create a new id that is unique for the lifetime of the program.
+    pub(super) fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Order in which the frames of an animation are played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Playback {
+    /// From the first to the last frame.
+    #[default]
+    Forward,
+    /// From the last to the first frame.
+    Reverse,
+    /// From the first to the last frame and back, without showing the last frame twice.
+    PingPong,
+}
+
+impl fmt::Display for Playback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Forward => "forward",
+            Self::Reverse => "reverse",
+            Self::PingPong => "pingpong",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Playback {
+    type Err = AnimationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "forward" => Ok(Self::Forward),
+            "reverse" => Ok(Self::Reverse),
+            "pingpong" => Ok(Self::PingPong),
+            _ => Err(AnimationParseError::BadFormatting),
+        }
+    }
 }
 
 /// Struct containing animation info.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Animation {
     pub(super) r#loop: bool,                // enable permanent loop
     pub(super) frames: Vec<AnimationFrame>, // frames of the animation
-    pub(super) repeats: usize,              // remaining times to repeat the animation
+    pub(super) repeats: usize,              // remaining times to play the animation
     pub(super) keep_last: bool,             // keep last frame active
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) playback: Playback, // order the frames are played in
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(super) activeframe: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(super) finished: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) paused: bool, // frames don't advance while paused
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) backwards: bool, // a ping-pong play is on its way back
 }
 
 /// A single frame of an animation.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnimationFrame {
+    #[cfg_attr(feature = "serde", serde(with = "super::duration_ms"))]
     pub(super) frame_dur: Duration, // time the frame is active
     pub(super) leds: Vec<(usize, usize, LedState)>, // x, y, led
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(super) start_time: Option<Instant>, // frame start time
-    pub(super) rst_after: bool,     // clear affected leds after frame ends
+    pub(super) rst_after: bool,                     // clear affected leds after frame ends
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(super) bg: Option<LedColor>, // background color set when the frame starts
 }
 
 impl Animation {
     /// Create a new animation.
+    ///
+    /// The animation is played `repeats` times in total, where `0` plays it once just like
+    /// `1`. If `loop` is set it is played over and over instead.
     pub fn new(r#loop: bool, frames: Vec<AnimationFrame>, repeats: usize, keep_last: bool) -> Self {
         Self {
             r#loop,
             frames,
             repeats,
             keep_last,
+            playback: Playback::Forward,
             activeframe: 0,
             finished: false,
+            paused: false,
+            backwards: false,
+        }
+    }
+
+    /// Create an animation that pulses the led at `x`, `y` once over `duration`.
+    ///
+    /// The led ramps from dim to full brightness and back, using the brightness of the led,
+    /// after which it returns to `state`. The led keeps blinking as given by `state` while it
+    /// pulses. A led that is off pulses in white.
+    pub fn pulse(x: usize, y: usize, state: LedState, duration: Duration) -> Self {
+        let color = match state.color {
+            LedColor::Off => LedColor::White,
+            color => color,
+        };
+        let step = (duration / PULSE_STEPS as u32).max(MIN_FRAME_DUR);
+
+        let mut frames: Vec<AnimationFrame> = (0..PULSE_STEPS)
+            .map(|i| {
+                // triangle from 1 / (PULSE_STEPS / 2 + 1) up to full brightness and back down
+                let level = PULSE_STEPS / 2 - i.abs_diff(PULSE_STEPS / 2);
+                let brightness = u8::MAX as usize * (level + 1) / (PULSE_STEPS / 2 + 1);
+                let led = LedState { color, ..state }.with_brightness(brightness as u8);
+                AnimationFrame::new(step, vec![(x, y, led)], false)
+            })
+            .collect();
+        // restore the steady state of the led
+        frames.push(AnimationFrame::new(
+            MIN_FRAME_DUR,
+            vec![(x, y, state)],
+            false,
+        ));
+
+        Self::new(false, frames, 0, true)
+    }
+
+    /// Create an animation that crossfades the board `from` into the colors of `to` over
+    /// `duration`.
+    ///
+    /// Every led that changes color first dims its old color and then brightens into its new
+    /// color, using the brightness of the leds. Once the animation has finished every led
+    /// shows its color of `to` at full brightness.
+    pub fn crossfade<const W: usize, const H: usize>(
+        from: &[[LedState; W]; H],
+        to: &[[LedColor; W]; H],
+        duration: Duration,
+    ) -> Self {
+        let step = (duration / FADE_STEPS as u32).max(MIN_FRAME_DUR);
+        let leds = || (0..H).flat_map(|y| (0..W).map(move |x| (x, y)));
+
+        let mut frames: Vec<AnimationFrame> = (0..FADE_STEPS)
+            .map(|i| {
+                // progress through the fade, without the ends
+                let progress = (i + 1) as f64 / (FADE_STEPS + 1) as f64;
+                let changes = leds()
+                    .filter(|&(x, y)| from[y][x].color as u8 != to[y][x] as u8)
+                    .map(|(x, y)| {
+                        let (color, level) = match progress < 0.5 {
+                            true => (
+                                from[y][x].color,
+                                from[y][x].brightness as f64 * (1.0 - 2.0 * progress),
+                            ),
+                            false => (to[y][x], u8::MAX as f64 * (2.0 * progress - 1.0)),
+                        };
+                        let led = LedState {
+                            color,
+                            blink: None,
+                            brightness: level as u8,
+                        };
+                        (x, y, led)
+                    })
+                    .collect();
+                AnimationFrame::new(step, changes, false)
+            })
+            .collect();
+        // end on the target board at full brightness
+        frames.push(AnimationFrame::new(
+            MIN_FRAME_DUR,
+            leds()
+                .map(|(x, y)| (x, y, LedState::with_color(to[y][x])))
+                .collect(),
+            false,
+        ));
+
+        Self::new(false, frames, 0, true)
+    }
+
+    /// Create an animation of a piece of `color` falling down column `col` of a `W` by `H`
+    /// board, from `from_row` to `to_row`.
+    ///
+    /// The piece is shown in every row it passes through for `step`, and stays in `to_row`
+    /// once the animation has finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the column or rows are not on the
+    /// board, or if `from_row` is below `to_row`.
+    pub fn drop_piece<const W: usize, const H: usize>(
+        col: usize,
+        from_row: usize,
+        to_row: usize,
+        color: LedColor,
+        step: Duration,
+    ) -> DisplayResult<Self> {
+        if col >= W || to_row >= H || from_row > to_row {
+            return Err(Error::InvalidDim);
         }
+
+        let frames = (from_row..=to_row)
+            .map(|row| {
+                AnimationFrame::new(
+                    step,
+                    vec![(col, row, LedState::with_color(color))],
+                    row != to_row,
+                )
+            })
+            .collect();
+
+        Ok(Self::new(false, frames, 0, true))
     }
 
     /// Create a new animation from an ascii text file.
     // TODO text file layout
     pub fn from_file(file: &str) -> DisplayResult<Self> {
-        match std::fs::read_to_string(file) {
-            Ok(string) => match Self::from_str(string.as_str()) {
-                Ok(animation) => Ok(animation),
-                Err(e) => Err(Error::ParseError(e)),
-            },
-            Err(e) => {
-                println!("{}", e);
-                Err(Error::FileNotFound)
-            }
+        Ok(Self::from_str(read_file(file)?.as_str())?)
+    }
+
+    /// Create several animations from one ascii text file.
+    ///
+    /// The animations are separated by a line containing only `---`.
+    pub fn many_from_file(file: &str) -> DisplayResult<Vec<Self>> {
+        Ok(read_file(file)?
+            .lines()
+            .collect::<Vec<&str>>()
+            .split(|line| line.trim() == ANIMATION_SEPARATOR)
+            .map(|block| Self::from_str(block.join("\n").as_str()))
+            .collect::<Result<Vec<Self>, AnimationParseError>>()?)
+    }
+
+    /// Show every frame for `1 / cells_per_sec` seconds, so an animation that moves by one
+    /// led per frame moves at `cells_per_sec` leds per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cells_per_sec` is not a positive number.
+    pub fn scroll_speed(mut self, cells_per_sec: f64) -> Self {
+        assert!(
+            cells_per_sec > 0.0,
+            "scroll speed must be positive, got {cells_per_sec}"
+        );
+        let frame_dur = Duration::from_secs_f64(1.0 / cells_per_sec);
+        for frame in &mut self.frames {
+            frame.frame_dur = frame_dur;
         }
+        self
     }
 
-    /// Increase the active frame by one.
-    pub(super) fn next_frame(&mut self) {
-        self.activeframe += 1;
+    /// Play the frames in the order given by `playback`.
+    pub fn with_playback(mut self, playback: Playback) -> Self {
+        self.playback = playback;
+        self.rst_frame_ctr();
+        self
+    }
+
+    /// Move to the next frame in playback order.
+    ///
+    /// Returns `false` and stays on the active frame if the play has ended.
+    pub(super) fn next_frame(&mut self) -> bool {
+        let last = self.frames.len().saturating_sub(1);
+        match (self.playback, self.backwards) {
+            (Playback::Forward, _) | (Playback::PingPong, false) if self.activeframe < last => {
+                self.activeframe += 1;
+                true
+            }
+            // bounce without showing the last frame twice
+            (Playback::PingPong, false) if self.activeframe > 0 => {
+                self.backwards = true;
+                self.activeframe -= 1;
+                true
+            }
+            (Playback::Reverse, _) | (Playback::PingPong, true) if self.activeframe > 0 => {
+                self.activeframe -= 1;
+                true
+            }
+            _ => false,
+        }
     }
 
-    /// Reset the active frame to frame 0.
-    fn rst_frame_ctr(&mut self) {
-        self.activeframe = 0;
+    /// Reset the active frame to the first frame in playback order.
+    pub(super) fn rst_frame_ctr(&mut self) {
+        self.activeframe = match self.playback {
+            Playback::Reverse => self.frames.len().saturating_sub(1),
+            Playback::Forward | Playback::PingPong => 0,
+        };
+        self.backwards = false;
     }
 
     /// Reset start time of all frames to [None](std::option::Option).
@@ -102,17 +350,29 @@ impl AnimationFrame {
             leds,
             start_time: None,
             rst_after,
+            bg: None,
         }
     }
 
-    // Check if the frame has finished
-    pub(super) fn finished(&self) -> DisplayResult<bool> {
+    /// Set the background color of the display when this frame starts.
+    pub fn with_bg(mut self, bg: LedColor) -> Self {
+        self.bg = Some(bg);
+        self
+    }
+
+    // Check if the frame has finished, with animations played at `speed` times their pace
+    pub(super) fn finished(&self, speed: f64) -> DisplayResult<bool> {
+        self.finished_at(Instant::now(), speed)
+    }
+
+    // Check if the frame has finished at `now`
+    fn finished_at(&self, now: Instant, speed: f64) -> DisplayResult<bool> {
         let start_time = match self.start_time {
             Some(start_time) => start_time,
             None => return Err(Error::Uninitiated),
         };
 
-        Ok(start_time + self.frame_dur < Instant::now())
+        Ok(start_time + self.frame_dur.div_f64(speed) < now)
     }
 }
 
@@ -123,10 +383,11 @@ impl FromStr for Animation {
         use self::AnimationParseError::*;
 
         let lowercased = s.to_lowercase();
-        let mut lines = lowercased.trim().lines();
+        let mut lines = lowercased.trim().lines().peekable();
         let animation_loop;
         let animation_repeats: usize;
         let animation_keep_last;
+        let mut animation_playback = Playback::Forward;
         let mut animation_frames: Vec<AnimationFrame> = Vec::new();
         // check for animation keyword
         match lines.next() {
@@ -268,6 +529,27 @@ impl FromStr for Animation {
             }
         }
 
+        // get optional playback order
+        if let Some(line) = lines.next_if(|line| line.trim_start().starts_with("playback")) {
+            let mut vars = line.split_whitespace().skip(1);
+            match vars.next() {
+                Some(var) => match Playback::from_str(var) {
+                    Ok(playback) => {
+                        log::trace!("found playback {playback}");
+                        animation_playback = playback;
+                    }
+                    Err(e) => {
+                        log::error!("expected playback order, found {var} with error {e:?}");
+                        return Err(BadFormatting);
+                    }
+                },
+                None => {
+                    log::error!("expected playback order, found nothing");
+                    return Err(MissingParam);
+                }
+            }
+        }
+
         match lines.next() {
             Some(line) if line.trim() == "" => (),
             _ => return Err(MissingSeperator),
@@ -294,7 +576,8 @@ impl FromStr for Animation {
             animation_frames,
             animation_repeats,
             animation_keep_last,
-        ))
+        )
+        .with_playback(animation_playback))
     }
 }
 
@@ -305,9 +588,10 @@ impl FromStr for AnimationFrame {
         use self::AnimationParseError::*;
 
         let lowercased = s.to_lowercase();
-        let mut lines = lowercased.trim().lines();
+        let mut lines = lowercased.trim().lines().peekable();
         let frame_dur: usize;
         let frame_rst;
+        let mut frame_bg = None;
         let mut frame_leds = Vec::new();
 
         // check if starts with frame keyword
@@ -341,6 +625,11 @@ impl FromStr for AnimationFrame {
                 // parse duration
                 match vars.next() {
                     Some(var) => match var.parse() {
+                        // a frame without duration finishes right away and flickers
+                        Ok(0) => {
+                            log::error!("expected frame duration larger than 0, found 0");
+                            return Err(InvalidDuration);
+                        }
                         Ok(dur) => {
                             log::trace!("found value {dur}");
                             frame_dur = dur;
@@ -406,13 +695,32 @@ impl FromStr for AnimationFrame {
             }
         }
 
+        // get optional background color
+        if let Some(line) = lines.next_if(|line| line.trim_start().starts_with("bg")) {
+            let mut vars = line.split_whitespace().skip(1);
+            match vars.next() {
+                Some(var) => match LedColor::from_str(var) {
+                    Ok(color) => {
+                        log::trace!("found background {color:?}");
+                        frame_bg = Some(color);
+                    }
+                    Err(e) => {
+                        log::error!("expected background color, found {var} with error {e:?}");
+                        return Err(BadFormatting);
+                    }
+                },
+                None => {
+                    log::error!("expected background color, found nothing");
+                    return Err(MissingParam);
+                }
+            }
+        }
+
         // get leds
         for line in lines {
             let led_x: usize;
             let led_y: usize;
             let led_color: LedColor;
-            let led_blink_dur: usize;
-            let led_blink_int: usize;
 
             let mut vars = line.split_whitespace();
 
@@ -472,59 +780,738 @@ impl FromStr for AnimationFrame {
                 }
             }
 
-            // blink dur
-            match vars.next() {
-                Some(var) => match var.parse() {
-                    Ok(dur) => {
-                        log::trace!("found blink duration {dur}");
-                        led_blink_dur = dur;
-                    }
-                    Err(_) => {
-                        log::error!("expected blink duration (usize), found {var}");
-                        return Err(BadFormatting);
-                    }
-                },
-                None => {
-                    frame_leds.push((led_x, led_y, LedState::with_color(led_color)));
-                    continue;
+            let mut led = LedState::with_color(led_color);
+            let mut vars = vars.peekable();
+
+            // optional blink, either `dur int` in milliseconds or `@hz duty`
+            match vars.next_if(|var| *var != "brightness") {
+                Some(var) if var.starts_with('@') => {
+                    led = led.with_blink(parse_blink_hz(var, vars.next())?)
                 }
+                Some(var) => led = led.with_blink(parse_blink_ms(var, vars.next())?),
+                None => (),
             }
 
-            // blink int
-            match vars.next() {
-                Some(var) => match var.parse() {
-                    Ok(int) => {
-                        log::trace!("found blink interval {int}");
-                        led_blink_int = int
-                    }
-                    Err(_) => {
-                        log::error!("expected blink interval (usize), found {var}");
-                        return Err(BadFormatting);
-                    }
-                },
-                None => {
-                    log::error!("expected blink interval (usize), found nothing");
-                    return Err(MissingParam);
-                }
+            // optional brightness
+            if vars.next_if(|var| *var == "brightness").is_some() {
+                led = led.with_brightness(parse_brightness(vars.next())?);
             }
 
-            frame_leds.push((
-                led_x,
-                led_y,
-                LedState {
-                    color: led_color,
-                    blink: Some(BlinkInfo {
-                        dur: Duration::from_millis(led_blink_dur as u64),
-                        int: Duration::from_millis(led_blink_int as u64),
-                    }),
-                },
-            ));
+            frame_leds.push((led_x, led_y, led));
         }
 
-        return Ok(AnimationFrame::new(
+        let frame = AnimationFrame::new(
             Duration::from_millis(frame_dur as u64),
             frame_leds,
             frame_rst,
+        );
+
+        return Ok(match frame_bg {
+            Some(bg) => frame.with_bg(bg),
+            None => frame,
+        });
+    }
+}
+
+impl fmt::Display for Animation {
+    /// Write the animation in the format read by [Animation::from_file].
+    ///
+    /// `repeats` is the number of plays that are left.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "animation")?;
+        writeln!(f, "loop {}", self.r#loop)?;
+        writeln!(f, "repeats {}", self.repeats)?;
+        writeln!(f, "keep_last {}", self.keep_last)?;
+        if self.playback != Playback::Forward {
+            writeln!(f, "playback {}", self.playback)?;
+        }
+        for frame in &self.frames {
+            writeln!(f)?;
+            write!(f, "{frame}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AnimationFrame {
+    /// Write the frame in the format read by [AnimationFrame::from_str].
+    ///
+    /// Durations are written in whole milliseconds.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "frame")?;
+        writeln!(f, "dur {}", self.frame_dur.as_millis())?;
+        writeln!(f, "rst {}", self.rst_after)?;
+        if let Some(bg) = self.bg {
+            writeln!(f, "bg {bg}")?;
+        }
+        for (x, y, state) in &self.leds {
+            write!(f, "{x} {y} {}", state.color)?;
+            if let Some(blink) = state.blink {
+                write!(f, " {} {}", blink.dur.as_millis(), blink.int.as_millis())?;
+            }
+            if state.brightness != u8::MAX {
+                write!(f, " brightness {}", state.brightness)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the animation file at `file`.
+///
+/// A file that does not exist is reported as a `c4_display::error::Error::FileNotFound`,
+/// every other failure as a `c4_display::error::Error::Io`.
+fn read_file(file: &str) -> DisplayResult<String> {
+    std::fs::read_to_string(file).map_err(|e| {
+        log::error!("Failed to read animation file {file}: {e}");
+        read_error(e)
+    })
+}
+
+/// The error reported for an animation file that could not be read.
+fn read_error(e: std::io::Error) -> Error {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => Error::FileNotFound,
+        _ => Error::from(e),
+    }
+}
+
+/// Parse a blink given as duration and interval in milliseconds, e.g. `250 500`.
+fn parse_blink_ms(dur: &str, int: Option<&str>) -> Result<BlinkInfo, AnimationParseError> {
+    use self::AnimationParseError::*;
+
+    let led_blink_dur: u64 = match dur.parse() {
+        Ok(dur) => {
+            log::trace!("found blink duration {dur}");
+            dur
+        }
+        Err(_) => {
+            log::error!("expected blink duration (usize), found {dur}");
+            return Err(BadFormatting);
+        }
+    };
+
+    let led_blink_int: u64 = match int {
+        Some(var) => match var.parse() {
+            Ok(int) => {
+                log::trace!("found blink interval {int}");
+                int
+            }
+            Err(_) => {
+                log::error!("expected blink interval (usize), found {var}");
+                return Err(BadFormatting);
+            }
+        },
+        None => {
+            log::error!("expected blink interval (usize), found nothing");
+            return Err(MissingParam);
+        }
+    };
+
+    Ok(BlinkInfo {
+        dur: Duration::from_millis(led_blink_dur),
+        int: Duration::from_millis(led_blink_int),
+    })
+}
+
+/// Parse the brightness of a led, e.g. `128` for about half brightness.
+fn parse_brightness(brightness: Option<&str>) -> Result<u8, AnimationParseError> {
+    use self::AnimationParseError::*;
+
+    match brightness {
+        Some(var) => match var.parse() {
+            Ok(brightness) => {
+                log::trace!("found brightness {brightness}");
+                Ok(brightness)
+            }
+            Err(_) => {
+                log::error!("expected brightness (0 - 255), found {var}");
+                Err(BadFormatting)
+            }
+        },
+        None => {
+            log::error!("expected brightness (0 - 255), found nothing");
+            Err(MissingParam)
+        }
+    }
+}
+
+/// Parse a blink given as frequency and duty cycle, e.g. `@2 0.5` for 2Hz at 50%.
+fn parse_blink_hz(hz: &str, duty: Option<&str>) -> Result<BlinkInfo, AnimationParseError> {
+    use self::AnimationParseError::*;
+
+    let led_blink_hz: f64 = match hz.trim_start_matches('@').parse::<f64>() {
+        Ok(hz) if hz > 0.0 && hz.is_finite() => {
+            log::trace!("found blink frequency {hz}");
+            hz
+        }
+        _ => {
+            log::error!("expected blink frequency (f64 > 0), found {hz}");
+            return Err(BadFormatting);
+        }
+    };
+
+    let led_blink_duty: f64 = match duty {
+        Some(var) => match var.parse() {
+            Ok(duty) if (0.0..=1.0).contains(&duty) => {
+                log::trace!("found blink duty cycle {duty}");
+                duty
+            }
+            _ => {
+                log::error!("expected blink duty cycle (0.0 - 1.0), found {var}");
+                return Err(BadFormatting);
+            }
+        },
+        None => {
+            log::error!("expected blink duty cycle (0.0 - 1.0), found nothing");
+            return Err(MissingParam);
+        }
+    };
+
+    let int = Duration::from_secs_f64(1.0 / led_blink_hz);
+    Ok(BlinkInfo {
+        dur: int.mul_f64(led_blink_duty),
+        int,
+    })
+}
+
+mod test_frame_bg {
+    #[allow(unused_imports)]
+    use super::{AnimationFrame, AnimationParseError};
+    #[allow(unused_imports)]
+    use crate::LedColor;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_bg() {
+        let frame =
+            AnimationFrame::from_str("frame\ndur 100\nrst false\nbg blue\n3 3 red").unwrap();
+        assert!(matches!(frame.bg, Some(LedColor::Blue)));
+        assert_eq!(frame.leds.len(), 1);
+    }
+
+    #[test]
+    fn parse_without_bg() {
+        let frame = AnimationFrame::from_str("frame\ndur 100\nrst false\n3 3 red").unwrap();
+        assert!(frame.bg.is_none());
+    }
+
+    #[test]
+    fn zero_duration() {
+        assert!(matches!(
+            AnimationFrame::from_str("frame\ndur 0\nrst false\n3 3 red"),
+            Err(AnimationParseError::InvalidDuration)
+        ));
+        assert!(AnimationFrame::from_str("frame\ndur 1\nrst false\n3 3 red").is_ok());
+    }
+
+    #[test]
+    fn parse_hex_colors() {
+        let frame =
+            AnimationFrame::from_str("frame\ndur 100\nrst false\nbg #000010\n3 3 #f01010").unwrap();
+        assert!(matches!(frame.bg, Some(LedColor::Off)));
+        assert!(matches!(frame.leds[0].2.color, LedColor::Red));
+    }
+}
+
+#[cfg(feature = "serde")]
+mod test_serde {
+    #[allow(unused_imports)]
+    use super::Animation;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn round_trip() {
+        let animation = Animation::from_str(
+            "animation\nloop true\nrepeats 2\nkeep_last false\n\nframe\ndur 1000\nrst true\n3 3 red 100 200\n\nframe\ndur 500\nrst false\nbg blue\n3 2 green",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&animation).unwrap();
+        let parsed: Animation = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.frames.len(), 2);
+        assert_eq!(parsed.frames[0].frame_dur.as_millis(), 1000);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+}
+
+mod test_pulse {
+    #[allow(unused_imports)]
+    use super::Animation;
+    #[allow(unused_imports)]
+    use crate::{BlinkInfo, LedColor, LedState};
+    #[allow(unused_imports)]
+    use std::{str::FromStr, time::Duration};
+
+    #[test]
+    fn peaks_mid_pulse() {
+        let state = LedState::with_color(LedColor::Green).with_brightness(100);
+        let pulse = Animation::pulse(2, 3, state, Duration::from_millis(800));
+        let levels: Vec<u8> = pulse
+            .frames
+            .iter()
+            .map(|frame| {
+                assert_eq!(frame.leds.len(), 1);
+                let (x, y, led) = frame.leds[0];
+                assert_eq!((x, y), (2, 3));
+                assert!(matches!(led.color, LedColor::Green));
+                assert_eq!(led.blink, None);
+                led.brightness()
+            })
+            .collect();
+
+        let mid = (levels.len() - 1) / 2;
+        assert_eq!(levels[mid], u8::MAX);
+        assert!(levels[0] < levels[1]);
+        assert!(levels[levels.len() - 2] < u8::MAX);
+        // steady brightness is restored once the pulse ends
+        assert_eq!(*levels.last().unwrap(), 100);
+        assert!(pulse.keep_last);
+
+        let steps = &pulse.frames[..pulse.frames.len() - 1];
+        let total: Duration = steps.iter().map(|frame| frame.frame_dur).sum();
+        assert_eq!(total, Duration::from_millis(800));
+    }
+
+    #[test]
+    fn export_round_trip() {
+        for duration in [Duration::from_millis(800), Duration::from_micros(500)] {
+            let state = LedState::with_color(LedColor::Green).with_brightness(100);
+            let pulse = Animation::pulse(2, 3, state, duration);
+            let text = pulse.to_string();
+            let parsed = Animation::from_str(&text).unwrap();
+            assert_eq!(parsed.frames.len(), pulse.frames.len());
+            assert_eq!(parsed.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn keeps_blink() {
+        let blink = BlinkInfo {
+            dur: Duration::from_millis(100),
+            int: Duration::from_millis(200),
+        };
+        let state = LedState::with_color(LedColor::Red).with_blink(blink);
+        let pulse = Animation::pulse(0, 0, state, Duration::from_millis(80));
+        assert!(pulse
+            .frames
+            .iter()
+            .all(|frame| frame.leds[0].2.blink == Some(blink)));
+    }
+
+    #[test]
+    fn off_led_pulses_white() {
+        let pulse = Animation::pulse(0, 0, LedState::default(), Duration::from_millis(80));
+        assert!(matches!(pulse.frames[0].leds[0].2.color, LedColor::White));
+        assert!(matches!(
+            pulse.frames.last().unwrap().leds[0].2.color,
+            LedColor::Off
+        ));
+    }
+}
+
+mod test_crossfade {
+    #[allow(unused_imports)]
+    use super::Animation;
+    #[allow(unused_imports)]
+    use crate::{LedColor, LedState};
+    #[allow(unused_imports)]
+    use std::{str::FromStr, time::Duration};
+
+    #[test]
+    fn dims_out_then_in() {
+        let mut from = [[LedState::with_color(LedColor::Blue); 2]; 2];
+        from[1][0] = LedState::with_color(LedColor::Red);
+        let mut to = [[LedColor::Blue; 2]; 2];
+        to[1][0] = LedColor::Green;
+        let fade = Animation::crossfade(&from, &to, Duration::from_millis(320));
+
+        // only the changing led is faded, first in its old color and then in its new one
+        let steps = &fade.frames[..fade.frames.len() - 1];
+        let levels: Vec<(u8, u8)> = steps
+            .iter()
+            .map(|frame| {
+                assert_eq!(frame.leds.len(), 1);
+                let (x, y, led) = frame.leds[0];
+                assert_eq!((x, y), (0, 1));
+                (led.color as u8, led.brightness)
+            })
+            .collect();
+        let half = levels.len() / 2;
+        assert!(levels[..half]
+            .iter()
+            .all(|&(color, _)| color == LedColor::Red as u8));
+        assert!(levels[half..]
+            .iter()
+            .all(|&(color, _)| color == LedColor::Green as u8));
+        assert!(levels[..half].windows(2).all(|w| w[0].1 > w[1].1));
+        assert!(levels[half..].windows(2).all(|w| w[0].1 < w[1].1));
+
+        // the target board is kept at full brightness
+        let last = fade.frames.last().unwrap();
+        assert_eq!(last.leds.len(), 4);
+        assert!(last
+            .leds
+            .iter()
+            .all(|(x, y, led)| led.color as u8 == to[*y][*x] as u8 && led.brightness == 255));
+        assert!(fade.keep_last);
+
+        let total: Duration = steps.iter().map(|frame| frame.frame_dur).sum();
+        assert_eq!(total, Duration::from_millis(320));
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let from = [[LedState::with_color(LedColor::Red); 3]; 2];
+        let to = [[LedColor::Cyan; 3]; 2];
+        for duration in [Duration::from_millis(320), Duration::ZERO] {
+            let fade = Animation::crossfade(&from, &to, duration);
+            let text = fade.to_string();
+            let parsed = Animation::from_str(&text).unwrap();
+            assert_eq!(parsed.frames.len(), fade.frames.len());
+            assert_eq!(parsed.to_string(), text);
+        }
+    }
+}
+
+mod test_drop_piece {
+    #[allow(unused_imports)]
+    use super::Animation;
+    #[allow(unused_imports)]
+    use crate::{Error, LedColor};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn straight_descent() {
+        let step = Duration::from_millis(50);
+        let drop = Animation::drop_piece::<7, 6>(2, 0, 5, LedColor::Red, step).unwrap();
+
+        assert_eq!(drop.frames.len(), 6);
+        for (row, frame) in drop.frames.iter().enumerate() {
+            assert_eq!(frame.leds.len(), 1);
+            let (x, y, led) = frame.leds[0];
+            assert_eq!((x, y), (2, row));
+            assert!(matches!(led.color, LedColor::Red));
+            assert_eq!(frame.frame_dur, step);
+            assert_eq!(frame.rst_after, row != 5);
+        }
+        assert!(drop.keep_last);
+        assert!(!drop.r#loop);
+    }
+
+    #[test]
+    fn off_board() {
+        let step = Duration::from_millis(50);
+        let drop =
+            |col, from, to| Animation::drop_piece::<7, 6>(col, from, to, LedColor::Red, step);
+        assert!(matches!(drop(7, 0, 5), Err(Error::InvalidDim)));
+        assert!(matches!(drop(0, 0, 6), Err(Error::InvalidDim)));
+        assert!(matches!(drop(0, 3, 2), Err(Error::InvalidDim)));
+        assert!(drop(6, 5, 5).is_ok());
+    }
+}
+
+mod test_from_file {
+    #[allow(unused_imports)]
+    use super::{read_error, Animation, AnimationParseError};
+    #[allow(unused_imports)]
+    use crate::Error;
+
+    #[test]
+    fn bad_file() {
+        let path = std::env::temp_dir().join("c4_display_bad_animation.mtxani");
+        std::fs::write(&path, "animation\nloop maybe\n").unwrap();
+
+        let result = Animation::from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(Error::ParseError(AnimationParseError::BadFormatting))
         ));
     }
+
+    #[test]
+    fn missing_file() {
+        let path = std::env::temp_dir().join("c4_display_missing_animation.mtxani");
+        assert!(matches!(
+            Animation::from_file(path.to_str().unwrap()),
+            Err(Error::FileNotFound)
+        ));
+    }
+
+    #[test]
+    fn unreadable_file() {
+        // a directory exists, but can not be read as a file
+        let dir = std::env::temp_dir();
+        assert!(matches!(
+            Animation::from_file(dir.to_str().unwrap()),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn permission_denied() {
+        // mapped without a locked file, since permissions do not stop a superuser
+        let e = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            read_error(e),
+            Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied
+        ));
+    }
+}
+
+mod test_many_from_file {
+    #[allow(unused_imports)]
+    use super::Animation;
+
+    #[test]
+    fn two_animations() {
+        let path = std::env::temp_dir().join("c4_display_two_animations.mtxani");
+        std::fs::write(
+            &path,
+            "animation\nloop true\nrepeats 0\nkeep_last false\n\nframe\ndur 50\nrst true\n0 0 red\n\
+             ---\n\
+             animation\nloop false\nrepeats 2\nkeep_last true\n\nframe\ndur 20\nrst false\n1 1 blue\n\n\
+             frame\ndur 20\nrst false\n2 2 blue\n",
+        )
+        .unwrap();
+
+        let animations = Animation::many_from_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(animations.len(), 2);
+        assert!(animations[0].r#loop);
+        assert_eq!(animations[0].frames.len(), 1);
+        assert!(!animations[1].r#loop);
+        assert_eq!(animations[1].repeats, 2);
+        assert_eq!(animations[1].frames.len(), 2);
+    }
+}
+
+mod test_blink_hz {
+    #[allow(unused_imports)]
+    use super::{AnimationFrame, AnimationParseError};
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn same_as_milliseconds() {
+        let frame =
+            AnimationFrame::from_str("frame\ndur 50\nrst false\n3 4 red @2 0.5\n3 5 red 250 500")
+                .unwrap();
+        let hz = frame.leds[0].2.blink.unwrap();
+        let ms = frame.leds[1].2.blink.unwrap();
+        assert_eq!(hz.dur, ms.dur);
+        assert_eq!(hz.int, ms.int);
+    }
+
+    #[test]
+    fn invalid_duty() {
+        assert!(matches!(
+            AnimationFrame::from_str("frame\ndur 50\nrst false\n3 4 red @2 1.5"),
+            Err(AnimationParseError::BadFormatting)
+        ));
+        assert!(matches!(
+            AnimationFrame::from_str("frame\ndur 50\nrst false\n3 4 red @2"),
+            Err(AnimationParseError::MissingParam)
+        ));
+    }
+}
+
+mod test_to_string {
+    #[allow(unused_imports)]
+    use super::Animation;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn round_trip() {
+        let text = "animation\nloop false\nrepeats 3\nkeep_last true\n\n\
+                    frame\ndur 50\nrst true\nbg blue\n0 0 red\n1 2 green 250 500\n\n\
+                    frame\ndur 20\nrst false\n6 6 white\n";
+        let animation = Animation::from_str(text).unwrap();
+        assert_eq!(animation.to_string(), text);
+
+        let reparsed = Animation::from_str(animation.to_string().as_str()).unwrap();
+        assert_eq!(reparsed.to_string(), animation.to_string());
+        assert_eq!(reparsed.frames.len(), 2);
+        assert_eq!(reparsed.repeats, 3);
+        assert!(reparsed.keep_last);
+    }
+
+    #[test]
+    fn keeps_brightness() {
+        let text = "animation\nloop false\nrepeats 0\nkeep_last false\n\n\
+                    frame\ndur 50\nrst true\n0 0 red brightness 40\n1 2 green 250 500 brightness 0\n\
+                    3 3 blue\n";
+        let animation = Animation::from_str(text).unwrap();
+        assert_eq!(animation.to_string(), text);
+        let leds = &animation.frames[0].leds;
+        assert_eq!(leds[0].2.brightness(), 40);
+        assert_eq!(leds[1].2.brightness(), 0);
+        assert!(leds[1].2.blink.is_some());
+        assert_eq!(leds[2].2.brightness(), u8::MAX);
+    }
+
+    #[test]
+    fn invalid_brightness() {
+        for led in [
+            "0 0 red brightness 256",
+            "0 0 red brightness",
+            "0 0 red 1 2 brightness x",
+        ] {
+            let text = format!("animation\nloop false\nrepeats 0\nkeep_last false\n\nframe\ndur 50\nrst true\n{led}\n");
+            assert!(Animation::from_str(&text).is_err(), "{led}");
+        }
+    }
+}
+
+mod test_scroll_speed {
+    #[allow(unused_imports)]
+    use super::{Animation, AnimationFrame};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn two_cells_per_second() {
+        let frames = (0..7)
+            .map(|x| {
+                AnimationFrame::new(
+                    Duration::from_millis(50),
+                    vec![(x, 0, Default::default())],
+                    true,
+                )
+            })
+            .collect();
+        let animation = Animation::new(true, frames, 0, false).scroll_speed(2.0);
+        assert!(animation
+            .frames
+            .iter()
+            .all(|frame| frame.frame_dur == Duration::from_millis(500)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_speed() {
+        let _ = Animation::new(true, Vec::new(), 0, false).scroll_speed(0.0);
+    }
+}
+
+mod test_playback {
+    #[allow(unused_imports)]
+    use super::{Animation, AnimationFrame, AnimationParseError, Playback};
+    #[allow(unused_imports)]
+    use std::{str::FromStr, time::Duration};
+
+    /// Indices of the frames of one play.
+    #[allow(dead_code)]
+    fn order(playback: Playback, frames: usize) -> Vec<usize> {
+        let frames = (0..frames)
+            .map(|x| {
+                AnimationFrame::new(
+                    Duration::from_millis(1),
+                    vec![(x, 0, Default::default())],
+                    false,
+                )
+            })
+            .collect();
+        let mut animation = Animation::new(false, frames, 0, false).with_playback(playback);
+        let mut order = vec![animation.activeframe];
+        while animation.next_frame() {
+            order.push(animation.activeframe);
+        }
+        order
+    }
+
+    #[test]
+    fn forward() {
+        assert_eq!(order(Playback::Forward, 4), [0, 1, 2, 3]);
+        assert_eq!(order(Playback::Forward, 1), [0]);
+    }
+
+    #[test]
+    fn reverse() {
+        assert_eq!(order(Playback::Reverse, 4), [3, 2, 1, 0]);
+        assert_eq!(order(Playback::Reverse, 1), [0]);
+    }
+
+    #[test]
+    fn ping_pong() {
+        assert_eq!(order(Playback::PingPong, 4), [0, 1, 2, 3, 2, 1, 0]);
+        assert_eq!(order(Playback::PingPong, 2), [0, 1, 0]);
+        assert_eq!(order(Playback::PingPong, 1), [0]);
+    }
+
+    #[test]
+    fn rst_restarts_direction() {
+        let frames = (0..3)
+            .map(|x| {
+                AnimationFrame::new(
+                    Duration::from_millis(1),
+                    vec![(x, 0, Default::default())],
+                    false,
+                )
+            })
+            .collect();
+        let mut animation =
+            Animation::new(true, frames, 0, false).with_playback(Playback::PingPong);
+        while animation.next_frame() {}
+        animation.rst();
+        assert_eq!(animation.activeframe, 0);
+        assert!(animation.next_frame());
+        assert_eq!(animation.activeframe, 1);
+    }
+
+    #[test]
+    fn parse() {
+        let text = "animation\nloop false\nrepeats 0\nkeep_last false\nplayback pingpong\n\n\
+                    frame\ndur 50\nrst true\n0 0 red\n\n\
+                    frame\ndur 50\nrst true\n1 0 red\n";
+        let animation = Animation::from_str(text).unwrap();
+        assert_eq!(animation.playback, Playback::PingPong);
+        assert_eq!(animation.to_string(), text);
+
+        let reverse = Animation::from_str(&text.replace("pingpong", "reverse")).unwrap();
+        assert_eq!(reverse.playback, Playback::Reverse);
+        assert_eq!(reverse.activeframe, 1);
+
+        let forward = Animation::from_str(&text.replace("playback pingpong\n", "")).unwrap();
+        assert_eq!(forward.playback, Playback::Forward);
+
+        assert!(matches!(
+            Animation::from_str(&text.replace("pingpong", "sideways")),
+            Err(AnimationParseError::BadFormatting)
+        ));
+    }
+}
+
+mod test_frame_finished {
+    #[allow(unused_imports)]
+    use super::AnimationFrame;
+    #[allow(unused_imports)]
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn double_speed() {
+        let mut frame = AnimationFrame::new(Duration::from_millis(1000), Vec::new(), false);
+        let start = Instant::now();
+        frame.start_time = Some(start);
+
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(!frame.finished_at(at(499), 2.0).unwrap());
+        assert!(frame.finished_at(at(501), 2.0).unwrap());
+        assert!(!frame.finished_at(at(999), 1.0).unwrap());
+        assert!(frame.finished_at(at(1001), 1.0).unwrap());
+        assert!(!frame.finished_at(at(1999), 0.5).unwrap());
+    }
+
+    #[test]
+    fn not_started() {
+        let frame = AnimationFrame::new(Duration::from_millis(1000), Vec::new(), false);
+        assert!(frame.finished_at(Instant::now(), 1.0).is_err());
+    }
 }
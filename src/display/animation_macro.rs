@@ -0,0 +1,170 @@
+/// Create an [Animation](crate::Animation) written in the grammar of
+/// [Animation::from_file](crate::Animation::from_file), checked at compile time.
+///
+/// Every frame is wrapped in braces, since the macro can not see the empty lines that
+/// separate the frames of a file. A led is written as `x y color`, optionally followed by
+/// `blink dur int` to blink it on for `dur` out of every `int` milliseconds. Colors are
+/// named like [LedColor](crate::LedColor) in lowercase.
+///
+/// Unknown keywords or colors and frames lasting `0` milliseconds fail to compile.
+/// Whether every led fits the display is still checked when the animation is added.
+///
+/// # Example
+///
+/// ```
+/// use c4_display::animation;
+///
+/// let sweep = animation! {
+///     loop true
+///     repeats 0
+///     keep_last false
+///     frame {
+///         dur 50
+///         rst true
+///         0 0 red
+///         1 0 red blink 100 200
+///     }
+///     frame {
+///         dur 50
+///         rst true
+///         bg blue
+///         2 0 white
+///     }
+/// };
+/// ```
+#[macro_export]
+macro_rules! animation {
+    (
+        loop $looping:literal
+        repeats $repeats:literal
+        keep_last $keep_last:literal
+        $(playback $playback:ident)?
+        $(
+            frame {
+                dur $dur:literal
+                rst $rst:literal
+                $(bg $bg:ident)?
+                $($x:literal $y:literal $color:ident $(blink $blink_dur:literal $blink_int:literal)?)*
+            }
+        )*
+    ) => {{
+        let animation = $crate::Animation::new(
+            $looping,
+            vec![$({
+                const _: () = assert!($dur > 0, "frames have to last at least a millisecond");
+                let frame = $crate::AnimationFrame::new(
+                    ::std::time::Duration::from_millis($dur),
+                    vec![$((
+                        $x,
+                        $y,
+                        $crate::__animation_led!($color $(, $blink_dur, $blink_int)?),
+                    )),*],
+                    $rst,
+                );
+                $(let frame = frame.with_bg($crate::__animation_color!($bg));)?
+                frame
+            }),*],
+            $repeats,
+            $keep_last,
+        );
+        $(let animation = animation.with_playback($crate::__animation_playback!($playback));)?
+        animation
+    }};
+}
+
+/// Led of an [animation!], with an optional blink in milliseconds.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __animation_led {
+    ($color:ident) => {
+        $crate::LedState::with_color($crate::__animation_color!($color))
+    };
+    ($color:ident, $dur:literal, $int:literal) => {
+        $crate::LedState::with_color($crate::__animation_color!($color)).with_blink(
+            $crate::BlinkInfo {
+                dur: ::std::time::Duration::from_millis($dur),
+                int: ::std::time::Duration::from_millis($int),
+            },
+        )
+    };
+}
+
+/// Color of an [animation!], named in lowercase.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __animation_color {
+    (off) => {
+        $crate::LedColor::Off
+    };
+    (red) => {
+        $crate::LedColor::Red
+    };
+    (green) => {
+        $crate::LedColor::Green
+    };
+    (yellow) => {
+        $crate::LedColor::Yellow
+    };
+    (blue) => {
+        $crate::LedColor::Blue
+    };
+    (magenta) => {
+        $crate::LedColor::Magenta
+    };
+    (cyan) => {
+        $crate::LedColor::Cyan
+    };
+    (white) => {
+        $crate::LedColor::White
+    };
+}
+
+/// Playback of an [animation!], named like in a file.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __animation_playback {
+    (forward) => {
+        $crate::Playback::Forward
+    };
+    (reverse) => {
+        $crate::Playback::Reverse
+    };
+    (pingpong) => {
+        $crate::Playback::PingPong
+    };
+}
+
+mod test_animation_macro {
+    #[allow(unused_imports)]
+    use crate::Animation;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn same_as_text() {
+        let text = "animation\nloop false\nrepeats 2\nkeep_last true\nplayback pingpong\n\n\
+                    frame\ndur 50\nrst true\n2 0 white\n3 0 red 100 200\n\n\
+                    frame\ndur 20\nrst false\nbg blue\n4 1 green\n";
+        let parsed = Animation::from_str(text).unwrap();
+        let inline = animation! {
+            loop false
+            repeats 2
+            keep_last true
+            playback pingpong
+            frame {
+                dur 50
+                rst true
+                2 0 white
+                3 0 red blink 100 200
+            }
+            frame {
+                dur 20
+                rst false
+                bg blue
+                4 1 green
+            }
+        };
+
+        assert_eq!(inline.to_string(), parsed.to_string());
+    }
+}
@@ -0,0 +1,187 @@
+use std::{sync::mpsc::channel, thread};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    display::{
+        display_interface::{snapshot_board, validate_animation, validate_sync},
+        Animation, AnimationId, DisplayInterface, Instruction, Running, Snapshot, Stopped,
+        SyncType,
+    },
+    DisplayResult, Error,
+};
+
+/// An interface for a running display that can be used from async code, created by
+/// [DisplayInterface::into_async].
+///
+/// Instructions are queued on a tokio channel and handed to the display thread by a bridge
+/// thread, so a full queue makes the sending task wait instead of blocking the runtime.
+/// Instructions keep their order, a snapshot always shows every sync sent before it.
+///
+/// Its methods have to be awaited on a tokio runtime.
+///
+/// If this gets dropped or goes out of scope the display will stop working.
+#[derive(Debug)]
+pub struct AsyncDisplayInterface<'d, const W: usize, const H: usize> {
+    tx: mpsc::Sender<Instruction>,
+    bridged: oneshot::Receiver<()>, // resolves once every queued instruction is handed over
+    interface: DisplayInterface<'d, Running, W, H>,
+}
+
+impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
+    /// Drive this display from async code, see [AsyncDisplayInterface].
+    pub fn into_async(self) -> AsyncDisplayInterface<'d, W, H> {
+        let (display_tx, capacity) = self.channel();
+        let (tx, mut rx) = mpsc::channel(capacity.max(1));
+        let (bridged_tx, bridged) = oneshot::channel();
+
+        thread::spawn(move || {
+            while let Some(instruction) = rx.blocking_recv() {
+                if display_tx.send(instruction).is_err() {
+                    log::error!("Display thread stopped, dropping queued instructions");
+                    break;
+                }
+            }
+            let _ = bridged_tx.send(());
+        });
+
+        AsyncDisplayInterface {
+            tx,
+            bridged,
+            interface: self,
+        }
+    }
+}
+
+impl<'d, const W: usize, const H: usize> AsyncDisplayInterface<'d, W, H> {
+    /// Update the colors of the leds, see [DisplayInterface::sync].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped.
+    ///
+    /// Returns every error of [DisplayInterface::sync] for an invalid sync.
+    pub async fn sync(&mut self, sync_type: SyncType) -> DisplayResult<()> {
+        validate_sync::<W, H>(&sync_type)?;
+        self.send(Instruction::Sync(sync_type)).await
+    }
+
+    /// Add an animation, see [DisplayInterface::add_animation].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped.
+    ///
+    /// Returns every error of [DisplayInterface::add_animation] for an invalid animation.
+    pub async fn add_animation(&mut self, animation: Animation) -> DisplayResult<AnimationId> {
+        validate_animation::<W, H>(&animation)?;
+
+        let id = AnimationId::next();
+        self.send(Instruction::AddAnimation(id, animation)).await?;
+        Ok(id)
+    }
+
+    /// Returns the current state of every led on the display, see
+    /// [DisplayInterface::snapshot].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped.
+    pub async fn snapshot(&self) -> DisplayResult<Snapshot<W, H>> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Instruction::Snapshot(reply_tx)).await?;
+
+        // the display thread replies on a std channel, which is waited on off the runtime
+        let board = tokio::task::spawn_blocking(move || reply_rx.recv())
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .ok_or(Error::Disconnected)?;
+        Ok(snapshot_board(&board))
+    }
+
+    /// Wait until every queued instruction is handed to the display thread, and return the
+    /// blocking interface of the display.
+    pub async fn into_inner(self) -> DisplayInterface<'d, Running, W, H> {
+        drop(self.tx);
+        // the bridge also finishes if the display thread stopped
+        let _ = self.bridged.await;
+        self.interface
+    }
+
+    /// Stop the display once every queued instruction is handed to the display thread, see
+    /// [DisplayInterface::stop].
+    ///
+    /// The display stops after completing its current cycle, which is waited for.
+    pub async fn stop(self) -> DisplayInterface<'d, Stopped, W, H> {
+        self.into_inner().await.stop()
+    }
+
+    /// Queue `instruction` for the display thread.
+    async fn send(&self, instruction: Instruction) -> DisplayResult<()> {
+        self.tx
+            .send(instruction)
+            .await
+            .map_err(|_| Error::Disconnected)
+    }
+}
+
+mod test_async_interface {
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        Animation, AnimationFrame, Error, LedColor, LedState, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    #[tokio::test]
+    async fn instructions_keep_order() {
+        let (disp, rx) = interface();
+        let mut disp = disp.into_async();
+
+        // answer snapshots like the display thread would
+        let display = thread::spawn(move || {
+            let mut sent = Vec::new();
+            for instruction in rx {
+                if let Instruction::Snapshot(reply) = &instruction {
+                    let mut board = vec![vec![LedState::default(); 7]; 7];
+                    board[1][2] = LedState::with_color(LedColor::Red);
+                    reply.send(board).unwrap();
+                }
+                sent.push(instruction);
+            }
+            sent
+        });
+
+        disp.sync(SyncType::Clear).await.unwrap();
+        let frame = AnimationFrame::new(
+            Duration::from_millis(100),
+            vec![(3, 3, LedState::with_color(LedColor::Blue))],
+            true,
+        );
+        disp.add_animation(Animation::new(false, vec![frame], 0, false))
+            .await
+            .unwrap();
+        let snapshot = disp.snapshot().await.unwrap();
+        assert_eq!(snapshot[1][2], LedState::with_color(LedColor::Red));
+        drop(disp.into_inner().await);
+
+        let sent = display.join().unwrap();
+        assert!(matches!(sent[0], Instruction::Sync(SyncType::Clear)));
+        assert!(matches!(sent[1], Instruction::AddAnimation(_, _)));
+        assert!(matches!(sent[2], Instruction::Snapshot(_)));
+        assert!(matches!(sent[3], Instruction::Stop));
+    }
+
+    #[tokio::test]
+    async fn invalid_sync() {
+        let (disp, rx) = interface();
+        let mut disp = disp.into_async();
+        let sync = SyncType::All(vec![vec![LedState::default(); 8]; 7]);
+        assert!(matches!(disp.sync(sync).await, Err(Error::InvalidDim)));
+
+        drop(disp.into_inner().await);
+        assert!(matches!(rx.try_recv(), Ok(Instruction::Stop)));
+    }
+}
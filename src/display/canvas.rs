@@ -0,0 +1,211 @@
+use crate::display::LedState;
+
+/// A `VW` by `VH` board that can be larger than the display, see
+/// [DisplayInterface::set_canvas](crate::DisplayInterface::set_canvas).
+///
+/// Indexes like the board it wraps, as `canvas[y][x]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas<const VW: usize, const VH: usize>(pub [[LedState; VW]; VH]);
+
+impl<const VW: usize, const VH: usize> Default for Canvas<VW, VH> {
+    /// A canvas with every led off.
+    fn default() -> Self {
+        Self([[LedState::default(); VW]; VH])
+    }
+}
+
+impl<const VW: usize, const VH: usize> std::ops::Deref for Canvas<VW, VH> {
+    type Target = [[LedState; VW]; VH];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const VW: usize, const VH: usize> std::ops::DerefMut for Canvas<VW, VH> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// The canvas shown by a display and the top left corner of the shown window.
+#[derive(Debug)]
+pub(super) struct Viewport {
+    pub(super) canvas: Vec<Vec<LedState>>,
+    pub(super) origin: (usize, usize),
+}
+
+impl Viewport {
+    /// Move the window to `origin`, clamped so it stays inside the canvas, and return the
+    /// board it shows.
+    pub(super) fn crop<const W: usize, const H: usize>(
+        &mut self,
+        origin: (usize, usize),
+    ) -> Vec<Vec<LedState>> {
+        self.origin = clamp_origin::<W, H>(&self.canvas, origin);
+        crop_canvas::<W, H>(&self.canvas, self.origin)
+    }
+}
+
+/// Copy the `W`x`H` window at `origin` out of a canvas, filling parts outside the canvas
+/// with leds that are off.
+fn crop_canvas<const W: usize, const H: usize>(
+    canvas: &[Vec<LedState>],
+    (ox, oy): (usize, usize),
+) -> Vec<Vec<LedState>> {
+    (oy..oy + H)
+        .map(|y| {
+            (ox..ox + W)
+                .map(|x| {
+                    canvas
+                        .get(y)
+                        .and_then(|row| row.get(x))
+                        .copied()
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Clamp `origin` so a `W`x`H` window at it stays inside `canvas` where possible.
+fn clamp_origin<const W: usize, const H: usize>(
+    canvas: &[Vec<LedState>],
+    (x, y): (usize, usize),
+) -> (usize, usize) {
+    let width = canvas.first().map_or(0, Vec::len);
+    (
+        x.min(width.saturating_sub(W)),
+        y.min(canvas.len().saturating_sub(H)),
+    )
+}
+
+mod test_canvas {
+    #[allow(unused_imports)]
+    use super::{Canvas, Viewport};
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        BlinkInfo, Error, LedColor, LedState, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[allow(dead_code)]
+    const COLUMN_COLORS: [LedColor; 8] = [
+        LedColor::Off,
+        LedColor::Red,
+        LedColor::Green,
+        LedColor::Yellow,
+        LedColor::Blue,
+        LedColor::Magenta,
+        LedColor::Cyan,
+        LedColor::White,
+    ];
+
+    /// A canvas where the color of every led depends on its column.
+    #[allow(dead_code)]
+    fn banner() -> Canvas<20, 10> {
+        let mut canvas = Canvas::default();
+        for row in canvas.iter_mut() {
+            for (x, led) in row.iter_mut().enumerate() {
+                *led = LedState::with_color(COLUMN_COLORS[x % 8]);
+            }
+        }
+        canvas
+    }
+
+    #[allow(dead_code)]
+    fn viewport(canvas: Canvas<20, 10>) -> Viewport {
+        Viewport {
+            canvas: canvas.iter().map(|row| row.to_vec()).collect(),
+            origin: (0, 0),
+        }
+    }
+
+    #[test]
+    fn mid_canvas_crop() {
+        let mut canvas = banner();
+        canvas[5][9] = LedState::with_color(LedColor::White);
+        let mut viewport = viewport(canvas.clone());
+
+        let board = viewport.crop::<7, 7>((6, 2));
+        assert_eq!(viewport.origin, (6, 2));
+        assert_eq!(board.len(), 7);
+        assert_eq!(board[0].len(), 7);
+        assert_eq!(board[0][0], canvas[2][6]);
+        assert_eq!(board[3][3].color, LedColor::White);
+        assert_eq!(board[6][6], canvas[8][12]);
+    }
+
+    #[test]
+    fn clamps_origin() {
+        let mut viewport = viewport(banner());
+
+        let board = viewport.crop::<7, 7>((18, 9));
+        assert_eq!(viewport.origin, (13, 3));
+        // the window ends at the bottom right corner of the canvas
+        assert_eq!(board[6][6], banner()[9][19]);
+        assert_eq!(board[0][6], banner()[3][19]);
+
+        viewport.crop::<7, 7>((usize::MAX, usize::MAX));
+        assert_eq!(viewport.origin, (13, 3));
+    }
+
+    #[test]
+    fn small_canvas() {
+        let mut viewport = Viewport {
+            canvas: vec![vec![LedState::with_color(LedColor::Red); 3]; 2],
+            origin: (0, 0),
+        };
+        let board = viewport.crop::<7, 7>((4, 4));
+        assert_eq!(viewport.origin, (0, 0));
+        assert_eq!(board[1][2].color, LedColor::Red);
+        assert_eq!(board[2][3], LedState::default());
+    }
+
+    #[test]
+    fn empty_canvas() {
+        let mut viewport = Viewport {
+            canvas: Vec::new(),
+            origin: (0, 0),
+        };
+        let board = viewport.crop::<7, 7>((2, 2));
+        assert_eq!(viewport.origin, (0, 0));
+        assert_eq!(board, vec![vec![LedState::default(); 7]; 7]);
+    }
+
+    #[test]
+    fn pans_display() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(disp.set_viewport(1, 1), Err(Error::Uninitiated)));
+
+        disp.set_canvas(&banner()).unwrap();
+        assert_eq!(disp.set_viewport(4, 20).unwrap(), (4, 3));
+        // the viewport is kept when the canvas changes
+        disp.set_canvas(&banner()).unwrap();
+
+        let sent: Vec<Instruction> = rx.try_iter().collect();
+        assert_eq!(sent.len(), 3);
+        for instruction in &sent[1..] {
+            match instruction {
+                Instruction::Sync(SyncType::All(board)) => {
+                    assert_eq!(board[0][0].color, COLUMN_COLORS[4])
+                }
+                other => panic!("unexpected instruction: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_blink() {
+        let (mut disp, rx) = interface();
+        let mut canvas = banner();
+        canvas[9][19].blink = Some(BlinkInfo {
+            dur: Duration::from_millis(300),
+            int: Duration::from_millis(200),
+        });
+        assert!(matches!(disp.set_canvas(&canvas), Err(Error::InvalidBlink)));
+        assert!(rx.try_recv().is_err());
+    }
+}
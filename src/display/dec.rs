@@ -1,20 +1,24 @@
-use rppal::gpio::{Gpio, Level, OutputPin};
+use super::OutputPin;
+use crate::{error, spin_wait, Timing};
 
-use crate::{
-    error,
-    pins::{A0PinNr, A1PinNr, A2PinNr, E1PinNr, LEPinNr},
-    spin_wait, PSWT,
-};
+/// Pwm frequency of the decoder output enable pin.
+const E1_PWM_FREQUENCY: f64 = 2_400.0;
+/// Pwm duty cycle of an active low decoder output enable pin.
+const E1_PWM_DUTY: f64 = 0.90;
 
 #[derive(Debug)]
-pub(super) struct Dec {
+pub(super) struct Dec<P> {
     // a: [OutputPin; 3],
-    a0: OutputPin,
-    a1: OutputPin,
-    a2: OutputPin,
-    le: OutputPin,
-    e1: OutputPin,
+    a0: P,
+    a1: P,
+    a2: P,
+    a3: Option<P>, // selects the second of two cascaded decoders
+    le: P,
+    e1: P,
+    e1_active_low: bool, // whether the outputs are enabled while e1 is low
     output: DecOutput,
+    bank: usize, // index of the decoder that is selected
+    timing: Timing,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,55 +33,57 @@ enum DecOutput {
     Y7 = 7,
 }
 
-// TODO make use of LE pin
-impl Dec {
+impl<P: OutputPin> Dec<P> {
+    /// create new decoder instance
+    ///
+    /// pin order: a0, a1, a2, a3 (only for cascaded decoders), latch enable, output enable
+    ///
+    /// The outputs are disabled before anything else and stay disabled until
+    /// [Dec::output_enable] is called. `e1_active_low` is the polarity of the output enable
+    /// pin, active low on a 74HC138.
     pub(super) fn new(
-        pins: (A0PinNr, A1PinNr, A2PinNr, LEPinNr, E1PinNr),
-    ) -> error::DisplayResult<Self> {
+        pins: (P, P, P, Option<P>, P, P),
+        timing: Timing,
+        e1_active_low: bool,
+    ) -> Self {
+        let (a0, a1, a2, a3, le, mut e1) = pins;
+        e1.set_level(e1_active_low);
+
         let mut dec = Self {
-            // a: [
-            //     Gpio::new()?.get(pins.0)?.into_output_low(),
-            //     Gpio::new()?.get(pins.1)?.into_output_low(),
-            //     Gpio::new()?.get(pins.2)?.into_output_low(),
-            // ],
-            a0: Gpio::new()?.get(pins.0)?.into_output(),
-            a1: Gpio::new()?.get(pins.1)?.into_output(),
-            a2: Gpio::new()?.get(pins.2)?.into_output(),
-            le: Gpio::new()?.get(pins.3)?.into_output(),
-            e1: Gpio::new()?.get(pins.4)?.into_output(),
+            a0,
+            a1,
+            a2,
+            a3,
+            le,
+            e1,
+            e1_active_low,
             output: DecOutput::default(),
+            bank: 0,
+            timing,
         };
 
         dec.a0.set_low();
         dec.a1.set_low();
         dec.a2.set_low();
+        if let Some(a3) = &mut dec.a3 {
+            a3.set_low();
+        }
         dec.le.set_low();
-        dec.e1.set_low();
 
-        dec.e1.set_pwm_frequency(2_400.0, 0.90)?;
-
-        Ok(dec)
+        dec
     }
 
     /// Update the decoder output.
     ///
-    /// This function takes at least `PinSwitchTime`
+    /// This function takes at least the decoder settle time.
     fn update(&mut self) {
-        self.a0.write(match self.output as u8 & 0b1 {
-            0 => Level::Low,
-            1 => Level::High,
-            _ => unreachable!(),
-        });
-        self.a1.write(match (self.output as u8 >> 1) & 0b1 {
-            0 => Level::Low,
-            1 => Level::High,
-            _ => unreachable!(),
-        });
-        self.a2.write(match (self.output as u8 >> 2) & 0b1 {
-            0 => Level::Low,
-            1 => Level::High,
-            _ => unreachable!(),
-        });
+        let [a0, a1, a2] = self.output.levels();
+        self.a0.set_level(a0);
+        self.a1.set_level(a1);
+        self.a2.set_level(a2);
+        if let Some(a3) = &mut self.a3 {
+            a3.set_level(self.bank == 1);
+        }
 
         // for b in 0..3 {
         //     match self.output as usize >> b & 1 {
@@ -87,48 +93,86 @@ impl Dec {
         //     }
         // }
 
-        spin_wait(PSWT);
+        spin_wait(self.timing.decoder_settle);
     }
 
-    /// Set the decoder output to a specific position.
+    /// Set the decoder output to a specific position, where positions 8 to 15 are the
+    /// outputs of the second decoder.
     ///
-    /// This function takes at least `PinSwitchTime`.
+    /// This function takes at least the decoder settle time.
     pub(super) fn set(&mut self, num: usize) {
-        self.output = DecOutput::from(num);
+        self.output = DecOutput::from(num % 8);
+        self.bank = num / 8;
         self.update();
     }
 
     /// Lock the decoder output.
     ///
+    /// While locked the outputs keep the address they had when locking, so the address pins
+    /// can be changed without other columns lighting up in between.
+    ///
     /// This function takes at least `PinSwitchTime`.
     pub(super) fn latch_on(&mut self) {
         self.le.set_high();
-        spin_wait(PSWT);
+        spin_wait(self.timing.pin_switch);
     }
 
-    /// Unlock the decoder output.
+    /// Unlock the decoder output, the outputs follow the address pins again.
     ///
     /// This function takes at least `PinSwitchTime`.
     pub(super) fn latch_off(&mut self) {
         self.le.set_low();
-        spin_wait(PSWT);
+        spin_wait(self.timing.pin_switch);
+    }
+
+    /// Enable the decoder outputs.
+    ///
+    /// The output enable pin is driven with pwm to dim the display.
+    pub(super) fn output_enable(&mut self) -> error::DisplayResult<()> {
+        // the outputs are enabled for the same share of the time with either polarity
+        let duty = match self.e1_active_low {
+            true => E1_PWM_DUTY,
+            false => 1.0 - E1_PWM_DUTY,
+        };
+        self.e1.set_pwm_frequency(E1_PWM_FREQUENCY, duty)?;
+        Ok(())
+    }
+
+    /// Disable the decoder outputs, turning every column off.
+    ///
+    /// This function takes at least `PinSwitchTime`.
+    pub(super) fn output_disable(&mut self) -> error::DisplayResult<()> {
+        self.e1.clear_pwm()?;
+        self.e1.set_level(self.e1_active_low);
+        spin_wait(self.timing.pin_switch);
+        Ok(())
     }
 }
 
-impl std::ops::AddAssign<usize> for Dec {
+impl<P: OutputPin> std::ops::AddAssign<usize> for Dec<P> {
     fn add_assign(&mut self, rhs: usize) {
         self.output += rhs;
         self.update();
     }
 }
 
-impl std::ops::SubAssign<usize> for Dec {
+impl<P: OutputPin> std::ops::SubAssign<usize> for Dec<P> {
     fn sub_assign(&mut self, rhs: usize) {
         self.output -= rhs;
         self.update();
     }
 }
 
+impl DecOutput {
+    /// The levels of the address pins `a0`, `a1` and `a2` that select this output,
+    /// where `true` is high.
+    fn levels(self) -> [bool; 3] {
+        let bit = |shift: u8| (self as u8 >> shift) & 0b1 == 1;
+        [bit(0), bit(1), bit(2)]
+    }
+}
+
+/// The output of a single decoder, larger numbers are clamped to its last output.
 impl From<usize> for DecOutput {
     fn from(num: usize) -> Self {
         match num.clamp(0, 7) {
@@ -264,3 +308,161 @@ mod test_add_sub {
         assert_eq!(DecOutput::Y1 - 10, DecOutput::Y7);
     }
 }
+
+mod test_levels {
+    #[allow(unused_imports)]
+    use super::DecOutput;
+
+    #[test]
+    fn binary_encoding() {
+        for num in 0..8 {
+            let expected = [num & 0b1, (num >> 1) & 0b1, (num >> 2) & 0b1].map(|bit| bit == 1);
+            assert_eq!(DecOutput::from(num).levels(), expected, "Y{num}");
+        }
+    }
+}
+
+mod test_banks {
+    #[allow(unused_imports)]
+    use super::Dec;
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::Trace;
+
+    #[test]
+    fn sixteen_rows() {
+        let trace = Trace::default();
+        let mut dec = Dec::new(
+            (
+                trace.pin(Trace::DEC_A0),
+                trace.pin(Trace::DEC_A1),
+                trace.pin(Trace::DEC_A2),
+                Some(trace.pin(Trace::DEC_A3)),
+                trace.pin(Trace::DEC_LE),
+                trace.pin(Trace::DEC_E1),
+            ),
+            Default::default(),
+            true,
+        );
+
+        for num in 0..16 {
+            dec.set(num);
+            let levels = (0..4)
+                .map(|bit| (trace.level(Trace::DEC_A0 + bit) == Some(true)) as usize)
+                .enumerate()
+                .map(|(bit, high)| high << bit)
+                .sum::<usize>();
+            assert_eq!(levels, num);
+        }
+    }
+}
+
+mod test_latch {
+    #[allow(unused_imports)]
+    use super::Dec;
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::Trace;
+
+    /// The output selected by a latching decoder such as the 74HC137 driven as in `trace`.
+    ///
+    /// While latch enable is high the output keeps the address it had when it was raised.
+    #[allow(dead_code)]
+    fn latched_output(trace: &Trace) -> usize {
+        let (mut address, mut latched, mut output) = (0, false, 0);
+        for (pin, high) in trace.events() {
+            match pin {
+                Trace::DEC_A0..=Trace::DEC_A2 => {
+                    let bit = pin - Trace::DEC_A0;
+                    address = address & !(1 << bit) | (high as usize) << bit;
+                }
+                Trace::DEC_LE => latched = high,
+                _ => continue,
+            }
+            if !latched {
+                output = address;
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn latching_blocks_output_changes() {
+        let trace = Trace::default();
+        let mut dec = Dec::new(
+            (
+                trace.pin(Trace::DEC_A0),
+                trace.pin(Trace::DEC_A1),
+                trace.pin(Trace::DEC_A2),
+                None,
+                trace.pin(Trace::DEC_LE),
+                trace.pin(Trace::DEC_E1),
+            ),
+            Default::default(),
+            true,
+        );
+
+        dec.set(2);
+        assert_eq!(latched_output(&trace), 2);
+
+        dec.latch_on();
+        dec.set(5);
+        assert_eq!(latched_output(&trace), 2);
+        dec.latch_off();
+        assert_eq!(latched_output(&trace), 5);
+    }
+}
+
+mod test_output_enable {
+    #[allow(unused_imports)]
+    use super::Dec;
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::Trace;
+
+    #[test]
+    fn disable_drives_e1_high() {
+        let trace = Trace::default();
+        let mut dec = Dec::new(
+            (
+                trace.pin(Trace::DEC_A0),
+                trace.pin(Trace::DEC_A1),
+                trace.pin(Trace::DEC_A2),
+                None,
+                trace.pin(Trace::DEC_LE),
+                trace.pin(Trace::DEC_E1),
+            ),
+            Default::default(),
+            true,
+        );
+        // disabled from the start
+        assert_eq!(trace.levels(Trace::DEC_E1), [true]);
+
+        dec.output_enable().unwrap();
+        assert_eq!(trace.level(Trace::DEC_E1), Some(false));
+
+        trace.clear();
+        dec.output_disable().unwrap();
+        assert_eq!(trace.levels(Trace::DEC_E1), [true]);
+    }
+
+    #[test]
+    fn active_high_e1() {
+        let trace = Trace::default();
+        let mut dec = Dec::new(
+            (
+                trace.pin(Trace::DEC_A0),
+                trace.pin(Trace::DEC_A1),
+                trace.pin(Trace::DEC_A2),
+                None,
+                trace.pin(Trace::DEC_LE),
+                trace.pin(Trace::DEC_E1),
+            ),
+            Default::default(),
+            false,
+        );
+        // disabled from the start
+        assert_eq!(trace.levels(Trace::DEC_E1), [false]);
+
+        trace.clear();
+        dec.output_disable().unwrap();
+        assert_eq!(trace.levels(Trace::DEC_E1), [false]);
+    }
+}
@@ -1,7 +1,8 @@
-// use rppal::{gpio, gpio::Gpio, gpio::OutputPin};
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+use crate::{display::ConfigPin, GpioRetry, PinConfig};
 use crate::{
-    display::{Dec, Rotation, ShiftReg},
-    error, spin_wait, PinConfig, Sync, SyncType,
+    display::{Axis, Dec, Direction, OutputPin, Rotation, ShiftReg},
+    error, spin_wait, DisplayPins, PanelConfig, Sync, SyncType, Timing,
 };
 use std::{
     str::FromStr,
@@ -10,17 +11,34 @@ use std::{
 
 #[derive(Debug)]
 #[allow(dead_code)]
-pub(super) struct Display<const W: usize, const H: usize> {
-    row: ShiftReg,
-    column: Dec,
+pub(super) struct Display<const W: usize, const H: usize, P> {
+    row: ShiftReg<P>,
+    column: Dec<P>,
     display: [[LedState; W]; H],
+    background: LedColor, // color shown by leds that are off
     // global_dim: f64, // global pwm
     tpl: Duration, // time per led in seconds, based on refresh rate
+    frame: u8,     // wrapping count of runs, used to dim leds
+    palette: [LedColor; 1 << COLOR_BITS], // color rendered for every color, by encoding
+    shifted: Option<[u8; W]>, // encoded colors in the shift register, if known
+    gamma_lut: [u8; 256], // share of runs a led is on for, by brightness
+    blink_epoch: u128, // microseconds since the unix epoch every blink starts from
+    orientation: Orientation, // how the panel is mounted
+    #[cfg(feature = "metrics")]
+    overruns: u32, // rows shown after their deadline since the overruns were last taken
 }
 
+/// Gamma of the brightness of dimmed leds, see
+/// [DisplayInterface::set_gamma](crate::DisplayInterface::set_gamma).
+pub const DEFAULT_GAMMA: f64 = 2.2;
+
+/// Periods of the output enable pwm every column is shown for while the display is dimmed.
+const OE_PWM_PERIODS: f64 = 8.0;
+
 /// Colors that can be displayed
 // #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LedColor {
     /// No color. This is also the default.
     Off = 0,
@@ -40,50 +58,260 @@ pub enum LedColor {
     White = 7,
 }
 
+/// Order in which the red, green and blue bits of a led are shifted into the shift register.
+///
+/// The bit shifted in first ends up on the last output of a led, so with the default
+/// [ColorOrder::Rgb] the outputs of every led are wired blue, green, red. Panels that have
+/// the color lines wired in another order can use the matching variant instead of being
+/// rewired.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// The bits of `color` in the order they are shifted in.
+    pub(super) fn bits(self, color: LedColor) -> [bool; COLOR_BITS] {
+        // index of the bit of every color in the encoding of a led color
+        let (r, g, b) = (0, 1, 2);
+        let order = match self {
+            Self::Rgb => [r, g, b],
+            Self::Rbg => [r, b, g],
+            Self::Grb => [g, r, b],
+            Self::Gbr => [g, b, r],
+            Self::Brg => [b, r, g],
+            Self::Bgr => [b, g, r],
+        };
+        order.map(|c_bit| color as usize >> c_bit & 1 != 0)
+    }
+}
+
+/// How the panel is mounted, mapping the coordinates used by syncs and animations to the
+/// leds that light up.
+///
+/// The coordinates are transposed first and flipped after, so `(0, 0)` is the top left led
+/// of a panel that is not flipped. Snapshots keep the coordinates as they were synced.
+/// A transposed panel has to be square.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Orientation {
+    /// Mirror the panel horizontally, `x` counts from the right.
+    pub flip_x: bool,
+    /// Mirror the panel vertically, `y` counts from the bottom.
+    pub flip_y: bool,
+    /// Swap `x` and `y`.
+    pub transpose: bool,
+}
+
+impl Orientation {
+    /// The coordinates on a `W`x`H` panel of the led at `x`, `y` on the hardware.
+    fn logical<const W: usize, const H: usize>(self, x: usize, y: usize) -> (usize, usize) {
+        let x = match self.flip_x {
+            true => W - 1 - x,
+            false => x,
+        };
+        let y = match self.flip_y {
+            true => H - 1 - y,
+            false => y,
+        };
+        match self.transpose {
+            true => (y, x),
+            false => (x, y),
+        }
+    }
+}
+
+/// Number of bits used to encode a [LedColor], one for each of red, green and blue.
+pub const COLOR_BITS: usize = 3;
+
+/// Every color that can be displayed, in order of their encoding.
+pub(super) const SUPPORTED_COLORS: [LedColor; 1 << COLOR_BITS] = [
+    LedColor::Off,
+    LedColor::Red,
+    LedColor::Green,
+    LedColor::Yellow,
+    LedColor::Blue,
+    LedColor::Magenta,
+    LedColor::Cyan,
+    LedColor::White,
+];
+
+/// Palette for red-green color blindness (deuteranopia and protanopia), for use with
+/// [DisplayInterface::set_palette_map](crate::DisplayInterface::set_palette_map).
+///
+/// Red is swapped with yellow and green with blue, so red and green are shown as the
+/// yellow and blue pair.
+pub const RED_GREEN_SAFE_PALETTE: [LedColor; 1 << COLOR_BITS] = [
+    LedColor::Off,
+    LedColor::Yellow,
+    LedColor::Blue,
+    LedColor::Red,
+    LedColor::Green,
+    LedColor::Magenta,
+    LedColor::Cyan,
+    LedColor::White,
+];
+
+/// Palette for blue-yellow color blindness (tritanopia), for use with
+/// [DisplayInterface::set_palette_map](crate::DisplayInterface::set_palette_map).
+///
+/// Blue is swapped with magenta, so blue can be told apart from green.
+pub const BLUE_YELLOW_SAFE_PALETTE: [LedColor; 1 << COLOR_BITS] = [
+    LedColor::Off,
+    LedColor::Red,
+    LedColor::Green,
+    LedColor::Yellow,
+    LedColor::Magenta,
+    LedColor::Blue,
+    LedColor::Cyan,
+    LedColor::White,
+];
+
 // ! this is a very crude solution to handeling animations
 // ! it's only meant as a quick way to implement blinking
 /// Blink duration and interval.
-#[derive(Debug, Clone, Copy)]
+///
+/// A blinking led is on for the first `dur` of every `int`, counted from a blink epoch
+/// shared by every led. Leds with the same interval therefore always blink together, and
+/// leds with different intervals all start a blink at the epoch. The epoch is the unix
+/// epoch until it is moved with
+/// [DisplayInterface::sync_blink](crate::DisplayInterface::sync_blink).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlinkInfo {
     /// The time the led is on. PWM equivalent: ton
+    #[cfg_attr(feature = "serde", serde(with = "super::duration_ms"))]
     pub dur: Duration,
     /// The time of on blink period. PWM equivalent: t
+    #[cfg_attr(feature = "serde", serde(with = "super::duration_ms"))]
     pub int: Duration,
 }
 
 /// Led state, contains color, blink duration and blink interval.
-#[derive(Debug, Clone, Copy)]
+///
+/// ```
+/// use c4_display::{LedColor, LedState};
+///
+/// let led = LedState {
+///     color: LedColor::Red,
+///     ..Default::default()
+/// };
+/// assert_eq!(led.brightness, u8::MAX);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LedState {
     /// The color of the led.
     pub color: LedColor,
     /// The blink information of the led.
     pub blink: Option<BlinkInfo>,
+    /// The brightness of the led, where `255` is full brightness.
+    ///
+    /// Dimmed leds are turned off on a share of the display refreshes. Leds built with
+    /// `..Default::default()` are at full brightness.
+    #[cfg_attr(feature = "serde", serde(default = "full_brightness"))]
+    pub brightness: u8,
 }
 
-impl<const W: usize, const H: usize> Display<W, H> {
-    /// Set up a new display instance.
-    pub(super) fn init(refresh: f64, pins: PinConfig) -> error::DisplayResult<Self> {
-        let tpl = Duration::from_secs_f64(1.0 / (refresh * W as f64 * H as f64));
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+impl<const W: usize, const H: usize> Display<W, H, ConfigPin> {
+    /// Set up a new display instance on the gpio pins of a Raspberry Pi, or in the terminal
+    /// with the `simulator` feature.
+    ///
+    /// Acquiring the gpio pins is retried according to `retry`.
+    pub(super) fn init(
+        refresh: f64,
+        pins: PinConfig,
+        retry: &GpioRetry,
+        timing: Timing,
+        panel: PanelConfig,
+    ) -> error::DisplayResult<Self> {
+        #[cfg(feature = "simulator")]
+        super::simulator::clear_screen();
+
+        let acquired = with_retry(retry, || pins.acquire(&panel))?;
+        Self::with_pins(refresh, acquired, timing, panel)
+    }
+}
+
+impl<const W: usize, const H: usize, P: OutputPin> Display<W, H, P> {
+    /// Set up a new display instance driven through the given pins, waiting for them as
+    /// given by `timing`, for a panel wired and mounted as given by `panel`.
+    pub(super) fn with_pins(
+        refresh: f64,
+        pins: DisplayPins<P>,
+        timing: Timing,
+        panel: PanelConfig,
+    ) -> error::DisplayResult<Self> {
+        // every decoder selects one of 8 rows, and a fourth address pin selects the decoder
+        let rows = match pins.dec_a3 {
+            Some(_) => 16,
+            None => 8,
+        };
+        if H > rows {
+            log::error!("{H} rows need more decoder address pins");
+            return Err(error::Error::InvalidDim);
+        }
+        if panel.orientation.transpose && W != H {
+            log::error!("Only a square panel can be transposed");
+            return Err(error::Error::InvalidDim);
+        }
+
+        let tpl = time_per_led::<W, H>(refresh);
         #[cfg(feature = "disp_debug")]
         log::debug!("time per led: {}", tpl.as_secs_f64());
 
-        let disp = Self {
-            row: ShiftReg::new((
-                pins.sr_serin,
-                pins.sr_srclk,
-                pins.sr_rclk,
-                pins.sr_srclr,
-                pins.sr_oe,
-            ))?,
-            column: Dec::new((
+        // the decoder is set up first so its outputs are disabled while the shift register
+        // is cleared, and only enabled once both are in a known state
+        let mut column = Dec::new(
+            (
                 pins.dec_a0,
                 pins.dec_a1,
                 pins.dec_a2,
+                pins.dec_a3,
                 pins.dec_le,
                 pins.dec_e1,
-            ))?,
+            ),
+            timing,
+            panel.e1_active_low,
+        );
+        let row = ShiftReg::new(
+            (
+                pins.sr_serin,
+                pins.sr_srclk,
+                pins.sr_rclk,
+                pins.sr_srclr,
+                pins.sr_oe,
+            ),
+            timing.pin_switch,
+            oe_pwm_frequency::<H>(refresh),
+            panel.color_order,
+            (panel.oe_active_low, panel.srclr_active_low),
+        );
+        column.output_enable()?;
+
+        let disp = Self {
+            row,
+            column,
             display: [[LedState::default(); W]; H],
+            background: LedColor::default(),
             tpl,
+            frame: 0,
+            palette: SUPPORTED_COLORS,
+            shifted: None,
+            gamma_lut: gamma_lut(DEFAULT_GAMMA),
+            blink_epoch: 0,
+            orientation: panel.orientation,
+            #[cfg(feature = "metrics")]
+            overruns: 0,
         };
 
         Ok(disp)
@@ -93,32 +321,41 @@ impl<const W: usize, const H: usize> Display<W, H> {
     pub(super) fn run_once(&mut self, start_time: Instant) {
         #[cfg(feature = "disp_debug")]
         log::debug!("Starting run");
-        for (c_index, row) in self.display.iter().enumerate() {
-            self.row.clear(); // empty the shift registers
 
-            // shift everything into the register
-            for led in row {
-                let now = SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_micros();
-
-                // blink led
-                self.row.shift_color(match led.blink {
-                    Some(blink) if now % blink.int.as_micros() > blink.dur.as_micros() => {
-                        &LedColor::Off
-                    }
-                    _ => &led.color,
-                });
+        // the blink phase is read once per run instead of once per led,
+        // which is one clock read per frame instead of W * H.
+        // a run lasts 1/refresh seconds, which is far below any sensible blink interval.
+        let now = unix_micros();
+
+        self.frame = self.frame.wrapping_add(1);
+
+        let board = self.hardware_board();
+        for (c_index, row) in board.iter().enumerate() {
+            // every column shares the shift registers, so a row only has to be shifted in
+            // again if it shows other colors than the row before it. the shown colors are
+            // compared rather than the led states, which covers blinking and dimmed leds.
+            let colors = row.map(|led| self.shown_color(&led, now));
+            let encoded = colors.map(|color| color as u8);
+            if self.shifted != Some(encoded) {
+                self.row.clear(); // empty the shift registers
 
-                // adaptive sleep
-                // let acc_wait_time =
-                //     self.tpl * (r_index + 1) as u32 + (self.tpl * (c_index * W) as u32);
-                // spin_wait(acc_wait_time - start_time.elapsed().min(acc_wait_time));
+                // shift everything into the register. the outputs only change on the push
+                // below, so the previous column stays lit while shifting and no led has to
+                // be paced on its own.
+                for color in &colors {
+                    self.row.shift_color(color);
+                }
+                self.shifted = Some(encoded);
             }
 
-            // disable row during switching to prevent unwanted leds from turning on
-            self.row.disable();
+            // disable row during switching to prevent unwanted leds from turning on.
+            // a dimmed row keeps its pwm running, so the column is turned off instead
+            let blank_column = self.row.disable_for_switch();
+            if blank_column {
+                if let Err(e) = self.column.output_disable() {
+                    log::warn!("Failed to disable decoder outputs: {e:?}");
+                }
+            }
             // lock column output
             self.column.latch_on();
             // set column
@@ -127,116 +364,397 @@ impl<const W: usize, const H: usize> Display<W, H> {
             self.column.latch_off();
             // update register
             self.row.push();
+            if blank_column {
+                if let Err(e) = self.column.output_enable() {
+                    log::warn!("Failed to enable decoder outputs: {e:?}");
+                }
+            }
             // enable row
             self.row.enable();
+            crate::breakpoint_sbs!("showing row {c_index}");
 
-            let wait_time = self.tpl * W as u32 * (c_index + 1) as u32; //? W or H?
-            let subbed_wait_time = wait_time
-                .checked_sub(start_time.elapsed())
-                .unwrap_or(Duration::ZERO);
+            let elapsed = start_time.elapsed();
+            // counted rather than logged, logging every row would slow the loop down further
+            #[cfg(feature = "metrics")]
+            if elapsed > self.column_deadline(c_index) {
+                self.overruns += 1;
+            }
+            let subbed_wait_time = self.column_wait(c_index, elapsed);
             #[cfg(feature = "disp_debug")]
-            log::debug!("{wait_time:?}, {subbed_wait_time:?}");
+            log::debug!("{:?}, {subbed_wait_time:?}", self.column_deadline(c_index));
             spin_wait(subbed_wait_time);
         }
+
+        #[cfg(feature = "simulator")]
+        super::simulator::render(
+            &self
+                .display
+                .map(|row| row.map(|led| self.shown_color(&led, now))),
+        );
+    }
+
+    /// The number of rows shown after their deadline since this was last called.
+    #[cfg(feature = "metrics")]
+    pub(super) fn take_overruns(&mut self) -> u32 {
+        std::mem::take(&mut self.overruns)
+    }
+
+    /// Time since the start of a run at which column `c_index` has been shown long enough.
+    ///
+    /// Every column is shown until a deadline relative to the start of the run, so the time
+    /// spent shifting is taken from the column before it rather than adding up, and every
+    /// column is lit for an equal share of the run.
+    fn column_deadline(&self, c_index: usize) -> Duration {
+        self.tpl * W as u32 * (c_index + 1) as u32
+    }
+
+    /// Time column `c_index` still has to be shown for, `elapsed` after the start of the run.
+    fn column_wait(&self, c_index: usize, elapsed: Duration) -> Duration {
+        self.column_deadline(c_index).saturating_sub(elapsed)
+    }
+
+    /// The board as it is wired, with every led at its position on the hardware.
+    fn hardware_board(&self) -> [[LedState; W]; H] {
+        if self.orientation == Orientation::default() {
+            return self.display;
+        }
+        std::array::from_fn(|y| {
+            std::array::from_fn(|x| {
+                let (lx, ly) = self.orientation.logical::<W, H>(x, y);
+                self.display[ly][lx]
+            })
+        })
+    }
+
+    /// The colors every led shows right now.
+    #[cfg(feature = "record")]
+    pub(super) fn shown_colors(&self) -> Vec<Vec<LedColor>> {
+        let now = unix_micros();
+        self.display
+            .iter()
+            .map(|row| row.iter().map(|led| self.shown_color(led, now)).collect())
+            .collect()
+    }
+
+    /// The color `led` shows at `now` microseconds since the unix epoch.
+    fn shown_color(&self, led: &LedState, now: u128) -> LedColor {
+        let since_epoch = now.saturating_sub(self.blink_epoch);
+        // blink led
+        let color = match led.blink {
+            Some(blink) if since_epoch % blink.int.as_micros() > blink.dur.as_micros() => {
+                LedColor::Off
+            }
+            // dim led
+            _ if !dither_on(self.gamma_lut[led.brightness as usize], self.frame) => LedColor::Off,
+            _ => led.color,
+        };
+
+        // leds that are off show the background
+        let color = match color {
+            LedColor::Off => self.background,
+            _ => color,
+        };
+
+        self.palette[color as usize]
     }
 
     /// Update the colors of the leds.
+    ///
+    /// The sync is expected to be validated by the interface beforehand.
     pub(super) fn sync(&mut self, sync_type: SyncType) {
-        match sync_type {
-            SyncType::Single(sync) => {
+        sync_board(&mut self.display, sync_type);
+    }
+
+    /// Returns a copy of the current state of every led.
+    pub(super) fn board(&self) -> Vec<Vec<LedState>> {
+        self.display.iter().map(|row| row.to_vec()).collect()
+    }
+
+    /// Render every color as the color at its encoding in `palette`.
+    ///
+    /// The led states are kept as they are, only the shown colors change.
+    pub(super) fn set_palette_map(&mut self, palette: [LedColor; 1 << COLOR_BITS]) {
+        self.palette = palette;
+    }
+
+    /// Set the color shown by all leds that are off.
+    pub(super) fn set_background(&mut self, color: LedColor) {
+        self.background = color;
+    }
+
+    /// The color shown by all leds that are off.
+    #[allow(dead_code)]
+    pub(super) fn background(&self) -> LedColor {
+        self.background
+    }
+
+    pub(super) fn clear_row(&mut self) {
+        self.row.clear();
+        self.row.push();
+        self.shifted = None;
+    }
+
+    /// Turn off the column shown last, which a run leaves lit until the next one.
+    pub(super) fn disable_row(&mut self) {
+        self.row.disable();
+    }
+
+    /// Clear the shift register and turn the panel dark.
+    pub(super) fn shutdown(&mut self) -> error::DisplayResult<()> {
+        self.clear_row();
+        self.blank()
+    }
+
+    /// Start every blink over at `epoch` microseconds since the unix epoch, or now if `None`.
+    pub(super) fn set_blink_epoch(&mut self, epoch: Option<u128>) {
+        self.blink_epoch = epoch.unwrap_or_else(unix_micros);
+    }
+
+    /// Correct the brightness of dimmed leds with `gamma` from the next run on.
+    pub(super) fn set_gamma(&mut self, gamma: f64) {
+        self.gamma_lut = gamma_lut(gamma);
+    }
+
+    /// Run at `refresh` Hz from the next run on.
+    pub(super) fn set_refresh(&mut self, refresh: f64) {
+        self.tpl = time_per_led::<W, H>(refresh);
+        #[cfg(feature = "disp_debug")]
+        log::debug!("time per led: {}", self.tpl.as_secs_f64());
+        if let Err(e) = self.row.set_pwm_frequency(oe_pwm_frequency::<H>(refresh)) {
+            log::warn!("Failed to update output enable pwm: {e:?}");
+        }
+    }
+
+    /// Time every led is shown for during a run.
+    #[allow(dead_code)]
+    pub(super) fn time_per_led(&self) -> Duration {
+        self.tpl
+    }
+
+    /// Set the brightness of all leds, from `0.0` to `1.0`.
+    pub(super) fn set_brightness(&mut self, level: f64) -> error::DisplayResult<()> {
+        self.row.set_brightness(level)
+    }
+
+    /// Turn the entire panel dark by disabling both the shift register and decoder outputs.
+    ///
+    /// This also restores full brightness.
+    pub(super) fn blank(&mut self) -> error::DisplayResult<()> {
+        self.row.set_brightness(1.0)?;
+        self.row.disable();
+        self.column.output_disable()
+    }
+}
+
+/// Time every led is shown for during a run, so that all leds are shown `refresh` times a
+/// second.
+fn time_per_led<const W: usize, const H: usize>(refresh: f64) -> Duration {
+    Duration::from_secs_f64(1.0 / (refresh * W as f64 * H as f64))
+}
+
+/// Frequency of the output enable pwm while dimmed, so every column is shown for
+/// [OE_PWM_PERIODS] whole periods of it at `refresh` Hz.
+fn oe_pwm_frequency<const H: usize>(refresh: f64) -> f64 {
+    OE_PWM_PERIODS * refresh * H as f64
+}
+
+/// The share of every 256 runs a led is on for, by its brightness, so the perceived
+/// brightness follows the brightness of the led for a display with the given `gamma`.
+///
+/// Full brightness stays full, so leds that are not dimmed are always on.
+fn gamma_lut(gamma: f64) -> [u8; 256] {
+    let mut lut = [0; 256];
+    for (level, on) in lut.iter_mut().enumerate() {
+        *on = (u8::MAX as f64 * (level as f64 / u8::MAX as f64).powf(gamma)).round() as u8;
+    }
+    lut
+}
+
+/// Microseconds since the unix epoch.
+fn unix_micros() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_micros()
+}
+
+/// Check if a led with the given `brightness` is on during run number `frame`.
+///
+/// The runs a dimmed led is on for are spread out evenly over every 256 runs
+/// by comparing against the bit reversed run number.
+fn dither_on(brightness: u8, frame: u8) -> bool {
+    brightness == u8::MAX || frame.reverse_bits() < brightness
+}
+
+/// Full brightness, used as serde default for [LedState::brightness].
+#[cfg(feature = "serde")]
+fn full_brightness() -> u8 {
+    u8::MAX
+}
+
+/// Apply `sync_type` to the led states in `display`.
+fn sync_board<const W: usize, const H: usize>(
+    display: &mut [[LedState; W]; H],
+    sync_type: SyncType,
+) {
+    match sync_type {
+        SyncType::Single(sync) => {
+            let Sync { x, y, state } = sync;
+            display[y][x] = state;
+        }
+        SyncType::Multi(sync_vec) => {
+            for sync in sync_vec {
                 let Sync { x, y, state } = sync;
-                match state.blink {
-                    Some(blink) if blink.dur > blink.int => panic!(
-                        "Blink duration larger than blink interval\nduration: {:?}, interval: {:?}",
-                        blink.dur, blink.int
-                    ),
-                    _ => self.display[y][x] = state,
-                }
+                display[y][x] = state;
             }
-            SyncType::Multi(sync_vec) => {
-                for sync in sync_vec {
-                    let Sync { x, y, state } = sync;
-                    match state.blink {
-                        Some(blink) if blink.dur > blink.int => panic!(
-                            "Blink duration larger than blink interval\nduration: {:?}, interval: {:?}",
-                            blink.dur, blink.int
-                        ),
-                        _ => self.display[y][x] = state,
-                    }
+        }
+        SyncType::All(board) => {
+            assert_eq!(H, board.len()); // panic if the dimensions are unexpected
+            for (y, height) in board.iter().enumerate() {
+                assert_eq!(W, height.len()); // panic if the dimensions are unexpected
+                for (x, led) in height.iter().enumerate() {
+                    display[y][x] = *led;
                 }
             }
-            SyncType::All(board) => {
-                assert_eq!(H, board.len()); // panic if the dimensions are unexpected
-                for (y, height) in board.iter().enumerate() {
-                    assert_eq!(W, height.len()); // panic if the dimensions are unexpected
-                    for (x, led) in height.iter().enumerate() {
-                        match led.blink {
-                            Some(blink) if blink.dur > blink.int => panic!(
-                                "Blink duration larger than blink interval\nduration: {:?}, interval: {:?}",
-                                blink.dur, blink.int
-                            ),
-                            _ => self.display[y][x] = *led,
-                        }
+        }
+        SyncType::Fill(state) => *display = [[state; W]; H],
+        SyncType::Clear => *display = [[LedState::default(); W]; H],
+        SyncType::Flip(axis) => match axis {
+            Axis::Horizontal => display.iter_mut().for_each(|row| row.reverse()),
+            Axis::Vertical => display.reverse(),
+        },
+        SyncType::Shift { dir, amount, wrap } => shift_board(display, dir, amount, wrap),
+        SyncType::Invert(color) => display.iter_mut().flatten().for_each(|led| {
+            led.color = match led.color {
+                LedColor::Off => color,
+                _ => LedColor::Off,
+            }
+        }),
+        SyncType::Rotate(r) => match r {
+            Rotation::Clockwise => {
+                let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
+                let mut disp_rotated = [[LedState::default(); W]; H];
+                for (y, r) in display.iter().enumerate() {
+                    for (x, l) in r.iter().enumerate() {
+                        // clockwise rotation
+                        // x => -y
+                        // y => x
+                        let x_new = -(y as f64 - center.1) + center.0;
+                        let y_new = x as f64 - center.0 + center.1;
+                        disp_rotated[y_new as usize][x_new as usize] = *l;
                     }
                 }
+                *display = disp_rotated;
             }
-            SyncType::Rotate(r) => match r {
-                Rotation::Clockwise => {
-                    let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
-                    let mut disp_rotated = [[LedState::default(); W]; H];
-                    for (y, r) in self.display.iter().enumerate() {
-                        for (x, l) in r.iter().enumerate() {
-                            // clockwise rotation
-                            // x => -y
-                            // y => x
-                            let x_new = -(y as f64 - center.1) + center.0;
-                            let y_new = x as f64 - center.0 + center.1;
-                            disp_rotated[y_new as usize][x_new as usize] = *l;
-                        }
+            Rotation::CounterClockwise => {
+                let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
+                let mut disp_rotated = [[LedState::default(); W]; H];
+                for (y, r) in display.iter().enumerate() {
+                    for (x, l) in r.iter().enumerate() {
+                        // counterclockwise rotation
+                        // x => y
+                        // y => -x
+                        let x_new = y as f64 - center.1 + center.0;
+                        let y_new = -(x as f64 - center.0) + center.1;
+                        disp_rotated[y_new as usize][x_new as usize] = *l;
                     }
-                    self.display = disp_rotated;
                 }
-                Rotation::CounterClockwise => {
-                    let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
-                    let mut disp_rotated = [[LedState::default(); W]; H];
-                    for (y, r) in self.display.iter().enumerate() {
-                        for (x, l) in r.iter().enumerate() {
-                            // counterclockwise rotation
-                            // x => y
-                            // y => -x
-                            let x_new = y as f64 - center.1 + center.0;
-                            let y_new = -(x as f64 - center.0) + center.1;
-                            disp_rotated[y_new as usize][x_new as usize] = *l;
-                        }
+                *display = disp_rotated;
+            }
+            Rotation::OneEighty => {
+                // TODO improve with swap() and ranges 0..W/2   0..H/2
+                let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
+                let mut disp_rotated = [[LedState::default(); W]; H];
+                for (y, r) in display.iter().enumerate() {
+                    for (x, l) in r.iter().enumerate() {
+                        // 180° rotation
+                        // x => -y
+                        // y => -x
+                        let x_new = -(x as f64 - center.0) + center.0;
+                        let y_new = -(y as f64 - center.1) + center.1;
+                        disp_rotated[y_new as usize][x_new as usize] = *l;
                     }
-                    self.display = disp_rotated;
                 }
-                Rotation::OneEighty => {
-                    // TODO improve with swap() and ranges 0..W/2   0..H/2
-                    let center = ((W - 1) as f64 / 2., (H - 1) as f64 / 2.);
-                    let mut disp_rotated = [[LedState::default(); W]; H];
-                    for (y, r) in self.display.iter().enumerate() {
-                        for (x, l) in r.iter().enumerate() {
-                            // 180° rotation
-                            // x => -y
-                            // y => -x
-                            let x_new = -(x as f64 - center.0) + center.0;
-                            let y_new = -(y as f64 - center.1) + center.1;
-                            disp_rotated[y_new as usize][x_new as usize] = *l;
-                        }
-                    }
-                    self.display = disp_rotated;
+                *display = disp_rotated;
+            }
+        },
+    }
+}
+
+/// Move every led of `display` `amount` cells in direction `dir`, see [SyncType::Shift].
+fn shift_board<const W: usize, const H: usize>(
+    display: &mut [[LedState; W]; H],
+    dir: Direction,
+    amount: usize,
+    wrap: bool,
+) {
+    // shifting further than the board is wide or high only changes anything when wrapping
+    let (dx, dy) = match (dir, wrap) {
+        (Direction::Left | Direction::Right, true) => ((amount % W) as isize, 0),
+        (Direction::Up | Direction::Down, true) => (0, (amount % H) as isize),
+        (Direction::Left | Direction::Right, false) => (amount.min(W) as isize, 0),
+        (Direction::Up | Direction::Down, false) => (0, amount.min(H) as isize),
+    };
+    let (dx, dy) = match dir {
+        Direction::Up | Direction::Left => (-dx, -dy),
+        Direction::Down | Direction::Right => (dx, dy),
+    };
+
+    let old = *display;
+    for (y, row) in display.iter_mut().enumerate() {
+        for (x, led) in row.iter_mut().enumerate() {
+            // the cell the led now at (x, y) came from
+            let (sx, sy) = (x as isize - dx, y as isize - dy);
+            *led = match wrap {
+                true => old[sy.rem_euclid(H as isize) as usize][sx.rem_euclid(W as isize) as usize],
+                false if (0..W as isize).contains(&sx) && (0..H as isize).contains(&sy) => {
+                    old[sy as usize][sx as usize]
                 }
-            },
+                false => LedState::default(),
+            };
         }
     }
+}
 
-    pub(super) fn clear_row(&mut self) {
-        self.row.clear();
-        self.row.push();
+/// Run `f` until it succeeds, it fails with an error other than a gpio error, or the
+/// attempts of `retry` run out.
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+fn with_retry<T>(
+    retry: &GpioRetry,
+    mut f: impl FnMut() -> error::DisplayResult<T>,
+) -> error::DisplayResult<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if e.is_gpio() && attempt < retry.attempts => {
+                log::warn!("Failed to acquire gpio (attempt {attempt}): {e:?}");
+                std::thread::sleep(retry.delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
     }
 }
 
+/// List the leds that differ between two boards.
+///
+/// Every changed led is returned as `(x, y, old, new)`, where `old` is the state in `a`
+/// and `new` the state in `b`.
+pub fn board_diff<const W: usize, const H: usize>(
+    a: &[[LedState; W]; H],
+    b: &[[LedState; W]; H],
+) -> Vec<(usize, usize, LedState, LedState)> {
+    let mut diff = Vec::new();
+    for (y, (row_a, row_b)) in a.iter().zip(b).enumerate() {
+        for (x, (old, new)) in row_a.iter().zip(row_b).enumerate() {
+            if old != new {
+                diff.push((x, y, *old, *new));
+            }
+        }
+    }
+    diff
+}
+
 impl Default for LedColor {
     fn default() -> Self {
         Self::Off
@@ -256,16 +774,133 @@ impl FromStr for LedColor {
             "magenta" => Ok(Self::Magenta),
             "cyan" => Ok(Self::Cyan),
             "white" => Ok(Self::White),
+            // hex color codes are shown as the nearest color
+            hex if hex.len() == 7
+                && hex.starts_with('#')
+                && hex[1..].chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+                Ok(Self::nearest_rgb(channel(1), channel(3), channel(5)))
+            }
             _ => Err("Could not parse string".to_string()),
         }
     }
 }
 
+impl std::fmt::Display for LedColor {
+    /// Write the color name as read by [LedColor::from_str].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Yellow => "yellow",
+            Self::Blue => "blue",
+            Self::Magenta => "magenta",
+            Self::Cyan => "cyan",
+            Self::White => "white",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl LedColor {
+    /// Every color, in order of their encoding.
+    pub fn all() -> [LedColor; 1 << COLOR_BITS] {
+        SUPPORTED_COLORS
+    }
+
+    /// Iterate over every color, in order of their encoding.
+    pub fn iter() -> impl Iterator<Item = LedColor> {
+        SUPPORTED_COLORS.into_iter()
+    }
+
+    /// Character the color is drawn as in ascii art, the first letter of its name or `.`
+    /// when off.
+    pub fn ascii(self) -> char {
+        match self {
+            Self::Off => '.',
+            Self::Red => 'R',
+            Self::Green => 'G',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+            Self::Magenta => 'M',
+            Self::Cyan => 'C',
+            Self::White => 'W',
+        }
+    }
+
+    /// The rgb values of the color, with every channel either off or full.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        // the color encoding has one bit for each of red, green and blue
+        let full = |bit: u8| (self as u8 >> bit & 1) * u8::MAX;
+        (full(0), full(1), full(2))
+    }
+
+    /// The color closest to the given rgb values.
+    ///
+    /// Every color is a corner of the rgb cube, so the closest one is found by rounding
+    /// each channel to either off or full.
+    pub fn nearest_rgb(r: u8, g: u8, b: u8) -> LedColor {
+        let bits = [r, g, b]
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (bit, channel)| {
+                bits | ((*channel >= 128) as usize) << bit
+            });
+        SUPPORTED_COLORS[bits]
+    }
+}
+
+/// The state of every led of a `W` by `H` display, returned by
+/// [DisplayInterface::snapshot](crate::DisplayInterface::snapshot).
+///
+/// Indexes like the board it wraps, as `snapshot[y][x]`. It is written as ascii art with one
+/// character per led, see [LedColor::ascii].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot<const W: usize, const H: usize>(pub [[LedState; W]; H]);
+
+impl<const W: usize, const H: usize> std::ops::Deref for Snapshot<W, H> {
+    type Target = [[LedState; W]; H];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const W: usize, const H: usize> std::ops::DerefMut for Snapshot<W, H> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<const W: usize, const H: usize> From<Snapshot<W, H>> for [[LedState; W]; H] {
+    fn from(snapshot: Snapshot<W, H>) -> Self {
+        snapshot.0
+    }
+}
+
+impl<const W: usize, const H: usize> std::fmt::Display for Snapshot<W, H> {
+    /// Write every row on its own line, with one character per led.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (y, row) in self.0.iter().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for led in row {
+                write!(f, "{}", led.color.ascii())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for LedState {
     fn default() -> Self {
         Self {
             color: LedColor::default(),
             blink: None,
+            brightness: u8::MAX,
         }
     }
 }
@@ -273,6 +908,1001 @@ impl Default for LedState {
 impl LedState {
     /// Create a new [LedState](self) with the given color and default blink.
     pub fn with_color(color: LedColor) -> Self {
-        Self { color, blink: None }
+        Self {
+            color,
+            blink: None,
+            brightness: u8::MAX,
+        }
+    }
+
+    /// Blink the led as given by `blink`.
+    pub fn with_blink(mut self, blink: BlinkInfo) -> Self {
+        self.blink = Some(blink);
+        self
+    }
+
+    /// Dim the led to `brightness`, where `255` is full brightness.
+    ///
+    /// Dimmed leds are turned off on a share of the display refreshes.
+    pub fn with_brightness(mut self, brightness: u8) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// The brightness of the led, where `255` is full brightness.
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Check that the blink interval is at least a microsecond and the blink duration does
+    /// not exceed it.
+    pub(crate) fn validate(&self) -> error::DisplayResult<()> {
+        match self.blink {
+            // the shown color divides by the interval in whole microseconds
+            Some(blink) if blink.int.as_micros() == 0 || blink.dur > blink.int => {
+                Err(error::Error::InvalidBlink)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+mod test_board_diff {
+    #[allow(unused_imports)]
+    use super::{board_diff, LedColor, LedState};
+
+    #[test]
+    fn two_changed_cells() {
+        let a = [[LedState::default(); 7]; 7];
+        let mut b = a;
+        b[4][3] = LedState::with_color(LedColor::Blue);
+        b[0][6] = LedState::with_color(LedColor::Red);
+
+        let diff = board_diff(&a, &b);
+        assert_eq!(diff.len(), 2);
+
+        let (x, y, old, new) = diff[0];
+        assert_eq!((x, y), (6, 0));
+        assert!(matches!(old.color, LedColor::Off));
+        assert!(matches!(new.color, LedColor::Red));
+
+        let (x, y, old, new) = diff[1];
+        assert_eq!((x, y), (3, 4));
+        assert!(matches!(old.color, LedColor::Off));
+        assert!(matches!(new.color, LedColor::Blue));
+    }
+
+    #[test]
+    fn identical_boards() {
+        let a = [[LedState::with_color(LedColor::Green); 5]; 3];
+        assert!(board_diff(&a, &a).is_empty());
+    }
+}
+
+#[cfg(feature = "rppal")]
+mod test_with_retry {
+    #[allow(unused_imports)]
+    use super::with_retry;
+    #[allow(unused_imports)]
+    use crate::{Error, GpioRetry};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    /// Returns a closure that fails with a gpio error the first `failures` times it is called.
+    #[allow(dead_code)]
+    fn failing(failures: usize) -> impl FnMut() -> crate::DisplayResult<usize> {
+        let mut calls = 0;
+        move || {
+            calls += 1;
+            match calls <= failures {
+                true => Err(Error::Gpio(rppal::gpio::Error::UnknownModel)),
+                false => Ok(calls),
+            }
+        }
+    }
+
+    #[test]
+    fn succeeds_after_failures() {
+        let retry = GpioRetry {
+            attempts: 4,
+            delay: Duration::from_millis(1),
+        };
+        assert_eq!(with_retry(&retry, failing(3)).unwrap(), 4);
+    }
+
+    #[test]
+    fn gives_up() {
+        let retry = GpioRetry {
+            attempts: 3,
+            delay: Duration::from_millis(1),
+        };
+        assert!(matches!(
+            with_retry(&retry, failing(3)),
+            Err(Error::Gpio(_))
+        ));
+    }
+
+    #[test]
+    fn no_retry_by_default() {
+        assert!(with_retry(&GpioRetry::default(), failing(1)).is_err());
+    }
+}
+
+mod test_sync_board {
+    #[allow(unused_imports)]
+    use super::{sync_board, LedColor, LedState};
+    #[allow(unused_imports)]
+    use crate::{Sync, SyncType};
+
+    #[test]
+    fn fill() {
+        let mut board = [[LedState::default(); 7]; 5];
+        sync_board(
+            &mut board,
+            SyncType::Fill(LedState::with_color(LedColor::Magenta)),
+        );
+        assert!(board
+            .iter()
+            .flatten()
+            .all(|led| matches!(led.color, LedColor::Magenta) && led.blink.is_none()));
+    }
+
+    #[test]
+    fn clear() {
+        let mut board = [[LedState::with_color(LedColor::Red); 7]; 7];
+        sync_board(
+            &mut board,
+            SyncType::Single(Sync {
+                x: 1,
+                y: 2,
+                state: LedState::with_color(LedColor::Blue),
+            }),
+        );
+        sync_board(&mut board, SyncType::Clear);
+        assert!(board
+            .iter()
+            .flatten()
+            .all(|led| matches!(led.color, LedColor::Off)));
+    }
+}
+
+mod test_flip {
+    #[allow(unused_imports)]
+    use super::{sync_board, LedColor, LedState};
+    #[allow(unused_imports)]
+    use crate::{Axis, Sync, SyncType};
+
+    /// A 7x5 board with a red led at (1, 0) and a blue led at (6, 3).
+    #[allow(dead_code)]
+    fn board() -> [[LedState; 7]; 5] {
+        let mut board = [[LedState::default(); 7]; 5];
+        board[0][1] = LedState::with_color(LedColor::Red);
+        board[3][6] = LedState::with_color(LedColor::Blue);
+        board
+    }
+
+    #[allow(dead_code)]
+    fn lit(board: &[[LedState; 7]; 5]) -> Vec<(usize, usize, u8)> {
+        let mut lit = Vec::new();
+        for (y, row) in board.iter().enumerate() {
+            for (x, led) in row.iter().enumerate() {
+                if !matches!(led.color, LedColor::Off) {
+                    lit.push((x, y, led.color as u8));
+                }
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn horizontal() {
+        let mut board = board();
+        sync_board(&mut board, SyncType::Flip(Axis::Horizontal));
+        assert_eq!(lit(&board), [(5, 0, 1), (0, 3, 4)]);
+    }
+
+    #[test]
+    fn vertical() {
+        let mut board = board();
+        sync_board(&mut board, SyncType::Flip(Axis::Vertical));
+        assert_eq!(lit(&board), [(6, 1, 4), (1, 4, 1)]);
+    }
+
+    #[test]
+    fn twice_is_identity() {
+        let mut flipped = board();
+        sync_board(&mut flipped, SyncType::Flip(Axis::Horizontal));
+        sync_board(&mut flipped, SyncType::Flip(Axis::Horizontal));
+        assert_eq!(lit(&flipped), lit(&board()));
+    }
+}
+
+mod test_invert {
+    #[allow(unused_imports)]
+    use super::{sync_board, BlinkInfo, LedColor, LedState};
+    #[allow(unused_imports)]
+    use crate::SyncType;
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn mixed_pattern() {
+        let blink = Some(BlinkInfo {
+            dur: Duration::from_millis(100),
+            int: Duration::from_millis(200),
+        });
+        let mut board = [[LedState::default(); 3]; 2];
+        board[0][0] = LedState::with_color(LedColor::Red);
+        board[0][1].blink = blink;
+        board[1][2] = LedState {
+            color: LedColor::White,
+            blink,
+            brightness: 100,
+        };
+
+        sync_board(&mut board, SyncType::Invert(LedColor::Green));
+        let colors = board.map(|row| row.map(|led| led.color as u8));
+        let (off, green) = (LedColor::Off as u8, LedColor::Green as u8);
+        assert_eq!(colors, [[off, green, green], [green, green, off]]);
+        // blink and brightness are kept
+        assert!(board[0][1].blink.is_some());
+        assert!(board[1][2].blink.is_some());
+        assert_eq!(board[1][2].brightness, 100);
+    }
+}
+
+mod test_shift_board {
+    #[allow(unused_imports)]
+    use super::{shift_board, LedColor, LedState};
+    #[allow(unused_imports)]
+    use crate::Direction;
+
+    /// A 4x3 board with a distinct color in every corner.
+    #[allow(dead_code)]
+    fn corners() -> [[LedState; 4]; 3] {
+        let mut board = [[LedState::default(); 4]; 3];
+        board[0][0] = LedState::with_color(LedColor::Red);
+        board[0][3] = LedState::with_color(LedColor::Green);
+        board[2][0] = LedState::with_color(LedColor::Blue);
+        board[2][3] = LedState::with_color(LedColor::White);
+        board
+    }
+
+    #[allow(dead_code)]
+    fn colors(board: &[[LedState; 4]; 3]) -> Vec<Vec<u8>> {
+        board
+            .iter()
+            .map(|row| row.iter().map(|led| led.color as u8).collect())
+            .collect()
+    }
+
+    #[test]
+    fn without_wrap() {
+        let mut board = corners();
+        shift_board(&mut board, Direction::Right, 1, false);
+        assert_eq!(colors(&board), [[0, 1, 0, 0], [0, 0, 0, 0], [0, 4, 0, 0]]);
+
+        let mut board = corners();
+        shift_board(&mut board, Direction::Up, 2, false);
+        assert_eq!(colors(&board), [[4, 0, 0, 7], [0, 0, 0, 0], [0, 0, 0, 0]]);
+
+        let mut board = corners();
+        shift_board(&mut board, Direction::Left, 10, false);
+        assert_eq!(colors(&board), [[0; 4]; 3]);
+    }
+
+    #[test]
+    fn with_wrap() {
+        let mut board = corners();
+        shift_board(&mut board, Direction::Left, 1, true);
+        assert_eq!(colors(&board), [[0, 0, 2, 1], [0, 0, 0, 0], [0, 0, 7, 4]]);
+
+        let mut board = corners();
+        shift_board(&mut board, Direction::Down, 4, true);
+        assert_eq!(colors(&board), [[4, 0, 0, 7], [1, 0, 0, 2], [0, 0, 0, 0]]);
+    }
+}
+
+mod test_dither_on {
+    #[allow(unused_imports)]
+    use super::dither_on;
+
+    #[test]
+    fn share_of_runs() {
+        for brightness in [0, 1, 64, 128, 200, 254] {
+            let on = (0..=u8::MAX)
+                .filter(|frame| dither_on(brightness, *frame))
+                .count();
+            assert_eq!(on, brightness as usize);
+        }
+        assert!((0..=u8::MAX).all(|frame| dither_on(u8::MAX, frame)));
+    }
+
+    #[test]
+    fn spread_out() {
+        // half brightness alternates between on and off
+        let on: Vec<bool> = (0..8).map(|frame| dither_on(128, frame)).collect();
+        assert_eq!(on, [true, false, true, false, true, false, true, false]);
+    }
+}
+
+mod test_gamma_lut {
+    #[allow(unused_imports)]
+    use super::gamma_lut;
+
+    #[test]
+    fn curve() {
+        let lut = gamma_lut(2.2);
+        assert_eq!(lut[0], 0);
+        assert_eq!(lut[255], 255);
+        // half brightness is perceived at a little over a fifth of the runs
+        assert_eq!(lut[128], 56);
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn linear() {
+        let lut = gamma_lut(1.0);
+        assert!((0..=255).all(|level| lut[level] as usize == level));
+    }
+}
+
+mod test_oe_pwm_frequency {
+    #[allow(unused_imports)]
+    use super::{oe_pwm_frequency, time_per_led, OE_PWM_PERIODS};
+
+    #[test]
+    fn whole_periods_per_column() {
+        let frequency = oe_pwm_frequency::<7>(60.0);
+        assert!((frequency - 3_360.0).abs() < 1e-9);
+        let column = time_per_led::<7, 7>(60.0) * 7;
+        assert!((column.as_secs_f64() * frequency - OE_PWM_PERIODS).abs() < 1e-4);
+        // a faster refresh rate speeds up the pwm with it
+        assert!((oe_pwm_frequency::<7>(120.0) - 2.0 * frequency).abs() < 1e-9);
+    }
+}
+
+mod test_nearest_rgb {
+    #[allow(unused_imports)]
+    use super::{LedColor, SUPPORTED_COLORS};
+
+    #[test]
+    fn nearest() {
+        assert!(matches!(LedColor::nearest_rgb(0, 0, 0), LedColor::Off));
+        assert!(matches!(
+            LedColor::nearest_rgb(255, 0, 255),
+            LedColor::Magenta
+        ));
+        assert!(matches!(
+            LedColor::nearest_rgb(30, 140, 250),
+            LedColor::Cyan
+        ));
+        assert!(matches!(
+            LedColor::nearest_rgb(127, 128, 90),
+            LedColor::Green
+        ));
+    }
+
+    #[test]
+    fn to_rgb() {
+        assert_eq!(LedColor::Yellow.to_rgb(), (255, 255, 0));
+        assert_eq!(LedColor::Off.to_rgb(), (0, 0, 0));
+        assert!(matches!(LedColor::nearest_rgb(200, 10, 10), LedColor::Red));
+    }
+
+    #[test]
+    fn round_trip() {
+        for color in SUPPORTED_COLORS {
+            let (r, g, b) = color.to_rgb();
+            assert_eq!(LedColor::nearest_rgb(r, g, b) as u8, color as u8);
+        }
+    }
+}
+
+mod test_hex_color {
+    #[allow(unused_imports)]
+    use super::LedColor;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn nearest_color() {
+        assert!(matches!(LedColor::from_str("#00ff00"), Ok(LedColor::Green)));
+        assert!(matches!(
+            LedColor::from_str("#FF00e0"),
+            Ok(LedColor::Magenta)
+        ));
+        assert!(matches!(LedColor::from_str("#808080"), Ok(LedColor::White)));
+        assert!(matches!(LedColor::from_str("#7f7f7f"), Ok(LedColor::Off)));
+    }
+
+    #[test]
+    fn invalid() {
+        for s in [
+            "#00ff0", "#00ff000", "#00fg00", "00ff00", "#+0ff00", "#0ö0ff",
+        ] {
+            assert!(LedColor::from_str(s).is_err(), "{s}");
+        }
+    }
+}
+
+mod test_all_colors {
+    #[allow(unused_imports)]
+    use super::LedColor;
+    #[allow(unused_imports)]
+    use std::str::FromStr;
+
+    #[test]
+    fn every_color() {
+        assert_eq!(LedColor::all().len(), 8);
+        assert_eq!(LedColor::iter().count(), 8);
+        for (encoding, color) in LedColor::iter().enumerate() {
+            assert_eq!(color as usize, encoding);
+            let parsed = LedColor::from_str(&format!("{color:?}").to_lowercase()).unwrap();
+            assert_eq!(parsed as u8, color as u8);
+        }
+    }
+}
+
+mod test_palette_map {
+    #[allow(unused_imports)]
+    use super::{Display, LedColor, LedState, RED_GREEN_SAFE_PALETTE};
+    #[allow(unused_imports)]
+    use crate::{display::test_output_pin::null_pins, Sync, SyncType};
+
+    #[test]
+    fn remaps_rendering_only() {
+        let mut disp = Display::<7, 7, _>::with_pins(
+            60.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let red = LedState::with_color(LedColor::Red);
+        disp.sync(SyncType::Single(Sync {
+            x: 2,
+            y: 3,
+            state: red,
+        }));
+        assert!(matches!(disp.shown_color(&red, 0), LedColor::Red));
+
+        disp.set_palette_map(RED_GREEN_SAFE_PALETTE);
+        assert!(matches!(disp.shown_color(&red, 0), LedColor::Yellow));
+        assert!(matches!(disp.board()[3][2].color, LedColor::Red));
+    }
+}
+
+mod test_blink_epoch {
+    #[allow(unused_imports)]
+    use super::{BlinkInfo, Display, LedColor, LedState};
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::{null_pins, NullPin};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    /// Red led that is on for `dur` out of every `int` milliseconds.
+    #[allow(dead_code)]
+    fn blinking(dur: u64, int: u64) -> LedState {
+        LedState {
+            color: LedColor::Red,
+            blink: Some(BlinkInfo {
+                dur: Duration::from_millis(dur),
+                int: Duration::from_millis(int),
+            }),
+            brightness: u8::MAX,
+        }
+    }
+
+    /// Check if `led` is on at `now` microseconds since the unix epoch.
+    #[allow(dead_code)]
+    fn on(disp: &Display<7, 7, NullPin>, led: &LedState, now: u128) -> bool {
+        !matches!(disp.shown_color(led, now), LedColor::Off)
+    }
+
+    #[test]
+    fn aligns_different_intervals() {
+        let mut disp = Display::<7, 7, _>::with_pins(
+            60.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let (fast, slow) = (blinking(100, 300), blinking(200, 700));
+
+        // 1.25s past the unix epoch the fast led is on and the slow one is off
+        let now = 1_250_000;
+        assert!(on(&disp, &fast, now));
+        assert!(!on(&disp, &slow, now));
+
+        // both start a blink at the epoch
+        let epoch = 1_200_000;
+        disp.set_blink_epoch(Some(epoch));
+        assert!(on(&disp, &fast, epoch) && on(&disp, &slow, epoch));
+        assert!(on(&disp, &fast, now) && on(&disp, &slow, now));
+        // and both are off once their on time has passed
+        assert!(!on(&disp, &fast, epoch + 250_000));
+        assert!(!on(&disp, &slow, epoch + 250_000));
+    }
+}
+
+mod test_safe_startup {
+    #[allow(unused_imports)]
+    use super::Display;
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::Trace;
+
+    #[test]
+    fn outputs_disabled_first() {
+        let trace = Trace::default();
+        Display::<7, 7, _>::with_pins(60.0, trace.pins(), Default::default(), Default::default())
+            .unwrap();
+
+        let events = trace.events();
+        assert_eq!(events[0], (Trace::DEC_E1, true));
+        let shift_reg: Vec<_> = events
+            .iter()
+            .filter(|(pin, _)| *pin <= Trace::SR_OE)
+            .collect();
+        assert_eq!(
+            shift_reg[..2],
+            [&(Trace::SR_OE, true), &(Trace::SR_SRCLR, false)]
+        );
+        // the decoder outputs are only enabled at the very end
+        let enabled = events
+            .iter()
+            .position(|event| *event == (Trace::DEC_E1, false));
+        assert_eq!(enabled, Some(events.len() - 1));
+    }
+}
+
+mod test_unchanged_rows {
+    #[allow(unused_imports)]
+    use super::Display;
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{RecordingPin, Trace},
+        LedColor, LedState, SyncType, COLOR_BITS,
+    };
+    #[allow(unused_imports)]
+    use std::time::Instant;
+
+    /// A display with the trace of its pins, which counts the bits shifted into its shift
+    /// registers and how often they are pushed to the outputs.
+    #[allow(dead_code)]
+    fn display<const W: usize, const H: usize>() -> (Display<W, H, RecordingPin>, Trace) {
+        let trace = Trace::default();
+        let disp = Display::with_pins(
+            1_000.0,
+            trace.pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        trace.clear();
+        (disp, trace)
+    }
+
+    /// Run once, and return the number of bits shifted in.
+    #[allow(dead_code)]
+    fn run_once(disp: &mut Display<7, 7, RecordingPin>, trace: &Trace) -> usize {
+        trace.clear();
+        disp.run_once(Instant::now());
+        trace.count_high(Trace::SR_SRCLK)
+    }
+
+    #[test]
+    fn static_frame() {
+        let (mut disp, trace) = display();
+        disp.sync(SyncType::All(vec![
+            vec![
+                LedState::with_color(LedColor::Red);
+                7
+            ];
+            7
+        ]));
+
+        // every row is the same, so only the first one is shifted in
+        assert_eq!(run_once(&mut disp, &trace), 7 * COLOR_BITS);
+        assert_eq!(run_once(&mut disp, &trace), 0);
+
+        // clearing the shift register forces the row to be shifted in again
+        disp.clear_row();
+        assert_eq!(run_once(&mut disp, &trace), 7 * COLOR_BITS);
+    }
+
+    #[test]
+    fn changing_rows() {
+        let (mut disp, trace) = display();
+        let board = (0..7)
+            .map(|y| {
+                let color = match y % 2 {
+                    0 => LedColor::Red,
+                    _ => LedColor::Blue,
+                };
+                vec![LedState::with_color(color); 7]
+            })
+            .collect();
+        disp.sync(SyncType::All(board));
+
+        assert_eq!(run_once(&mut disp, &trace), 7 * 7 * COLOR_BITS);
+        // the first row is the same as the last one of the previous run
+        assert_eq!(run_once(&mut disp, &trace), 6 * 7 * COLOR_BITS);
+    }
+
+    #[test]
+    fn wide_rows() {
+        let (mut disp, trace) = display::<16, 2>();
+        let board = [LedColor::Red, LedColor::Green]
+            .map(|color| vec![LedState::with_color(color); 16])
+            .to_vec();
+        disp.sync(SyncType::All(board));
+        disp.run_once(Instant::now());
+
+        // every row is shifted through the whole chain before it is pushed once
+        assert_eq!(trace.count_high(Trace::SR_SRCLK), 2 * 16 * COLOR_BITS);
+        assert_eq!(trace.count_high(Trace::SR_RCLK), 2);
+    }
+}
+
+mod test_tall_display {
+    #[allow(unused_imports)]
+    use super::Display;
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{null_pins, NullPin},
+        Error,
+    };
+
+    #[test]
+    fn needs_fourth_address_pin() {
+        assert!(matches!(
+            Display::<7, 16, _>::with_pins(
+                60.0,
+                null_pins(),
+                Default::default(),
+                Default::default()
+            ),
+            Err(Error::InvalidDim)
+        ));
+
+        let mut pins = null_pins();
+        pins.dec_a3 = Some(NullPin);
+        assert!(
+            Display::<7, 16, _>::with_pins(60.0, pins, Default::default(), Default::default())
+                .is_ok()
+        );
+        let mut pins = null_pins();
+        pins.dec_a3 = Some(NullPin);
+        assert!(matches!(
+            Display::<7, 17, _>::with_pins(60.0, pins, Default::default(), Default::default()),
+            Err(Error::InvalidDim)
+        ));
+    }
+}
+
+mod test_snapshot {
+    #[allow(unused_imports)]
+    use super::{LedColor, LedState, Snapshot};
+
+    #[test]
+    fn ascii_art() {
+        let mut board = [[LedState::default(); 4]; 3];
+        board[0][0] = LedState::with_color(LedColor::Red);
+        board[1][1] = LedState::with_color(LedColor::Green);
+        board[1][2] = LedState::with_color(LedColor::Blue);
+        board[2][3] = LedState::with_color(LedColor::White);
+
+        assert_eq!(Snapshot(board).to_string(), "R...\n.GB.\n...W");
+    }
+
+    #[test]
+    fn every_color() {
+        let chars: String = LedColor::iter().map(LedColor::ascii).collect();
+        assert_eq!(chars, ".RGYBMCW");
+    }
+}
+
+mod test_equality {
+    #[allow(unused_imports)]
+    use super::{BlinkInfo, LedColor, LedState, Snapshot};
+    #[allow(unused_imports)]
+    use std::{collections::HashSet, time::Duration};
+
+    #[test]
+    fn led_states() {
+        let red = LedState::with_color(LedColor::Red);
+        assert_eq!(red, LedState::with_color(LedColor::Red));
+        assert_ne!(red, LedState::with_color(LedColor::Blue));
+        assert_ne!(
+            red,
+            LedState {
+                brightness: 128,
+                ..red
+            }
+        );
+
+        let blink = BlinkInfo {
+            dur: Duration::from_millis(100),
+            int: Duration::from_millis(200),
+        };
+        assert_ne!(
+            red,
+            LedState {
+                blink: Some(blink),
+                ..red
+            }
+        );
+        assert_eq!(Snapshot([[red; 2]; 2]), Snapshot([[red; 2]; 2]));
+    }
+
+    #[test]
+    fn colors_hash() {
+        let colors: HashSet<LedColor> = LedColor::iter().chain(LedColor::iter()).collect();
+        assert_eq!(colors.len(), 8);
+        assert!(colors.contains(&LedColor::Cyan));
+    }
+}
+
+mod test_column_timing {
+    #[allow(unused_imports)]
+    use super::Display;
+    #[allow(unused_imports)]
+    use crate::display::test_output_pin::null_pins;
+    #[allow(unused_imports)]
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn equal_slots() {
+        let disp = Display::<7, 5, _>::with_pins(
+            100.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let slot = disp.time_per_led() * 7;
+
+        let mut previous = Duration::ZERO;
+        for column in 0..5 {
+            let deadline = disp.column_deadline(column);
+            assert_eq!(deadline, previous + slot);
+            previous = deadline;
+        }
+        // the last column ends with the run, up to the rounding of the time per led
+        assert!(previous.abs_diff(Duration::from_millis(10)) < Duration::from_micros(1));
+    }
+
+    #[test]
+    fn shifting_is_taken_from_the_column() {
+        let disp = Display::<7, 5, _>::with_pins(
+            100.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let slot = disp.time_per_led() * 7;
+        let shifting = slot / 3;
+
+        // however long shifting took, a column is shown until its deadline
+        for column in 0..5 {
+            let start = disp.column_deadline(column) - slot + shifting;
+            let wait = disp.column_wait(column, start);
+            assert_eq!(wait, slot - shifting);
+            assert_eq!(start + wait, disp.column_deadline(column));
+        }
+    }
+
+    #[test]
+    fn overrun_does_not_wait() {
+        let disp = Display::<7, 5, _>::with_pins(
+            100.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let late = disp.column_deadline(2) + Duration::from_micros(1);
+        assert_eq!(disp.column_wait(2, late), Duration::ZERO);
+        // the next column only gets what is left of its slot
+        assert_eq!(disp.column_wait(3, late), disp.column_deadline(3) - late);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn overruns_are_counted() {
+        let mut disp = Display::<7, 5, _>::with_pins(
+            100.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        // a run that started a second ago is past the deadline of every row
+        disp.run_once(Instant::now() - Duration::from_secs(1));
+        assert_eq!(disp.take_overruns(), 5);
+        assert_eq!(disp.take_overruns(), 0);
+    }
+}
+
+mod test_dimmed_switch {
+    #[allow(unused_imports)]
+    use super::Display;
+    #[allow(unused_imports)]
+    use crate::{display::test_output_pin::Trace, LedColor, LedState, SyncType};
+    #[allow(unused_imports)]
+    use std::time::Instant;
+
+    #[test]
+    fn outputs_disabled_while_switching() {
+        let trace = Trace::default();
+        let mut disp = Display::<7, 7, _>::with_pins(
+            1_000.0,
+            trace.pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        disp.set_brightness(0.5).unwrap();
+        disp.sync(SyncType::All(vec![
+            vec![
+                LedState::with_color(LedColor::Red);
+                7
+            ];
+            7
+        ]));
+        disp.run_once(Instant::now());
+        assert!(trace.levels(Trace::SR_OE).contains(&false));
+
+        // replay the levels from the start, both enable lines are active low
+        let (mut oe, mut e1) = (true, true);
+        for (pin, high) in trace.events() {
+            match pin {
+                Trace::SR_OE => oe = high,
+                Trace::DEC_E1 => e1 = high,
+                Trace::DEC_A0 | Trace::DEC_A1 | Trace::DEC_A2 | Trace::SR_RCLK => {
+                    assert!(oe || e1, "outputs enabled while switching")
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+mod test_orientation {
+    #[allow(unused_imports)]
+    use super::{Display, Orientation, COLOR_BITS};
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{null_pins, Trace},
+        Error, LedColor, LedState, PanelConfig, Sync, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::time::Instant;
+
+    /// Where the only lit led of a square display mounted as given by `orientation` shows up
+    /// on the hardware when the led at `x`, `y` is synced: the decoder address enabled
+    /// while it is lit and its position in the chain, counted in the order the leds are
+    /// shifted in.
+    #[allow(dead_code)]
+    fn lit_led(orientation: Orientation, x: usize, y: usize) -> (usize, usize) {
+        let trace = Trace::default();
+        let panel = PanelConfig {
+            orientation,
+            ..Default::default()
+        };
+        let mut disp =
+            Display::<5, 5, _>::with_pins(5_000.0, trace.pins(), Default::default(), panel)
+                .unwrap();
+        disp.sync(SyncType::Single(Sync {
+            x,
+            y,
+            state: LedState::with_color(LedColor::Red),
+        }));
+
+        trace.clear();
+        disp.run_once(Instant::now());
+
+        // replay the run, noting what the shift register holds every time the outputs
+        // are enabled. every enable line of the default panel is active low.
+        let mut address = [false; 3];
+        let (mut serin, mut shifted) = (false, Vec::new());
+        let mut lit = Vec::new();
+        for (pin, high) in trace.events() {
+            match pin {
+                Trace::DEC_A0 | Trace::DEC_A1 | Trace::DEC_A2 => {
+                    address[usize::from(pin - Trace::DEC_A0)] = high
+                }
+                Trace::SR_SERIN => serin = high,
+                Trace::SR_SRCLK if high => shifted.push(serin),
+                Trace::SR_SRCLR if !high => shifted.clear(),
+                Trace::SR_OE if !high => {
+                    if let Some(bit) = shifted.iter().position(|bit| *bit) {
+                        let address = address
+                            .iter()
+                            .enumerate()
+                            .map(|(i, a)| usize::from(*a) << i)
+                            .sum();
+                        lit.push((address, bit / COLOR_BITS));
+                    }
+                }
+                _ => (),
+            }
+        }
+        assert_eq!(lit.len(), 1, "exactly one column lights up");
+        lit[0]
+    }
+
+    #[test]
+    fn identity() {
+        assert_eq!(lit_led(Orientation::default(), 0, 0), (0, 0));
+        assert_eq!(lit_led(Orientation::default(), 1, 3), (3, 1));
+        assert_eq!(lit_led(Orientation::default(), 3, 1), (1, 3));
+    }
+
+    #[test]
+    fn flips() {
+        let flipped = Orientation {
+            flip_x: true,
+            flip_y: true,
+            ..Default::default()
+        };
+        // the opposite corner
+        assert_eq!(lit_led(flipped, 0, 0), (4, 4));
+
+        let flip_x = Orientation {
+            flip_x: true,
+            ..Default::default()
+        };
+        assert_eq!(lit_led(flip_x, 0, 0), (0, 4));
+
+        let flip_y = Orientation {
+            flip_y: true,
+            ..Default::default()
+        };
+        assert_eq!(lit_led(flip_y, 1, 0), (4, 1));
+    }
+
+    #[test]
+    fn transpose() {
+        let transposed = Orientation {
+            transpose: true,
+            ..Default::default()
+        };
+        assert_eq!(lit_led(transposed, 1, 3), (1, 3));
+
+        let rotated = Orientation {
+            flip_x: true,
+            transpose: true,
+            ..Default::default()
+        };
+        // transposed to (3, 1) first, then flipped
+        assert_eq!(lit_led(rotated, 1, 3), (1, 1));
+    }
+
+    #[test]
+    fn board_keeps_coordinates() {
+        let mut panel = PanelConfig::default();
+        panel.orientation.flip_x = true;
+        let mut disp =
+            Display::<7, 7, _>::with_pins(60.0, null_pins(), Default::default(), panel).unwrap();
+        disp.sync(SyncType::Single(Sync {
+            x: 0,
+            y: 0,
+            state: LedState::with_color(LedColor::Red),
+        }));
+        assert_eq!(disp.board()[0][0], LedState::with_color(LedColor::Red));
+    }
+
+    #[test]
+    fn transpose_needs_square_panel() {
+        let mut panel = PanelConfig::default();
+        panel.orientation.transpose = true;
+        assert!(matches!(
+            Display::<7, 5, _>::with_pins(60.0, null_pins(), Default::default(), panel),
+            Err(Error::InvalidDim)
+        ));
     }
 }
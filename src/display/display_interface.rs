@@ -1,15 +1,42 @@
 use std::{
     marker::PhantomData,
-    sync::mpsc::{channel, Sender},
+    sync::mpsc::{channel, sync_channel, RecvTimeoutError, SyncSender, TrySendError},
     thread,
+    time::Duration,
 };
 
 use crate::{
-    display::{interface_components::*, Display, DisplayManager, LedColor},
-    error, DisplayResult, Error, PinConfig,
+    display::{
+        interface_components::*, BlinkInfo, Display, DisplayManager, FrameClock, LedColor,
+        LedState, OutputPin, Snapshot, COLOR_BITS, SUPPORTED_COLORS,
+    },
+    error,
+    life::Life,
+    DisplayPins, DisplayResult, Error, GpioRetry, PanelConfig, PinConfig, Timing,
 };
 
-use super::animation::Animation;
+use super::animation::{Animation, AnimationId};
+use super::canvas::{Canvas, Viewport};
+
+/// Refresh rate used by [DisplayInterface::show_once].
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+const SHOW_ONCE_REFRESH: f64 = 60.0;
+
+/// Number of instructions that can be queued for the display thread by default, see
+/// [DisplayInterface::with_channel_capacity].
+const DEFAULT_CHANNEL_CAPACITY: usize = 64;
+
+/// Size in pixels of a led in a recording, see [DisplayInterface::stop_recording].
+#[cfg(feature = "record")]
+const RECORDING_SCALE: u32 = 16;
+
+/// Slowest pace animations can be played at, see [DisplayInterface::set_animation_speed].
+const MIN_ANIMATION_SPEED: f64 = 0.01;
+/// Fastest pace animations can be played at, see [DisplayInterface::set_animation_speed].
+const MAX_ANIMATION_SPEED: f64 = 100.0;
+
+/// Time a board is shown by [DisplayInterface::show_once].
+pub const SHOW_ONCE_DURATION: Duration = Duration::from_millis(500);
 
 /// An interface for the display created by the crate.
 ///
@@ -17,9 +44,16 @@ use super::animation::Animation;
 #[derive(Debug)]
 pub struct DisplayInterface<'d, S: State, const W: usize, const H: usize> {
     handle: Option<thread::JoinHandle<()>>,
-    tx: Option<Sender<Instruction>>,
+    tx: Option<SyncSender<Instruction>>,
     state: PhantomData<S>,
     id: &'d str,
+    refresh: Option<f64>,       // refresh rate of the last start
+    pins: Option<PinConfig>,    // pin configuration of the last start
+    gpio_retry: GpioRetry,      // retry policy for acquiring the gpio pins
+    timing: Timing,             // delays after switching the pins of the display
+    panel: PanelConfig,         // wiring and mounting of the panel
+    channel_capacity: usize,    // instructions that can be queued for the display thread
+    viewport: Option<Viewport>, // canvas shown by set_viewport
 }
 
 impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Stopped, W, H> {
@@ -27,24 +61,33 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Stopped, W, H> {
     ///
     /// # Example
     ///
-    /// ```
+    #[cfg_attr(
+        any(feature = "rppal", feature = "simulator", feature = "mock"),
+        doc = "```no_run"
+    )]
+    #[cfg_attr(
+        not(any(feature = "rppal", feature = "simulator", feature = "mock")),
+        doc = "```ignore"
+    )]
+    /// use c4_display::{DisplayInterface, PinConfig};
+    ///
     /// // Create a variable with the pin configuration
     /// let pin_config = PinConfig {
-    ///     sr_serin: 0,
-    ///     sr_srclk: 0,
-    ///     sr_rclk: 0,
-    ///     sr_srclr: 0,
-    ///     sr_oe: 0,
-    ///     dec_a0: 0,
-    ///     dec_a1: 0,
-    ///     dec_a2: 0,
+    ///     sr_serin: 17,
+    ///     sr_srclk: 22,
+    ///     sr_rclk: 23,
+    ///     sr_srclr: 24,
+    ///     sr_oe: 27,
+    ///     dec_a0: 25,
+    ///     dec_a1: 11,
+    ///     dec_a2: 5,
+    ///     dec_a3: None,
+    ///     dec_le: 6,
+    ///     dec_e1: 10,
     /// };
     ///
     /// // Create and start the display
-    /// let display = DisplayInterface::<_, 4, 4>::new("id").start(
-    ///     refresh: 30.0,
-    ///     pins: pin_config,
-    /// );
+    /// let display = DisplayInterface::<_, 4, 4>::new("id").start(30.0, pin_config);
     ///
     /// // Wait 5 seconds
     /// std::thread::sleep(std::time::Duration::from_secs(5));
@@ -58,24 +101,194 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Stopped, W, H> {
             tx: None,
             state: PhantomData,
             id,
+            gpio_retry: GpioRetry::default(),
+            timing: Timing::default(),
+            panel: PanelConfig::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: None,
+            pins: None,
         }
     }
 
+    /// Retry acquiring the gpio pins according to `retry` when the display is started.
+    ///
+    /// By default acquiring the pins is only attempted once.
+    pub fn with_gpio_retry(mut self, retry: GpioRetry) -> Self {
+        self.gpio_retry = retry;
+        self
+    }
+
+    /// Wait for the shift registers and decoder as given by `timing` after switching their
+    /// pins, see [Timing] for what happens when the delays are too short.
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Drive a panel wired and mounted as given by `panel`, see [PanelConfig].
+    pub fn with_panel(mut self, panel: PanelConfig) -> Self {
+        self.panel = panel;
+        self
+    }
+
+    /// Let at most `capacity` instructions wait for the display thread, 64 by default.
+    ///
+    /// The display thread applies every waiting instruction once per refresh. Once the queue
+    /// is full, [DisplayInterface::sync] and the other methods sending instructions block
+    /// until the display thread catches up, while [DisplayInterface::try_sync] returns an
+    /// error instead. A small capacity keeps the latency between sending and showing an
+    /// update low and bounds the memory used by a sender that outpaces the display, at the
+    /// cost of blocking sooner when sending bursts of updates. A capacity of 0 makes every
+    /// instruction wait until the display thread takes it.
+    pub fn with_channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
     /// Start the display. It will run at the given refresh rate and make use of the gpio pins
     /// provided in `PinConfig`.
     ///
     /// This function creates a new thread with the name `disp: id` where `id` is the id given
     /// to the display interface upon creation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display could not be initialised. Use [DisplayInterface::try_start]
+    /// to handle this case.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
     pub fn start(self, refresh: f64, pins: PinConfig) -> DisplayInterface<'d, Running, W, H> {
-        let (tx, rx) = channel::<Instruction>();
-        let disp = match Display::<W, H>::init(refresh, pins) {
+        match self.try_start(refresh, pins) {
+            Ok(disp) => disp,
+            Err(e) => panic!("failed to initialise display: {:?}", e),
+        }
+    }
+
+    /// Start the display like [DisplayInterface::start], letting at most `capacity`
+    /// instructions wait for the display thread, see [DisplayInterface::with_channel_capacity].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display could not be initialised.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn start_with_capacity(
+        self,
+        refresh: f64,
+        pins: PinConfig,
+        capacity: usize,
+    ) -> DisplayInterface<'d, Running, W, H> {
+        self.with_channel_capacity(capacity).start(refresh, pins)
+    }
+
+    /// Start the display. It will run at the given refresh rate and make use of the gpio pins
+    /// provided in `PinConfig`.
+    ///
+    /// This function creates a new thread with the name `disp: id` where `id` is the id given
+    /// to the display interface upon creation.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Gpio` if any of the pins could not be acquired.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn try_start(
+        self,
+        refresh: f64,
+        pins: PinConfig,
+    ) -> DisplayResult<DisplayInterface<'d, Running, W, H>> {
+        let disp = Display::<W, H, _>::init(
+            refresh,
+            pins.clone(),
+            &self.gpio_retry,
+            self.timing,
+            self.panel,
+        )?;
+        Ok(self.spawn(disp, refresh, Some(pins), None))
+    }
+
+    /// Start the display driven through the given output pins. It will run at the given
+    /// refresh rate.
+    ///
+    /// This function creates a new thread with the name `disp: id` where `id` is the id given
+    /// to the display interface upon creation.
+    ///
+    /// A display started this way can not be restarted with [DisplayInterface::restart],
+    /// since the pins are moved into the display thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the pins if the decoder output could not be enabled.
+    pub fn try_start_with_pins<P: OutputPin + 'static>(
+        self,
+        refresh: f64,
+        pins: DisplayPins<P>,
+    ) -> DisplayResult<DisplayInterface<'d, Running, W, H>> {
+        let disp = Display::<W, H, P>::with_pins(refresh, pins, self.timing, self.panel)?;
+        Ok(self.spawn(disp, refresh, None, None))
+    }
+
+    /// Start the display in step with every other display started on `clock`. It will make
+    /// use of the gpio pins provided in `PinConfig`.
+    ///
+    /// Instead of running at its own pace, the display runs once every time the clock ticks.
+    /// `refresh` should be the rate the clock is ticked at, it is used to spread the rows
+    /// of a run over time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display could not be initialised.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn start_synced(
+        self,
+        refresh: f64,
+        pins: PinConfig,
+        clock: &FrameClock,
+    ) -> DisplayInterface<'d, Running, W, H> {
+        match self.try_start_synced(refresh, pins, clock) {
             Ok(disp) => disp,
-            // TODO return error to user.
-            Err(e) => panic!("failed to initialise diplay: {}", e),
+            Err(e) => panic!("failed to initialise display: {:?}", e),
+        }
+    }
+
+    /// Start the display in step with every other display started on `clock`, like
+    /// [DisplayInterface::start_synced].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Gpio` if any of the pins could not be acquired.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn try_start_synced(
+        self,
+        refresh: f64,
+        pins: PinConfig,
+        clock: &FrameClock,
+    ) -> DisplayResult<DisplayInterface<'d, Running, W, H>> {
+        let disp = Display::<W, H, _>::init(
+            refresh,
+            pins.clone(),
+            &self.gpio_retry,
+            self.timing,
+            self.panel,
+        )?;
+        Ok(self.spawn(disp, refresh, Some(pins), Some(clock.clone())))
+    }
+
+    /// Spawn the display thread running `disp`, in step with `clock` if one is given.
+    fn spawn<P: OutputPin + 'static>(
+        self,
+        disp: Display<W, H, P>,
+        refresh: f64,
+        pins: Option<PinConfig>,
+        clock: Option<FrameClock>,
+    ) -> DisplayInterface<'d, Running, W, H> {
+        let (tx, rx) = sync_channel::<Instruction>(self.channel_capacity);
+        let manager = DisplayManager::new(disp, rx);
+        let mut manager = match clock {
+            Some(clock) => manager.synced(clock),
+            None => manager,
         };
         let handle = thread::Builder::new()
             .name(String::from(format!("disp: {}", self.id)))
-            .spawn(move || DisplayManager::new(disp, rx).start())
+            .spawn(move || manager.start())
             .expect("Couldn't spawn display thread");
 
         DisplayInterface::<'d, Running, W, H> {
@@ -83,6 +296,60 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Stopped, W, H> {
             tx: Some(tx),
             id: self.id,
             state: PhantomData,
+            gpio_retry: self.gpio_retry,
+            timing: self.timing,
+            panel: self.panel,
+            channel_capacity: self.channel_capacity,
+            viewport: None,
+            refresh: Some(refresh),
+            pins,
+        }
+    }
+
+    /// Show a board for a short moment without starting a display thread, on a panel wired
+    /// and mounted as given by `panel`.
+    ///
+    /// The board is multiplexed for [SHOW_ONCE_DURATION] on the calling thread, after which
+    /// the panel is cleared again. Leds are only lit while they are being multiplexed, so
+    /// unless the hardware latches its outputs the board will not persist afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if any led has a zero blink
+    /// interval or a blink duration larger than its blink interval.
+    ///
+    /// Returns a `c4_display::error::Error::Gpio` if any of the pins could not be acquired.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn show_once(
+        board: [[LedState; W]; H],
+        pins: PinConfig,
+        panel: PanelConfig,
+    ) -> DisplayResult<()> {
+        let sync = SyncType::All(board.iter().map(|row| row.to_vec()).collect());
+        validate_sync::<W, H>(&sync)?;
+
+        let disp = Display::<W, H, _>::init(
+            SHOW_ONCE_REFRESH,
+            pins,
+            &GpioRetry::default(),
+            Timing::default(),
+            panel,
+        )?;
+        show_for(disp, sync, SHOW_ONCE_DURATION)
+    }
+
+    /// Start the display again using the refresh rate and pin configuration it was last
+    /// started with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display has never been started with a [PinConfig], or if it could not
+    /// be initialised.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn restart(mut self) -> DisplayInterface<'d, Running, W, H> {
+        match (self.refresh, self.pins.take()) {
+            (Some(refresh), Some(pins)) => self.start(refresh, pins),
+            _ => panic!("Display has not been started before"),
         }
     }
 }
@@ -98,14 +365,21 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
     ///
     /// This is meant to be used when the display is no longer needed, and will be called
     /// automatically when the `DisplayInterface` instance is dropped.
-    pub fn stop(self) -> DisplayInterface<'d, Stopped, W, H> {
-        match self.tx {
-            Some(tx) => tx.send(Instruction::Stop).expect("Failed to send message"),
+    pub fn stop(mut self) -> DisplayInterface<'d, Stopped, W, H> {
+        match self.tx.take() {
+            // the thread may already have stopped on its own
+            Some(tx) => {
+                let _ = tx.send(Instruction::Stop);
+            }
             None => panic!("State machine broke: no sender found"),
         };
 
-        match self.handle {
-            Some(handle) => handle.join().unwrap(),
+        match self.handle.take() {
+            Some(handle) => {
+                if handle.join().is_err() {
+                    log::error!("Display thread panicked");
+                }
+            }
             None => panic!("State machine broke: no thread handle found"),
         }
 
@@ -114,21 +388,36 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
             tx: None,
             id: self.id,
             state: PhantomData,
+            gpio_retry: self.gpio_retry,
+            timing: self.timing,
+            panel: self.panel,
+            channel_capacity: self.channel_capacity,
+            viewport: None,
+            refresh: self.refresh,
+            pins: self.pins.take(),
         }
     }
 
     /// Pause the display thread. The display will no longer update but all data regarding
     /// its color and io pins state will remain.
-    pub fn pause(self) -> DisplayInterface<'d, Paused, W, H> {
-        match &self.tx {
-            Some(tx) => tx.send(Instruction::Pause).expect("Failed to send message"),
-            None => panic!("State machine broke: no thread handle found"),
+    ///
+    /// The display thread pauses once it has handled every instruction sent before.
+    pub fn pause(mut self) -> DisplayInterface<'d, Paused, W, H> {
+        if self.send(Instruction::Pause).is_err() {
+            log::error!("Display thread stopped before it could be paused");
         }
         DisplayInterface::<'d, Paused, W, H> {
-            handle: self.handle,
-            tx: self.tx,
+            handle: self.handle.take(),
+            tx: self.tx.take(),
             id: self.id,
             state: PhantomData,
+            gpio_retry: self.gpio_retry,
+            timing: self.timing,
+            panel: self.panel,
+            channel_capacity: self.channel_capacity,
+            viewport: self.viewport.take(),
+            refresh: self.refresh,
+            pins: self.pins.take(),
         }
     }
 
@@ -143,108 +432,2334 @@ impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
     ///
     /// Returns a `c4_display::error::Error::InvalidDim` if the length of the vectors
     /// do not match the provided width and height in the case of `SyncType::All`.
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if any led has a zero blink
+    /// interval or a blink duration larger than its blink interval.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
     pub fn sync(&mut self, sync_type: SyncType) -> error::DisplayResult<()> {
-        match &sync_type {
-            SyncType::Single(sync) => {
-                if sync.x >= W || sync.y >= H {
-                    return Err(error::Error::InvalidDim);
-                }
-            }
-            SyncType::Multi(sync_vec) => {
-                for sync in sync_vec {
-                    if sync.x >= W || sync.y >= H {
-                        return Err(error::Error::InvalidDim);
-                    }
-                }
-            }
-            SyncType::All(board) => {
-                if board.len() != H {
-                    return Err(error::Error::InvalidDim);
-                }
-                for h in board {
-                    if h.len() != W {
-                        return Err(error::Error::InvalidDim);
-                    }
-                }
-            }
-            SyncType::Rotate(_) => (),
-        }
+        validate_sync::<W, H>(&sync_type)?;
+        self.send(Instruction::Sync(sync_type))
+    }
+
+    /// Update the colors of the leds like [DisplayInterface::sync], without waiting for the
+    /// display thread if too many instructions are queued already.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::QueueFull` if the instruction queue is full, see
+    /// [DisplayInterface::with_channel_capacity].
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    ///
+    /// Returns every error of [DisplayInterface::sync] for an invalid sync.
+    pub fn try_sync(&mut self, sync_type: SyncType) -> error::DisplayResult<()> {
+        validate_sync::<W, H>(&sync_type)?;
         match &self.tx {
             Some(tx) => tx
-                .send(Instruction::Sync(sync_type))
-                .expect("Failed to send message"),
+                .try_send(Instruction::Sync(sync_type))
+                .map_err(|e| match e {
+                    TrySendError::Full(_) => Error::QueueFull,
+                    TrySendError::Disconnected(_) => Error::Disconnected,
+                }),
             None => panic!("No sender exists"),
         }
+    }
+
+    /// Set the color of the led at `x`, `y`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the position is out of bounds.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: LedColor) -> DisplayResult<()> {
+        self.set_pixel_state(x, y, LedState::with_color(color))
+    }
+
+    /// Set the state of the led at `x`, `y`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the position is out of bounds.
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if the blink interval is zero or
+    /// the blink duration is larger than the blink interval.
+    pub fn set_pixel_state(&mut self, x: usize, y: usize, state: LedState) -> DisplayResult<()> {
+        self.sync(SyncType::Single(Sync { x, y, state }))
+    }
+
+    /// Set every led on the display to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn fill(&mut self, color: LedColor) -> DisplayResult<()> {
+        self.sync(SyncType::Fill(LedState::with_color(color)))
+    }
+
+    /// Turn every led on the display off.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn clear(&mut self) -> DisplayResult<()> {
+        self.sync(SyncType::Clear)
+    }
+
+    /// Show the board of `template`, see [DisplayInterface::sync_template].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn apply_template(&mut self, template: &SyncTemplate<W, H>) -> DisplayResult<()> {
+        self.sync(template.clone().into())
+    }
+
+    /// Show a window of `canvas` the size of the display, at the current viewport.
+    ///
+    /// The viewport starts at the top left corner of the first canvas, and is kept when the
+    /// canvas is replaced. Move it with [DisplayInterface::set_viewport].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if any led of the canvas
+    /// has a blink duration larger than its blink interval.
+    pub fn set_canvas<const VW: usize, const VH: usize>(
+        &mut self,
+        canvas: &Canvas<VW, VH>,
+    ) -> DisplayResult<()> {
+        for led in canvas.iter().flatten() {
+            led.validate()?;
+        }
+
+        let origin = self
+            .viewport
+            .as_ref()
+            .map_or((0, 0), |viewport| viewport.origin);
+        self.viewport = Some(Viewport {
+            canvas: canvas.iter().map(|row| row.to_vec()).collect(),
+            origin,
+        });
+        self.set_viewport(origin.0, origin.1)?;
         Ok(())
     }
 
+    /// Move the top left corner of the shown window of the canvas to `(x, y)`, and return
+    /// where it ended up.
+    ///
+    /// Origins too far right or down are clamped so the window stays inside the canvas. A
+    /// canvas smaller than the display is shown at its top left corner, with every led
+    /// outside of it turned off.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Uninitiated` if no canvas is set, see
+    /// [DisplayInterface::set_canvas].
+    pub fn set_viewport(&mut self, x: usize, y: usize) -> DisplayResult<(usize, usize)> {
+        let viewport = self.viewport.as_mut().ok_or(Error::Uninitiated)?;
+        let board = viewport.crop::<W, H>((x, y));
+        let origin = viewport.origin;
+        self.sync(SyncType::All(board))?;
+        Ok(origin)
+    }
+
     /// Add an animation
-    pub fn add_animation(&mut self, animation: Animation) -> DisplayResult<()> {
-        for frames in &animation.frames {
-            for (x, y, _) in &frames.leds {
-                if x >= &W || y >= &H {
-                    return Err(Error::InvalidDim);
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if any led of the animation is out
+    /// of bounds.
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if any led of the animation
+    /// has a zero blink interval or a blink duration larger than its blink interval.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn add_animation(&mut self, animation: Animation) -> DisplayResult<AnimationId> {
+        validate_animation::<W, H>(&animation)?;
+
+        let id = AnimationId::next();
+        self.send(Instruction::AddAnimation(id, animation))?;
+        Ok(id)
+    }
+
+    /// Play `animation` and wait until it has finished, for at most `timeout`.
+    ///
+    /// If the animation has not finished in time it is removed from the display.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::LoopingAnimation` if the animation loops, since it
+    /// would never finish.
+    ///
+    /// Returns a `c4_display::error::Error::Timeout` if the animation has not finished in time.
+    ///
+    /// Returns a `c4_display::error::Error::UnknownAnimation` if the animation was removed
+    /// before it finished, or the display thread stopped.
+    ///
+    /// Returns every error of [DisplayInterface::add_animation] for an invalid animation.
+    pub fn play_animation_blocking(
+        &mut self,
+        animation: Animation,
+        timeout: Duration,
+    ) -> DisplayResult<()> {
+        if animation.r#loop {
+            return Err(Error::LoopingAnimation);
+        }
+        validate_animation::<W, H>(&animation)?;
+
+        let id = AnimationId::next();
+        let (done_tx, done_rx) = channel();
+        self.send(Instruction::PlayAnimation(id, animation, done_tx))?;
+
+        match done_rx.recv_timeout(timeout) {
+            Ok(()) => Ok(()),
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(tx) = &self.tx {
+                    // the thread may have stopped in the meantime
+                    let _ = tx.send(Instruction::RemoveAnimation(id));
                 }
+                Err(Error::Timeout)
             }
+            Err(RecvTimeoutError::Disconnected) => Err(Error::UnknownAnimation),
         }
+    }
 
-        match &self.tx {
-            Some(tx) => tx
-                .send(Instruction::AddAnimation(animation))
-                .expect("No receiver exists"),
-            None => panic!("No sender exists"),
+    /// Returns the active animation with the given id in the format read by
+    /// [Animation::from_file].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::UnknownAnimation` if no active animation has
+    /// the given id, for example because it has finished.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn export_animation(&self, id: AnimationId) -> DisplayResult<String> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Instruction::ExportAnimation(id, reply_tx))?;
+
+        reply_rx
+            .recv()
+            .map_err(|_| Error::Disconnected)?
+            .ok_or(Error::UnknownAnimation)
+    }
+
+    /// Returns the refresh rate the display achieves, averaged over the time between the
+    /// starts of its last runs.
+    ///
+    /// This can be lower than the configured refresh rate when the display thread does not
+    /// get enough time, for example because the system is overloaded. A synced display runs
+    /// at the rate of its clock, and a stepped one at the rate of its steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Uninitiated` if the display has not run twice
+    /// yet.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn measured_fps(&self) -> DisplayResult<f64> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Instruction::QueryFps(reply_tx))?;
+
+        reply_rx.recv().map_err(|_| Error::Uninitiated)
+    }
+
+    /// Briefly pulse the brightness of the led at `x`, `y` over `duration`.
+    ///
+    /// The led keeps its current state once the pulse has ended. See [Animation::pulse].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the position is out of bounds.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn pulse_led(&mut self, x: usize, y: usize, duration: Duration) -> DisplayResult<()> {
+        if x >= W || y >= H {
+            return Err(Error::InvalidDim);
         }
+        let state = self.snapshot()?[y][x];
+        self.add_animation(Animation::pulse(x, y, state, duration))?;
         Ok(())
     }
 
-    /// Clear all active animations
-    pub fn clear_animations(&mut self) {
-        match &self.tx {
-            Some(tx) => tx
-                .send(Instruction::ClearAnimations)
-                .expect("No receiver exists"),
-            None => panic!("No sender exists"),
+    /// Blink every led that is lit, on for `dur` out of every `int`.
+    ///
+    /// Leds that are off are left as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBlink` if `dur` is larger than `int`.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn blink_all(&mut self, dur: Duration, int: Duration) -> DisplayResult<()> {
+        let blink = BlinkInfo { dur, int };
+        LedState {
+            blink: Some(blink),
+            ..Default::default()
         }
+        .validate()?;
+        let board = lit_with_blink(self.snapshot()?.0, Some(blink));
+        self.sync(board.into())
     }
-}
 
-impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Paused, W, H> {
-    /// Resume the display thread.
-    pub fn resume(self) -> DisplayInterface<'d, Running, W, H> {
-        match &self.handle {
-            Some(handle) => handle.thread().unpark(),
-            None => panic!("No thread handle"),
-        }
+    /// Start the blink of every blinking led over now, so leds with different intervals
+    /// blink in phase, see [BlinkInfo].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn sync_blink(&mut self) -> DisplayResult<()> {
+        self.send(Instruction::SyncBlink)
+    }
 
-        DisplayInterface::<'d, Running, W, H> {
-            handle: self.handle,
-            tx: self.tx,
-            id: self.id,
-            state: PhantomData,
+    /// Only run the display when told to with [DisplayInterface::step], instead of
+    /// `refresh` times per second. A stepped display ignores the clock it was started on.
+    ///
+    /// The leds are only lit while a step runs and turned off once it has finished, so this
+    /// is meant for testing and for driving the display from an external clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn set_stepped(&mut self, stepped: bool) -> DisplayResult<()> {
+        self.send(Instruction::SetStepped(stepped))
+    }
+
+    /// Run the display once, with every instruction sent before, and wait until the run has
+    /// finished. See [DisplayInterface::set_stepped].
+    ///
+    /// A stepped display advances its animations by exactly one frame every step, however
+    /// long the frames last. A display that is not stepped returns after its next run.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn step(&mut self) -> DisplayResult<()> {
+        let (done_tx, done_rx) = channel();
+        self.send(Instruction::Step(done_tx))?;
+        done_rx.recv().map_err(|_| Error::Disconnected)
+    }
+
+    /// Stop every led from blinking, see [DisplayInterface::blink_all].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn stop_blinking_all(&mut self) -> DisplayResult<()> {
+        let mut board = self.snapshot()?.0;
+        board.iter_mut().flatten().for_each(|led| led.blink = None);
+        self.sync(board.into())
+    }
+
+    /// Crossfade the display from its current state into the colors of `target` over
+    /// `duration`, see [Animation::crossfade].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDuration` if `duration` is zero.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn fade_to(&mut self, target: [[LedColor; W]; H], duration: Duration) -> DisplayResult<()> {
+        if duration.is_zero() {
+            return Err(Error::InvalidDuration);
         }
+        let from = self.snapshot()?;
+        self.add_animation(Animation::crossfade(&from, &target, duration))?;
+        Ok(())
     }
-}
 
-impl<'d, S: State, const W: usize, const H: usize> DisplayInterface<'d, S, W, H> {
-    /// Returns the current state of the display
-    pub fn get_state(&self) -> &str {
-        stringify!(S)
+    /// Run Game of Life from `seed`, showing the next `generations` for `step` each with
+    /// alive cells in `color` before starting over, see [Life::animation].
+    ///
+    /// The current board can be used as seed with [Life::from_board] and
+    /// [DisplayInterface::snapshot].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDuration` if `step` is zero.
+    pub fn run_life(
+        &mut self,
+        seed: &Life<W, H>,
+        generations: usize,
+        color: LedColor,
+        step: Duration,
+    ) -> DisplayResult<AnimationId> {
+        if step.is_zero() {
+            return Err(Error::InvalidDuration);
+        }
+        self.add_animation(seed.animation(generations, color, step))
     }
-    /// Returns the id of the display thread
-    pub fn get_id(&self) -> &str {
-        self.id.clone()
+
+    /// Returns the current state of every led on the display, see [Snapshot].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn snapshot(&self) -> DisplayResult<Snapshot<W, H>> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Instruction::Snapshot(reply_tx))?;
+
+        let board = reply_rx.recv().map_err(|_| Error::Disconnected)?;
+        Ok(snapshot_board(&board))
     }
 
-    /// Creates an empty board with
-    pub fn sync_template() -> SyncTemplate<W, H> {
-        SyncTemplate {
-            board: [[LedColor::default(); W]; H],
+    /// The sender of the instruction queue and its capacity.
+    #[cfg(feature = "async")]
+    pub(super) fn channel(&self) -> (SyncSender<Instruction>, usize) {
+        match &self.tx {
+            Some(tx) => (tx.clone(), self.channel_capacity),
+            None => panic!("No sender exists"),
         }
     }
 
-    /// Returns the width and height of the display.
-    /// The witdh is stored in the first place and height in the second.
-    pub fn get_dim(&self) -> (usize, usize) {
-        (W, H)
+    /// Show the image at `path` on the display, with every pixel shown as the nearest
+    /// [LedColor], see [LedColor::nearest_rgb].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the image is not `W`x`H` pixels,
+    /// and a `c4_display::error::Error::Image` if the image could not be read.
+    #[cfg(feature = "image")]
+    pub fn load_image(&mut self, path: &str) -> DisplayResult<()> {
+        let board = image_board::<W, H>(&image::open(path)?.to_rgb8())?;
+        self.sync(SyncType::All(board))
+    }
+
+    /// Estimate the average current drawn by the leds in milliampere, given the current
+    /// `per_led_ma` of a single color channel of a led.
+    ///
+    /// Every lit color channel counts, scaled by the brightness and blink duty of its led.
+    /// Since only one row is lit at a time the total is divided by the number of rows.
+    /// Leds that are off are counted as dark, even if a background color is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn estimated_current_ma(&self, per_led_ma: f64) -> DisplayResult<f64> {
+        Ok(estimate_current_ma(&self.snapshot()?.0, per_led_ma))
+    }
+
+    /// Save the current state of the display as a png image at `path`, with every led drawn
+    /// as a `scale`x`scale` block of its color.
+    ///
+    /// Leds that are off are drawn black, blinking and dimming are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if `scale` is 0, and a
+    /// `c4_display::error::Error::Image` if the image could not be written.
+    #[cfg(feature = "image")]
+    pub fn save_png(&self, path: &str, scale: u32) -> DisplayResult<()> {
+        if scale == 0 {
+            return Err(Error::InvalidDim);
+        }
+        board_image(&self.snapshot()?.0, scale).save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// Start recording what the display shows, `fps` times per second.
+    ///
+    /// A recording that was already running is discarded. The recording is written with
+    /// [DisplayInterface::stop_recording].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidRefresh` if `fps` is zero.
+    #[cfg(feature = "record")]
+    pub fn start_recording(&mut self, fps: u32) -> DisplayResult<()> {
+        if fps == 0 {
+            return Err(Error::InvalidRefresh);
+        }
+        self.send(Instruction::StartRecording(Duration::from_secs(1) / fps))?;
+        Ok(())
+    }
+
+    /// Stop recording and write the recording to `path` as a looping gif, with every led
+    /// drawn as a block of 16x16 pixels.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Uninitiated` if no recording was started.
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    ///
+    /// Returns a `c4_display::error::Error::Io` if the file could not be created, and a
+    /// `c4_display::error::Error::Image` if the gif could not be written.
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&mut self, path: &str) -> DisplayResult<()> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Instruction::StopRecording(reply_tx))?;
+
+        let (interval, frames) = reply_rx.recv().map_err(|_| Error::Uninitiated)?;
+        let file = std::fs::File::create(path)?;
+        write_gif::<W, H>(file, &frames, interval, RECORDING_SCALE)
+    }
+
+    /// Remove the animation with the given id.
+    ///
+    /// Nothing happens if no active animation has the given id.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn remove_animation(&mut self, id: AnimationId) -> DisplayResult<()> {
+        self.send(Instruction::RemoveAnimation(id))
+    }
+
+    /// Freeze the animation with the given id on its current frame, while other
+    /// animations keep running.
+    ///
+    /// Nothing happens if no active animation has the given id.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn pause_animation(&mut self, id: AnimationId) -> DisplayResult<()> {
+        self.send(Instruction::PauseAnimation(id))
+    }
+
+    /// Continue the animation with the given id after [DisplayInterface::pause_animation].
+    ///
+    /// The frame it was paused on is shown again for its full duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn resume_animation(&mut self, id: AnimationId) -> DisplayResult<()> {
+        self.send(Instruction::ResumeAnimation(id))
+    }
+
+    /// Freeze every animation on its current frame, including animations added while
+    /// paused.
+    ///
+    /// Unlike [DisplayInterface::pause], the display keeps running, so syncs are still shown.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn pause_animations(&mut self) -> DisplayResult<()> {
+        self.send(Instruction::PauseAnimations)
+    }
+
+    /// Continue the animations after [DisplayInterface::pause_animations].
+    ///
+    /// The frames they were paused on are shown again for their full duration. Animations
+    /// paused with [DisplayInterface::pause_animation] stay paused.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn resume_animations(&mut self) -> DisplayResult<()> {
+        self.send(Instruction::ResumeAnimations)
+    }
+
+    /// Clear all active animations
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn clear_animations(&mut self) -> DisplayResult<()> {
+        self.send(Instruction::ClearAnimations)
+    }
+
+    /// Play every animation at `speed` times its pace, `2.0` shows every frame for half its
+    /// duration. The default is `1.0`.
+    ///
+    /// The speed is clamped to the range from `0.01` to `100.0` and applies to animations that
+    /// are already running as well, including the frames they are showing.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn set_animation_speed(&mut self, speed: f64) -> DisplayResult<()> {
+        let speed = match speed.is_nan() {
+            true => 1.0,
+            false => speed.clamp(MIN_ANIMATION_SPEED, MAX_ANIMATION_SPEED),
+        };
+        self.send(Instruction::SetAnimationSpeed(speed))
+    }
+
+    /// Run the display at `refresh` Hz, without losing the board or animations.
+    ///
+    /// The new rate is also used by [DisplayInterface::restart].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidRefresh` if the rate is not a positive
+    /// number.
+    pub fn set_refresh(&mut self, refresh: f64) -> DisplayResult<()> {
+        if !(refresh.is_finite() && refresh > 0.0) {
+            return Err(Error::InvalidRefresh);
+        }
+        self.send(Instruction::SetRefresh(refresh))?;
+        self.refresh = Some(refresh);
+        Ok(())
+    }
+
+    /// Correct the brightness of dimmed leds for a display with the given `gamma`, which is
+    /// [DEFAULT_GAMMA](crate::DEFAULT_GAMMA) by default.
+    ///
+    /// The eye perceives brightness nonlinearly, so a led at half brightness is on for less
+    /// than half of the time to look half as bright. A gamma of `1.0` turns the correction
+    /// off. This only affects leds dimmed through [LedState::brightness], leds at full
+    /// brightness are always on.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidGamma` if the gamma is not a positive
+    /// number.
+    pub fn set_gamma(&mut self, gamma: f64) -> DisplayResult<()> {
+        if !(gamma.is_finite() && gamma > 0.0) {
+            return Err(Error::InvalidGamma);
+        }
+        self.send(Instruction::SetGamma(gamma))?;
+        Ok(())
+    }
+
+    /// Set the brightness of the display, from `0.0` (dark) to `1.0` (full brightness).
+    ///
+    /// The level is clamped to that range. Brightness is global across the matrix, since it
+    /// is applied through pwm on the output enable line shared by all shift registers. The
+    /// pwm runs at a multiple of the rate the columns are switched at, so every column is
+    /// dimmed evenly. Full brightness is restored when the display stops.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn set_brightness(&mut self, level: f64) -> DisplayResult<()> {
+        let level = match level.is_nan() {
+            true => 1.0,
+            false => level.clamp(0.0, 1.0),
+        };
+        self.send(Instruction::SetBrightness(level))
+    }
+
+    /// Show `color` on every led that is off.
+    ///
+    /// Animation frames with a background replace it when they are shown.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn set_background(&mut self, color: LedColor) -> DisplayResult<()> {
+        self.send(Instruction::SetBackground(color))
+    }
+
+    /// Show every color as the color at its encoding in `palette`, for example
+    /// [RED_GREEN_SAFE_PALETTE](crate::RED_GREEN_SAFE_PALETTE) for red-green color blindness.
+    ///
+    /// Only the rendering changes, led states and snapshots keep the logical colors.
+    /// By default every color is shown as itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Disconnected` if the display thread has stopped,
+    /// see [DisplayInterface::is_alive].
+    pub fn set_palette_map(&mut self, palette: [LedColor; 1 << COLOR_BITS]) -> DisplayResult<()> {
+        self.send(Instruction::SetPalette(palette))
+    }
+}
+
+impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Paused, W, H> {
+    /// Resume the display thread.
+    pub fn resume(mut self) -> DisplayInterface<'d, Running, W, H> {
+        if self.send(Instruction::Resume).is_err() {
+            log::error!("Display thread stopped while it was paused");
+        }
+
+        DisplayInterface::<'d, Running, W, H> {
+            handle: self.handle.take(),
+            tx: self.tx.take(),
+            id: self.id,
+            state: PhantomData,
+            gpio_retry: self.gpio_retry,
+            timing: self.timing,
+            panel: self.panel,
+            channel_capacity: self.channel_capacity,
+            viewport: self.viewport.take(),
+            refresh: self.refresh,
+            pins: self.pins.take(),
+        }
+    }
+}
+
+impl<'d, S: State, const W: usize, const H: usize> DisplayInterface<'d, S, W, H> {
+    /// Returns the current state of the display
+    pub fn get_state(&self) -> &str {
+        stringify!(S)
+    }
+    /// Returns the id of the display thread
+    pub fn get_id(&self) -> &str {
+        self.id.clone()
+    }
+
+    /// Send `instruction` to the display thread.
+    fn send(&self, instruction: Instruction) -> DisplayResult<()> {
+        match &self.tx {
+            Some(tx) => tx.send(instruction).map_err(|_| Error::Disconnected),
+            None => panic!("No sender exists"),
+        }
+    }
+
+    /// Returns whether the display thread is still running, which it is not if it has been
+    /// stopped or has panicked.
+    pub fn is_alive(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Creates an empty board with every led off, which can be sent with
+    /// [DisplayInterface::sync] once converted into a [SyncType].
+    pub fn sync_template() -> SyncTemplate<W, H> {
+        SyncTemplate {
+            board: [[LedColor::default(); W]; H],
+        }
+    }
+
+    /// Returns the width and height of the display.
+    /// The witdh is stored in the first place and height in the second.
+    pub fn get_dim(&self) -> (usize, usize) {
+        (W, H)
+    }
+
+    /// Returns every color the display is able to show.
+    pub fn supported_colors() -> &'static [LedColor] {
+        &SUPPORTED_COLORS
+    }
+}
+
+impl<'d, S: State, const W: usize, const H: usize> Drop for DisplayInterface<'d, S, W, H> {
+    /// Stop the display thread if it is still running or paused.
+    fn drop(&mut self) {
+        // a paused thread keeps receiving instructions, so it sees the stop as well
+        if let Some(tx) = self.tx.take() {
+            // the thread may already have stopped on its own
+            let _ = tx.send(Instruction::Stop);
+        }
+
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                log::error!("Display thread panicked");
+            }
+        }
+    }
+}
+
+/// Snapshot of a board sent by the display thread.
+pub(super) fn snapshot_board<const W: usize, const H: usize>(
+    board: &[Vec<LedState>],
+) -> Snapshot<W, H> {
+    let mut snapshot = [[LedState::default(); W]; H];
+    for (y, row) in board.iter().enumerate().take(H) {
+        for (x, led) in row.iter().enumerate().take(W) {
+            snapshot[y][x] = *led;
+        }
+    }
+    Snapshot(snapshot)
+}
+
+/// Multiplex `sync` on the calling thread for at least one run and until `duration` has
+/// passed, then clear the panel.
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+fn show_for<const W: usize, const H: usize, P: OutputPin>(
+    mut disp: Display<W, H, P>,
+    sync: SyncType,
+    duration: Duration,
+) -> DisplayResult<()> {
+    disp.sync(sync);
+
+    let start = std::time::Instant::now();
+    loop {
+        disp.run_once(std::time::Instant::now());
+        if start.elapsed() >= duration {
+            break;
+        }
+    }
+
+    disp.shutdown()
+}
+
+/// Check that a sync fits within the display and only contains valid led states.
+pub(super) fn validate_sync<const W: usize, const H: usize>(
+    sync_type: &SyncType,
+) -> DisplayResult<()> {
+    match sync_type {
+        SyncType::Single(sync) => {
+            if sync.x >= W || sync.y >= H {
+                return Err(Error::InvalidDim);
+            }
+            sync.state.validate()?;
+        }
+        SyncType::Multi(sync_vec) => {
+            for sync in sync_vec {
+                if sync.x >= W || sync.y >= H {
+                    return Err(Error::InvalidDim);
+                }
+                sync.state.validate()?;
+            }
+        }
+        SyncType::All(board) => {
+            if board.len() != H {
+                return Err(Error::InvalidDim);
+            }
+            for h in board {
+                if h.len() != W {
+                    return Err(Error::InvalidDim);
+                }
+                for led in h {
+                    led.validate()?;
+                }
+            }
+        }
+        SyncType::Fill(state) => state.validate()?,
+        SyncType::Clear
+        | SyncType::Rotate(_)
+        | SyncType::Flip(_)
+        | SyncType::Shift { .. }
+        | SyncType::Invert(_) => (),
+    }
+    Ok(())
+}
+
+/// Set the blink of every lit led of `board` to `blink`.
+fn lit_with_blink<const W: usize, const H: usize>(
+    mut board: [[LedState; W]; H],
+    blink: Option<BlinkInfo>,
+) -> [[LedState; W]; H] {
+    board
+        .iter_mut()
+        .flatten()
+        .filter(|led| !matches!(led.color, LedColor::Off))
+        .for_each(|led| led.blink = blink);
+    board
+}
+
+/// Check that every frame of an animation fits within the display and only contains
+/// valid led states.
+pub(super) fn validate_animation<const W: usize, const H: usize>(
+    animation: &Animation,
+) -> DisplayResult<()> {
+    for frames in &animation.frames {
+        for (x, y, state) in &frames.leds {
+            if x >= &W || y >= &H {
+                return Err(Error::InvalidDim);
+            }
+            state.validate()?;
+        }
+    }
+    Ok(())
+}
+
+/// Estimate the average current drawn by `board`, see
+/// [DisplayInterface::estimated_current_ma].
+fn estimate_current_ma<const W: usize, const H: usize>(
+    board: &[[LedState; W]; H],
+    per_led_ma: f64,
+) -> f64 {
+    let lit_channels: f64 = board
+        .iter()
+        .flatten()
+        .map(|led| {
+            let duty = match led.blink {
+                Some(blink) if !blink.int.is_zero() => {
+                    blink.dur.as_secs_f64() / blink.int.as_secs_f64()
+                }
+                _ => 1.0,
+            };
+            (led.color as u8).count_ones() as f64 * duty * led.brightness as f64 / u8::MAX as f64
+        })
+        .sum();
+    // one row is lit at a time
+    lit_channels * per_led_ma / H as f64
+}
+
+/// Draw `board` with every led as a `scale`x`scale` block, see [DisplayInterface::save_png].
+#[cfg(feature = "image")]
+fn board_image<const W: usize, const H: usize>(
+    board: &[[LedState; W]; H],
+    scale: u32,
+) -> image::RgbImage {
+    image::RgbImage::from_fn(W as u32 * scale, H as u32 * scale, |x, y| {
+        let (r, g, b) = board[(y / scale) as usize][(x / scale) as usize]
+            .color
+            .to_rgb();
+        image::Rgb([r, g, b])
+    })
+}
+
+/// Write `frames` as a looping gif showing every frame for `interval`, with every led drawn
+/// as a `scale`x`scale` block, see [DisplayInterface::stop_recording].
+#[cfg(feature = "record")]
+fn write_gif<const W: usize, const H: usize>(
+    writer: impl std::io::Write,
+    frames: &[Vec<Vec<LedColor>>],
+    interval: Duration,
+    scale: u32,
+) -> DisplayResult<()> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+
+    let mut encoder = GifEncoder::new(writer);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = image::Delay::from_saturating_duration(interval);
+    encoder.encode_frames(frames.iter().map(|colors| {
+        let image = image::RgbaImage::from_fn(W as u32 * scale, H as u32 * scale, |x, y| {
+            let (r, g, b) = colors[(y / scale) as usize][(x / scale) as usize].to_rgb();
+            image::Rgba([r, g, b, u8::MAX])
+        });
+        image::Frame::from_parts(image, 0, 0, delay)
+    }))?;
+    Ok(())
+}
+
+/// Convert every pixel of `image` to a led, see [DisplayInterface::load_image].
+#[cfg(feature = "image")]
+fn image_board<const W: usize, const H: usize>(
+    image: &image::RgbImage,
+) -> DisplayResult<Vec<Vec<LedState>>> {
+    if image.dimensions() != (W as u32, H as u32) {
+        return Err(Error::InvalidDim);
+    }
+
+    Ok(image
+        .rows()
+        .map(|row| {
+            row.map(|pixel| {
+                let [r, g, b] = pixel.0;
+                LedState::with_color(LedColor::nearest_rgb(r, g, b))
+            })
+            .collect()
+        })
+        .collect())
+}
+
+mod test_validate {
+    #[allow(unused_imports)]
+    use super::{test_interface::interface, validate_animation, validate_sync};
+    #[allow(unused_imports)]
+    use crate::{Animation, AnimationFrame, BlinkInfo, Error, LedColor, LedState, Sync, SyncType};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[allow(dead_code)]
+    fn blinking(dur: u64, int: u64) -> LedState {
+        LedState {
+            color: LedColor::Red,
+            blink: Some(BlinkInfo {
+                dur: Duration::from_millis(dur),
+                int: Duration::from_millis(int),
+            }),
+            brightness: u8::MAX,
+        }
+    }
+
+    #[test]
+    fn valid_blink() {
+        let sync = SyncType::Single(Sync {
+            x: 0,
+            y: 0,
+            state: blinking(100, 200),
+        });
+        assert!(validate_sync::<7, 7>(&sync).is_ok());
+    }
+
+    #[test]
+    fn blink_dur_larger_than_int() {
+        let sync = SyncType::Multi(vec![Sync {
+            x: 0,
+            y: 0,
+            state: blinking(300, 200),
+        }]);
+        assert!(matches!(
+            validate_sync::<7, 7>(&sync),
+            Err(Error::InvalidBlink)
+        ));
+    }
+
+    #[test]
+    fn zero_blink_int() {
+        let (mut disp, rx) = interface();
+        let sync = SyncType::Single(Sync {
+            x: 0,
+            y: 0,
+            state: blinking(0, 0),
+        });
+        assert!(matches!(disp.sync(sync), Err(Error::InvalidBlink)));
+
+        let frame = AnimationFrame::new(
+            Duration::from_millis(100),
+            vec![(1, 1, blinking(0, 0))],
+            false,
+        );
+        let animation = Animation::new(false, vec![frame], 0, false);
+        assert!(matches!(
+            disp.add_animation(animation),
+            Err(Error::InvalidBlink)
+        ));
+        // nothing reaches the display thread
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn animation_blink_dur_larger_than_int() {
+        let frame = AnimationFrame::new(
+            Duration::from_millis(100),
+            vec![(1, 1, blinking(300, 200))],
+            false,
+        );
+        let animation = Animation::new(false, vec![frame], 0, false);
+        assert!(matches!(
+            validate_animation::<7, 7>(&animation),
+            Err(Error::InvalidBlink)
+        ));
+    }
+
+    #[test]
+    fn add_animation_rejects_invalid_blink() {
+        let (mut disp, rx) = interface();
+        let frames = vec![
+            AnimationFrame::new(
+                Duration::from_millis(100),
+                vec![(1, 1, blinking(100, 200))],
+                true,
+            ),
+            AnimationFrame::new(
+                Duration::from_millis(100),
+                vec![(2, 2, blinking(300, 200))],
+                true,
+            ),
+        ];
+        let animation = Animation::new(false, frames, 0, false);
+        assert!(matches!(
+            disp.add_animation(animation),
+            Err(Error::InvalidBlink)
+        ));
+        // nothing reaches the display thread
+        assert!(rx.try_recv().is_err());
+    }
+}
+
+mod test_drop {
+    #[allow(unused_imports)]
+    use super::{DisplayInterface, Instruction, DEFAULT_CHANNEL_CAPACITY};
+    #[allow(unused_imports)]
+    use crate::{PinConfig, Running};
+    #[allow(unused_imports)]
+    use std::{
+        marker::PhantomData,
+        sync::mpsc::{channel, sync_channel, Receiver},
+        thread,
+    };
+
+    /// Create an interface with a thread that reports when it receives a stop instruction.
+    #[allow(dead_code)]
+    fn interface() -> (DisplayInterface<'static, Running, 7, 7>, Receiver<()>) {
+        let (tx, rx) = sync_channel(DEFAULT_CHANNEL_CAPACITY);
+        let (stopped_tx, stopped_rx) = channel();
+        let handle = thread::spawn(move || loop {
+            match rx.recv() {
+                Ok(Instruction::Stop) => {
+                    stopped_tx.send(()).unwrap();
+                    break;
+                }
+                Ok(_) => (),
+                Err(_) => break,
+            }
+        });
+        let disp = DisplayInterface {
+            handle: Some(handle),
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: Some(60.0),
+            pins: Some(PinConfig {
+                sr_serin: 17,
+                sr_srclk: 22,
+                sr_rclk: 23,
+                sr_srclr: 24,
+                sr_oe: 27,
+                dec_a0: 25,
+                dec_a1: 11,
+                dec_a2: 5,
+                dec_a3: None,
+                dec_le: 6,
+                dec_e1: 10,
+            }),
+        };
+        (disp, stopped_rx)
+    }
+
+    #[test]
+    fn drop_running() {
+        let (disp, stopped) = interface();
+        drop(disp);
+        assert!(stopped.try_recv().is_ok());
+    }
+
+    #[test]
+    fn drop_paused() {
+        let (disp, stopped) = interface();
+        drop(disp.pause());
+        assert!(stopped.try_recv().is_ok());
+    }
+
+    #[test]
+    fn rapid_pause_resume() {
+        let (mut disp, stopped) = interface();
+        for _ in 0..100 {
+            disp = disp.pause().resume();
+        }
+        // the thread only sees the stop if it is running again
+        drop(disp);
+        assert!(stopped.try_recv().is_ok());
+    }
+
+    #[test]
+    fn stop_remembers_config() {
+        let (disp, _stopped) = interface();
+        let disp = disp.pause().resume().stop();
+        assert_eq!(disp.refresh, Some(60.0));
+        assert!(matches!(
+            disp.pins,
+            Some(PinConfig {
+                sr_serin: 17,
+                dec_e1: 10,
+                ..
+            })
+        ));
+
+        // a restart starts a new display thread with the same configuration
+        #[cfg(feature = "mock")]
+        {
+            let disp = disp.restart();
+            assert!(disp.is_alive());
+            assert_eq!(disp.refresh, Some(60.0));
+            assert!(matches!(
+                disp.pins,
+                Some(PinConfig {
+                    sr_serin: 17,
+                    dec_e1: 10,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn stop_then_drop() {
+        let (disp, stopped) = interface();
+        drop(disp.stop());
+        assert!(stopped.try_recv().is_ok());
+    }
+}
+
+mod test_snapshot {
+    #[allow(unused_imports)]
+    use super::{DisplayInterface, Instruction, DEFAULT_CHANNEL_CAPACITY};
+    #[allow(unused_imports)]
+    use crate::{Error, LedColor, LedState, Running};
+    #[allow(unused_imports)]
+    use std::{marker::PhantomData, sync::mpsc::sync_channel, thread};
+
+    #[test]
+    fn snapshot() {
+        let (tx, rx) = sync_channel(DEFAULT_CHANNEL_CAPACITY);
+        let handle = thread::spawn(move || {
+            while let Ok(instruction) = rx.recv() {
+                match instruction {
+                    Instruction::Snapshot(reply) => {
+                        let mut board = vec![vec![LedState::default(); 7]; 7];
+                        board[2][5] = LedState::with_color(LedColor::Cyan);
+                        reply.send(board).unwrap();
+                    }
+                    Instruction::Stop => break,
+                    _ => (),
+                }
+            }
+        });
+        let disp = DisplayInterface::<Running, 7, 7> {
+            handle: Some(handle),
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: None,
+            pins: None,
+        };
+
+        let snapshot = disp.snapshot().unwrap();
+        assert!(matches!(snapshot[2][5].color, LedColor::Cyan));
+        assert!(matches!(snapshot[5][2].color, LedColor::Off));
+    }
+
+    #[test]
+    fn disconnected() {
+        let (tx, _) = sync_channel(DEFAULT_CHANNEL_CAPACITY);
+        let disp = DisplayInterface::<Running, 7, 7> {
+            handle: None,
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: None,
+            pins: None,
+        };
+
+        assert!(matches!(disp.snapshot(), Err(Error::Disconnected)));
+    }
+}
+
+mod test_export_animation {
+    #[allow(unused_imports)]
+    use super::{AnimationId, DisplayInterface, Instruction, DEFAULT_CHANNEL_CAPACITY};
+    #[allow(unused_imports)]
+    use crate::{Animation, Error, Running};
+    #[allow(unused_imports)]
+    use std::{marker::PhantomData, str::FromStr, sync::mpsc::sync_channel, thread};
+
+    #[test]
+    fn export_round_trip() {
+        let (tx, rx) = sync_channel(DEFAULT_CHANNEL_CAPACITY);
+        let handle = thread::spawn(move || {
+            let mut animations = Vec::new();
+            while let Ok(instruction) = rx.recv() {
+                match instruction {
+                    Instruction::AddAnimation(id, animation) => animations.push((id, animation)),
+                    Instruction::ExportAnimation(id, reply) => {
+                        let text = animations
+                            .iter()
+                            .find(|(animation_id, _)| *animation_id == id)
+                            .map(|(_, animation): &(_, Animation)| animation.to_string());
+                        reply.send(text).unwrap();
+                    }
+                    Instruction::Stop => break,
+                    _ => (),
+                }
+            }
+        });
+        let mut disp = DisplayInterface::<Running, 7, 7> {
+            handle: Some(handle),
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: None,
+            pins: None,
+        };
+
+        let text = "animation\nloop true\nrepeats 0\nkeep_last false\n\n\
+                    frame\ndur 50\nrst true\n2 0 white\n3 0 white 100 200\n";
+        let first = disp
+            .add_animation(Animation::from_str(text).unwrap())
+            .unwrap();
+        let second = disp
+            .add_animation(Animation::pulse(
+                1,
+                1,
+                Default::default(),
+                Default::default(),
+            ))
+            .unwrap();
+        assert_ne!(first, second);
+
+        let exported = disp.export_animation(first).unwrap();
+        let reparsed = Animation::from_str(exported.as_str()).unwrap();
+        assert_eq!(reparsed.to_string(), text);
+
+        assert!(matches!(
+            disp.export_animation(AnimationId::next()),
+            Err(Error::UnknownAnimation)
+        ));
+    }
+}
+
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+mod test_show_once {
+    #[allow(unused_imports)]
+    use super::{show_for, DisplayInterface, SHOW_ONCE_REFRESH};
+    #[allow(unused_imports)]
+    use crate::{
+        display::{test_output_pin::Trace, Display},
+        BlinkInfo, Error, LedColor, LedState, PinConfig, Stopped, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn shifts_out_before_shutdown() {
+        let trace = Trace::default();
+        let disp = Display::<7, 7, _>::with_pins(
+            SHOW_ONCE_REFRESH,
+            trace.pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let mut board = vec![vec![LedState::default(); 7]; 7];
+        board[1][1] = LedState::with_color(LedColor::Red);
+        trace.clear();
+        show_for(disp, SyncType::All(board), Duration::ZERO).unwrap();
+
+        // the lit led is shifted in and latched before the outputs are disabled for good
+        let events = trace.events();
+        let shifted = events
+            .iter()
+            .position(|&event| event == (Trace::SR_SERIN, true))
+            .unwrap();
+        let latched = shifted
+            + events[shifted..]
+                .iter()
+                .position(|&event| event == (Trace::SR_RCLK, true))
+                .unwrap();
+        let shutdown = events
+            .iter()
+            .rposition(|&event| event == (Trace::SR_OE, true))
+            .unwrap();
+        assert!(latched < shutdown);
+        assert_eq!(trace.level(Trace::SR_OE), Some(true));
+        assert_eq!(trace.level(Trace::DEC_E1), Some(true));
+    }
+
+    #[test]
+    fn invalid_blink_before_init() {
+        let mut board = [[LedState::default(); 7]; 7];
+        board[1][1] = LedState {
+            color: LedColor::Red,
+            blink: Some(BlinkInfo {
+                dur: Duration::from_millis(300),
+                int: Duration::from_millis(200),
+            }),
+            brightness: u8::MAX,
+        };
+        let pins = PinConfig {
+            sr_serin: 17,
+            sr_srclk: 22,
+            sr_rclk: 23,
+            sr_srclr: 24,
+            sr_oe: 27,
+            dec_a0: 25,
+            dec_a1: 11,
+            dec_a2: 5,
+            dec_a3: None,
+            dec_le: 6,
+            dec_e1: 10,
+        };
+
+        assert!(matches!(
+            DisplayInterface::<Stopped, 7, 7>::show_once(board, pins, Default::default()),
+            Err(Error::InvalidBlink)
+        ));
+    }
+}
+
+pub(super) mod test_interface {
+    #[allow(unused_imports)]
+    use super::{DisplayInterface, Instruction, DEFAULT_CHANNEL_CAPACITY};
+    #[allow(unused_imports)]
+    use crate::Running;
+    #[allow(unused_imports)]
+    use std::{
+        marker::PhantomData,
+        sync::mpsc::{sync_channel, Receiver},
+    };
+
+    /// Create a running interface without a display thread.
+    /// The returned receiver gets every instruction sent by the interface.
+    #[allow(dead_code)]
+    pub(crate) fn interface() -> (
+        DisplayInterface<'static, Running, 7, 7>,
+        Receiver<Instruction>,
+    ) {
+        interface_with_capacity(DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// Like [interface], with room for `capacity` instructions in the queue.
+    #[allow(dead_code)]
+    pub(crate) fn interface_with_capacity(
+        capacity: usize,
+    ) -> (
+        DisplayInterface<'static, Running, 7, 7>,
+        Receiver<Instruction>,
+    ) {
+        let (tx, rx) = sync_channel(capacity);
+        let disp = DisplayInterface {
+            handle: None,
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: capacity,
+            viewport: None,
+            refresh: None,
+            pins: None,
+        };
+        (disp, rx)
+    }
+}
+
+mod test_set_pixel {
+    #[allow(unused_imports)]
+    use super::{test_interface::interface, Instruction};
+    #[allow(unused_imports)]
+    use crate::{Error, LedColor, Sync, SyncType};
+
+    #[test]
+    fn set_pixel() {
+        let (mut disp, rx) = interface();
+        disp.set_pixel(3, 4, LedColor::Green).unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::Sync(SyncType::Single(Sync { x: 3, y: 4, state }))) => {
+                assert!(matches!(state.color, LedColor::Green));
+                assert!(state.blink.is_none());
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.set_pixel(7, 0, LedColor::Red),
+            Err(Error::InvalidDim)
+        ));
+        assert!(matches!(
+            disp.set_pixel(0, 7, LedColor::Red),
+            Err(Error::InvalidDim)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn fill() {
+        let (mut disp, rx) = interface();
+        disp.fill(LedColor::Cyan).unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::Sync(SyncType::Fill(state))) => {
+                assert!(matches!(state.color, LedColor::Cyan));
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+
+        disp.clear().unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Instruction::Sync(SyncType::Clear))
+        ));
+    }
+
+    #[test]
+    fn brightness_is_clamped() {
+        let (mut disp, rx) = interface();
+        for (level, expected) in [(0.25, 0.25), (1.5, 1.0), (-0.5, 0.0), (f64::NAN, 1.0)] {
+            disp.set_brightness(level).unwrap();
+            match rx.try_recv() {
+                Ok(Instruction::SetBrightness(sent)) => assert_eq!(sent, expected),
+                other => panic!("unexpected instruction: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn pulse_out_of_bounds() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.pulse_led(7, 0, std::time::Duration::from_millis(100)),
+            Err(Error::InvalidDim)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+}
+
+mod test_fade_to {
+    #[allow(unused_imports)]
+    use super::{test_interface::interface, DisplayInterface};
+    #[allow(unused_imports)]
+    use crate::{display::test_output_pin::null_pins, Error, LedColor, LedState, Stopped};
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn without_duration() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.fade_to([[LedColor::Red; 7]; 7], Duration::ZERO),
+            Err(Error::InvalidDuration)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn ends_on_target() {
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id")
+            .try_start_with_pins(1_000.0, null_pins())
+            .unwrap();
+        disp.fade_to([[LedColor::Red; 7]; 7], Duration::from_millis(20))
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        let board = disp.snapshot().unwrap();
+        assert!(board
+            .iter()
+            .flatten()
+            .all(|led| *led == LedState::with_color(LedColor::Red)));
+    }
+}
+
+mod test_run_life {
+    #[allow(unused_imports)]
+    use super::{test_interface::interface, Instruction};
+    #[allow(unused_imports)]
+    use crate::{
+        life::{Edges, Life},
+        Error, LedColor,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn without_duration() {
+        let (mut disp, rx) = interface();
+        let life = Life::new([[true; 7]; 7], Edges::Wrap);
+        assert!(matches!(
+            disp.run_life(&life, 4, LedColor::Green, Duration::ZERO),
+            Err(Error::InvalidDuration)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn adds_animation() {
+        let (mut disp, rx) = interface();
+        let life = Life::new([[true; 7]; 7], Edges::Wrap);
+        disp.run_life(&life, 4, LedColor::Green, Duration::from_millis(200))
+            .unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Instruction::AddAnimation(_, _))));
+    }
+}
+
+mod test_supported_colors {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::Stopped;
+
+    #[test]
+    fn all_colors() {
+        let colors = DisplayInterface::<Stopped, 7, 7>::supported_colors();
+        assert_eq!(colors.len(), 8);
+        for (i, color) in colors.iter().enumerate() {
+            assert_eq!(*color as usize, i);
+        }
+    }
+}
+
+#[cfg(all(feature = "rppal", not(any(feature = "simulator", feature = "mock"))))]
+mod test_try_start {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::{Error, FrameClock, PinConfig, Stopped};
+
+    #[test]
+    fn unavailable_pins() {
+        let pins = PinConfig {
+            sr_serin: 200,
+            sr_srclk: 201,
+            sr_rclk: 202,
+            sr_srclr: 203,
+            sr_oe: 204,
+            dec_a0: 205,
+            dec_a1: 206,
+            dec_a2: 207,
+            dec_a3: None,
+            dec_le: 208,
+            dec_e1: 209,
+        };
+        let result = DisplayInterface::<Stopped, 7, 7>::new("id").try_start(60.0, pins.clone());
+        // without a gpio chip no pin is tried, otherwise the first pin is reported
+        assert!(matches!(
+            result,
+            Err(Error::Gpio(_))
+                | Err(Error::PinInit {
+                    role: "sr_serin",
+                    pin: 200,
+                    ..
+                })
+        ));
+
+        let result = DisplayInterface::<Stopped, 7, 7>::new("id").try_start_synced(
+            60.0,
+            pins,
+            &FrameClock::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::Gpio(_)) | Err(Error::PinInit { pin: 200, .. })
+        ));
+    }
+}
+
+#[cfg(all(feature = "mock", not(feature = "simulator")))]
+mod test_mock {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::{LedColor, LedState, PinConfig, Stopped};
+
+    #[test]
+    fn start_with_capacity() {
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id").start_with_capacity(
+            120.0,
+            PinConfig::default(),
+            2,
+        );
+        assert_eq!(disp.channel_capacity, 2);
+        disp.fill(LedColor::Red).unwrap();
+        // the capacity is kept for a restart
+        assert_eq!(disp.stop().restart().channel_capacity, 2);
+    }
+
+    #[test]
+    fn runs_without_gpio() {
+        let mut disp =
+            DisplayInterface::<Stopped, 7, 7>::new("id").start(120.0, PinConfig::default());
+        disp.fill(LedColor::Red).unwrap();
+        let board = disp.snapshot().unwrap();
+        assert!(board
+            .iter()
+            .flatten()
+            .all(|led| matches!(led.color, LedColor::Red)));
+        disp.stop();
+    }
+
+    #[test]
+    fn plays_macro_animation() {
+        let mut disp =
+            DisplayInterface::<Stopped, 7, 7>::new("id").start(120.0, PinConfig::default());
+        disp.add_animation(crate::animation! {
+            loop false
+            repeats 0
+            keep_last true
+            frame {
+                dur 10
+                rst true
+                0 6 red
+            }
+            frame {
+                dur 10
+                rst false
+                6 6 cyan blink 100 200
+            }
+        })
+        .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let board = disp.snapshot().unwrap();
+        assert_eq!(board[6][0], LedState::default());
+        assert_eq!(board[6][6].color, LedColor::Cyan);
+        disp.stop();
+    }
+}
+
+mod test_is_alive {
+    #[allow(unused_imports)]
+    use super::{DisplayInterface, Instruction, DEFAULT_CHANNEL_CAPACITY};
+    #[allow(unused_imports)]
+    use crate::{
+        Animation, AnimationFrame, Error, LedColor, LedState, PinConfig, Running, Stopped, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::{
+        marker::PhantomData,
+        sync::mpsc::{channel, sync_channel},
+        thread,
+        time::Duration,
+    };
+
+    /// Create an interface with a display thread that panics on its first instruction.
+    #[allow(dead_code)]
+    fn fragile_interface() -> DisplayInterface<'static, Running, 7, 7> {
+        let (tx, rx) = sync_channel::<Instruction>(DEFAULT_CHANNEL_CAPACITY);
+        let handle = thread::spawn(move || {
+            if rx.recv().is_ok() {
+                panic!("display thread crashed");
+            }
+        });
+        DisplayInterface {
+            handle: Some(handle),
+            tx: Some(tx),
+            state: PhantomData,
+            id: "id",
+            gpio_retry: Default::default(),
+            timing: Default::default(),
+            panel: Default::default(),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            viewport: None,
+            refresh: None,
+            pins: None,
+        }
+    }
+
+    #[test]
+    fn dead_display() {
+        let mut disp = fragile_interface();
+        assert!(disp.is_alive());
+
+        disp.sync(SyncType::Clear).unwrap();
+        while disp.is_alive() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(matches!(
+            disp.sync(SyncType::Clear),
+            Err(Error::Disconnected)
+        ));
+        let frame = AnimationFrame::new(
+            Duration::from_millis(100),
+            vec![(3, 3, LedState::with_color(LedColor::Blue))],
+            true,
+        );
+        assert!(matches!(
+            disp.add_animation(Animation::new(false, vec![frame], 0, false)),
+            Err(Error::Disconnected)
+        ));
+        assert!(matches!(disp.clear_animations(), Err(Error::Disconnected)));
+        assert!(matches!(disp.snapshot(), Err(Error::Disconnected)));
+
+        // stopping a display whose thread is gone does not panic
+        assert!(!disp.stop().is_alive());
+    }
+
+    #[test]
+    fn pause_dead_display() {
+        let mut disp = fragile_interface();
+        disp.clear().unwrap();
+        while disp.is_alive() {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut disp = disp.pause().resume();
+        assert!(matches!(disp.stop_blinking_all(), Err(Error::Disconnected)));
+    }
+
+    #[test]
+    fn stopped_display() {
+        assert!(!DisplayInterface::<Stopped, 7, 7>::new("id").is_alive());
+    }
+}
+
+mod test_step {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{null_pins, Trace},
+        Animation, AnimationFrame, LedColor, LedState, Stopped,
+    };
+    #[allow(unused_imports)]
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[allow(dead_code)]
+    fn frame(x: usize, color: LedColor, duration: Duration) -> AnimationFrame {
+        AnimationFrame::new(duration, vec![(x, 0, LedState::with_color(color))], true)
+    }
+
+    #[test]
+    fn one_frame_per_step() {
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id")
+            .try_start_with_pins(1_000.0, null_pins())
+            .unwrap();
+        disp.set_stepped(true).unwrap();
+        // frames far longer than the test, which a step does not wait for
+        let frames = [LedColor::Red, LedColor::Green, LedColor::Blue]
+            .into_iter()
+            .enumerate()
+            .map(|(x, color)| frame(x, color, Duration::from_secs(60)))
+            .collect();
+        disp.add_animation(Animation::new(false, frames, 0, true))
+            .unwrap();
+
+        // nothing runs without a step
+        assert_eq!(disp.snapshot().unwrap()[0], [LedState::default(); 7]);
+
+        for (x, color) in [LedColor::Red, LedColor::Green, LedColor::Blue]
+            .into_iter()
+            .enumerate()
+        {
+            disp.step().unwrap();
+            let row = disp.snapshot().unwrap()[0];
+            assert_eq!(row[x], LedState::with_color(color), "step {x}");
+            // the previous frame was reset
+            assert_eq!(
+                row.iter().filter(|led| led.color != LedColor::Off).count(),
+                1
+            );
+        }
+
+        // the animation has ended and keeps its last frame
+        disp.step().unwrap();
+        disp.step().unwrap();
+        assert_eq!(
+            disp.snapshot().unwrap()[0][2],
+            LedState::with_color(LedColor::Blue)
+        );
+    }
+
+    #[test]
+    fn dark_between_steps() {
+        let trace = Trace::default();
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id")
+            .try_start_with_pins(1_000.0, trace.pins())
+            .unwrap();
+        disp.set_stepped(true).unwrap();
+        disp.step().unwrap();
+
+        trace.clear();
+        disp.step().unwrap();
+        // the outputs were enabled during the run, and are disabled (high) once it is done
+        assert!(trace.levels(Trace::SR_OE).contains(&false));
+        assert_eq!(trace.level(Trace::SR_OE), Some(true));
+    }
+
+    #[test]
+    fn free_running_step() {
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id")
+            .try_start_with_pins(1_000.0, null_pins())
+            .unwrap();
+        disp.set_stepped(true).unwrap();
+        let frames = [LedColor::Red, LedColor::Green]
+            .into_iter()
+            .enumerate()
+            .map(|(x, color)| frame(x, color, Duration::from_millis(1)))
+            .collect();
+        disp.add_animation(Animation::new(false, frames, 0, true))
+            .unwrap();
+        disp.step().unwrap();
+        assert_eq!(
+            disp.snapshot().unwrap()[0][0],
+            LedState::with_color(LedColor::Red)
+        );
+
+        // once it is not stepped anymore the display runs on its own again, and a step only
+        // waits for its next run
+        disp.set_stepped(false).unwrap();
+        disp.step().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while disp.snapshot().unwrap()[0][1] != LedState::with_color(LedColor::Green) {
+            assert!(Instant::now() < deadline, "the animation did not advance");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+mod test_estimate_current {
+    #[allow(unused_imports)]
+    use super::estimate_current_ma;
+    #[allow(unused_imports)]
+    use crate::{BlinkInfo, LedColor, LedState};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn white_board() {
+        let board = [[LedState::with_color(LedColor::White); 7]; 7];
+        // 49 leds with 3 channels each, one of 7 rows lit at a time
+        assert_eq!(estimate_current_ma(&board, 20.0), 49.0 * 3.0 * 20.0 / 7.0);
+    }
+
+    #[test]
+    fn dark_board() {
+        let board = [[LedState::default(); 7]; 7];
+        assert_eq!(estimate_current_ma(&board, 20.0), 0.0);
+    }
+
+    #[test]
+    fn blink_and_brightness() {
+        let mut board = [[LedState::default(); 2]; 2];
+        board[0][0] = LedState {
+            color: LedColor::Red,
+            blink: Some(BlinkInfo {
+                dur: Duration::from_millis(250),
+                int: Duration::from_millis(500),
+            }),
+            brightness: u8::MAX,
+        };
+        board[1][1] = LedState {
+            brightness: 0,
+            ..LedState::with_color(LedColor::White)
+        };
+        assert_eq!(estimate_current_ma(&board, 10.0), 0.5 * 10.0 / 2.0);
+    }
+}
+
+mod test_start_with_pins {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::{display::test_output_pin::null_pins, LedColor, Stopped};
+
+    #[test]
+    fn runs_on_custom_pins() {
+        let mut disp = DisplayInterface::<Stopped, 7, 7>::new("id")
+            .try_start_with_pins(60.0, null_pins())
+            .unwrap();
+        disp.set_pixel(2, 3, LedColor::Yellow).unwrap();
+        assert!(matches!(
+            disp.snapshot().unwrap()[3][2].color,
+            LedColor::Yellow
+        ));
+
+        let disp = disp.stop();
+        assert_eq!(disp.refresh, Some(60.0));
+        assert!(disp.pins.is_none());
+    }
+}
+
+#[cfg(feature = "image")]
+mod test_board_image {
+    #[allow(unused_imports)]
+    use super::board_image;
+    #[allow(unused_imports)]
+    use crate::{LedColor, LedState};
+
+    #[test]
+    fn scaled_blocks() {
+        let mut board = [[LedState::default(); 3]; 2];
+        board[1][2] = LedState::with_color(LedColor::Yellow);
+        board[0][1] = LedState::with_color(LedColor::Cyan);
+
+        let image = board_image(&board, 4);
+        assert_eq!(image.dimensions(), (12, 8));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(4, 3).0, [0, 255, 255]);
+        assert_eq!(image.get_pixel(8, 4).0, [255, 255, 0]);
+        assert_eq!(image.get_pixel(11, 7).0, [255, 255, 0]);
+        assert_eq!(image.get_pixel(7, 4).0, [0, 0, 0]);
+    }
+}
+
+#[cfg(feature = "record")]
+mod test_write_gif {
+    #[allow(unused_imports)]
+    use super::write_gif;
+    #[allow(unused_imports)]
+    use crate::LedColor;
+    #[allow(unused_imports)]
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+    #[allow(unused_imports)]
+    use std::{io::Cursor, time::Duration};
+
+    #[test]
+    fn frames() {
+        let mut red = vec![vec![LedColor::Off; 3]; 2];
+        red[1][2] = LedColor::Red;
+        let blue = vec![vec![LedColor::Blue; 3]; 2];
+
+        let mut gif = Vec::new();
+        write_gif::<3, 2>(&mut gif, &[red, blue], Duration::from_millis(100), 4).unwrap();
+
+        let frames = GifDecoder::new(Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].buffer().dimensions(), (12, 8));
+        assert_eq!(frames[0].buffer().get_pixel(9, 5).0, [255, 0, 0, 255]);
+        assert_eq!(frames[0].buffer().get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(frames[1].buffer().get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(
+            Duration::from(frames[1].delay()),
+            Duration::from_millis(100)
+        );
+    }
+}
+
+#[cfg(feature = "image")]
+mod test_image_board {
+    #[allow(unused_imports)]
+    use super::{board_image, image_board};
+    #[allow(unused_imports)]
+    use crate::{Error, LedColor, LedState};
+
+    #[test]
+    fn exact_colors() {
+        let mut board = [[LedState::default(); 3]; 2];
+        board[0][2] = LedState::with_color(LedColor::Magenta);
+        board[1][0] = LedState::with_color(LedColor::White);
+
+        let loaded = image_board::<3, 2>(&board_image(&board, 1)).unwrap();
+        for (row, loaded_row) in board.iter().zip(&loaded) {
+            for (led, loaded_led) in row.iter().zip(loaded_row) {
+                assert_eq!(led.color as u8, loaded_led.color as u8);
+            }
+        }
+    }
+
+    #[test]
+    fn off_palette_colors() {
+        let image = image::RgbImage::from_fn(2, 1, |x, _| match x {
+            0 => image::Rgb([200, 30, 10]),
+            _ => image::Rgb([20, 160, 220]),
+        });
+        let loaded = image_board::<2, 1>(&image).unwrap();
+        assert!(matches!(loaded[0][0].color, LedColor::Red));
+        assert!(matches!(loaded[0][1].color, LedColor::Cyan));
+    }
+
+    #[test]
+    fn wrong_size() {
+        let image = image::RgbImage::new(3, 3);
+        assert!(matches!(
+            image_board::<3, 2>(&image),
+            Err(Error::InvalidDim)
+        ));
+    }
+}
+
+mod test_start_synced {
+    #[allow(unused_imports)]
+    use super::DisplayInterface;
+    #[allow(unused_imports)]
+    use crate::{
+        display::{test_output_pin::Trace, Display},
+        FrameClock, Running,
+    };
+    #[allow(unused_imports)]
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Start a display on `clock`, with the trace of its pins.
+    #[allow(dead_code)]
+    fn synced_display(clock: &FrameClock) -> (DisplayInterface<'static, Running, 7, 7>, Trace) {
+        let trace = Trace::default();
+        let disp = Display::<7, 7, _>::with_pins(
+            1_000.0,
+            trace.pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        let disp = DisplayInterface::new("synced").spawn(disp, 1_000.0, None, Some(clock.clone()));
+        (disp, trace)
+    }
+
+    /// Number of rows latched by the display recorded in `trace`.
+    #[allow(dead_code)]
+    fn rows(trace: &Trace) -> usize {
+        trace.count_high(Trace::DEC_LE)
+    }
+
+    #[test]
+    fn runs_in_step() {
+        let clock = FrameClock::new();
+        let (first, first_trace) = synced_display(&clock);
+        let (second, second_trace) = synced_display(&clock);
+
+        // no run happens without a tick
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(rows(&first_trace), 0);
+        assert_eq!(rows(&second_trace), 0);
+
+        for ticks in 1..=3 {
+            clock.tick();
+            let start = Instant::now();
+            while (rows(&first_trace) < ticks * 7 || rows(&second_trace) < ticks * 7)
+                && start.elapsed() < Duration::from_secs(1)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            // both displays ran exactly once for every tick
+            thread::sleep(Duration::from_millis(10));
+            assert_eq!(rows(&first_trace), ticks * 7);
+            assert_eq!(rows(&second_trace), ticks * 7);
+        }
+
+        first.stop();
+        second.stop();
+    }
+}
+
+mod test_try_sync {
+    #[allow(unused_imports)]
+    use super::test_interface::interface_with_capacity;
+    #[allow(unused_imports)]
+    use crate::{Error, LedState, Sync, SyncType};
+
+    #[allow(dead_code)]
+    fn single() -> SyncType {
+        SyncType::Single(Sync {
+            x: 0,
+            y: 0,
+            state: LedState::default(),
+        })
+    }
+
+    #[test]
+    fn full_queue() {
+        let (mut disp, rx) = interface_with_capacity(1);
+        disp.try_sync(single()).unwrap();
+        assert!(matches!(disp.try_sync(single()), Err(Error::QueueFull)));
+
+        // taking the queued instruction makes room again
+        rx.try_recv().unwrap();
+        disp.try_sync(single()).unwrap();
+    }
+
+    #[test]
+    fn invalid_sync() {
+        let (mut disp, rx) = interface_with_capacity(1);
+        let sync = SyncType::Single(Sync {
+            x: 7,
+            y: 0,
+            state: LedState::default(),
+        });
+        assert!(matches!(disp.try_sync(sync), Err(Error::InvalidDim)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn stopped_thread() {
+        let (mut disp, rx) = interface_with_capacity(1);
+        drop(rx);
+        assert!(matches!(disp.try_sync(single()), Err(Error::Disconnected)));
+    }
+}
+
+mod test_play_animation_blocking {
+    #[allow(unused_imports)]
+    use super::test_interface::interface;
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, Animation, AnimationFrame, Error, LedState};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[allow(dead_code)]
+    fn animation(r#loop: bool) -> Animation {
+        let frame = AnimationFrame::new(
+            Duration::from_millis(10),
+            vec![(0, 0, LedState::default())],
+            true,
+        );
+        Animation::new(r#loop, vec![frame], 0, false)
+    }
+
+    #[test]
+    fn rejects_looping() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.play_animation_blocking(animation(true), Duration::from_millis(10)),
+            Err(Error::LoopingAnimation)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn timeout_removes_animation() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.play_animation_blocking(animation(false), Duration::from_millis(10)),
+            Err(Error::Timeout)
+        ));
+        let played = match rx.try_recv() {
+            Ok(Instruction::PlayAnimation(id, _, _)) => id,
+            other => panic!("unexpected instruction: {other:?}"),
+        };
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Instruction::RemoveAnimation(id)) if id == played
+        ));
+    }
+}
+
+mod test_animation_speed {
+    #[allow(unused_imports)]
+    use super::test_interface::interface;
+    #[allow(unused_imports)]
+    use crate::display::Instruction;
+
+    #[test]
+    fn clamped() {
+        let (mut disp, rx) = interface();
+        for speed in [2.0, 0.0, -1.0, 1e9, f64::NAN] {
+            disp.set_animation_speed(speed).unwrap();
+        }
+        let sent: Vec<f64> = rx
+            .try_iter()
+            .map(|instruction| match instruction {
+                Instruction::SetAnimationSpeed(speed) => speed,
+                other => panic!("unexpected instruction: {other:?}"),
+            })
+            .collect();
+        assert_eq!(sent, [2.0, 0.01, 0.01, 100.0, 1.0]);
+    }
+}
+
+mod test_set_refresh {
+    #[allow(unused_imports)]
+    use super::test_interface::interface;
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, Error};
+
+    #[test]
+    fn valid_rate() {
+        let (mut disp, rx) = interface();
+        disp.set_refresh(120.0).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Instruction::SetRefresh(refresh)) if refresh == 120.0));
+        assert_eq!(disp.refresh, Some(120.0));
+    }
+
+    #[test]
+    fn invalid_rate() {
+        let (mut disp, rx) = interface();
+        for refresh in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                disp.set_refresh(refresh),
+                Err(Error::InvalidRefresh)
+            ));
+        }
+        assert!(rx.try_recv().is_err());
+        assert_eq!(disp.refresh, None);
+    }
+}
+
+mod test_measured_fps {
+    #[allow(unused_imports)]
+    use super::test_interface::interface;
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, Error};
+    #[allow(unused_imports)]
+    use std::thread;
+
+    #[test]
+    fn replied() {
+        let (disp, rx) = interface();
+        let handle = thread::spawn(move || match rx.recv() {
+            Ok(Instruction::QueryFps(reply)) => reply.send(59.5).unwrap(),
+            other => panic!("unexpected instruction: {other:?}"),
+        });
+        assert_eq!(disp.measured_fps().unwrap(), 59.5);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn no_run_finished() {
+        let (disp, rx) = interface();
+        // the reply is dropped without an answer
+        let handle = thread::spawn(move || drop(rx.recv()));
+        assert!(matches!(disp.measured_fps(), Err(Error::Uninitiated)));
+        handle.join().unwrap();
+    }
+}
+
+mod test_set_gamma {
+    #[allow(unused_imports)]
+    use super::test_interface::interface;
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, Error};
+
+    #[test]
+    fn valid_gamma() {
+        let (mut disp, rx) = interface();
+        disp.set_gamma(1.8).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Instruction::SetGamma(gamma)) if gamma == 1.8));
+    }
+
+    #[test]
+    fn invalid_gamma() {
+        let (mut disp, rx) = interface();
+        for gamma in [0.0, -2.2, f64::NAN, f64::INFINITY] {
+            assert!(matches!(disp.set_gamma(gamma), Err(Error::InvalidGamma)));
+        }
+        assert!(rx.try_recv().is_err());
+    }
+}
+
+mod test_blink_all {
+    #[allow(unused_imports)]
+    use super::{lit_with_blink, test_interface::interface};
+    #[allow(unused_imports)]
+    use crate::{BlinkInfo, Error, LedColor, LedState};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[test]
+    fn lit_cells_only() {
+        let mut board = [[LedState::default(); 3]; 2];
+        board[0][2] = LedState::with_color(LedColor::Red);
+        board[1][0] = LedState::with_color(LedColor::Blue);
+        let blink = BlinkInfo {
+            dur: Duration::from_millis(100),
+            int: Duration::from_millis(300),
+        };
+
+        let board = lit_with_blink(board, Some(blink));
+        for (y, row) in board.iter().enumerate() {
+            for (x, led) in row.iter().enumerate() {
+                let lit = (x, y) == (2, 0) || (x, y) == (0, 1);
+                assert_eq!(led.blink.is_some(), lit, "{x}, {y}");
+            }
+        }
+        assert_eq!(board[0][2].blink.unwrap().int, blink.int);
+    }
+
+    #[test]
+    fn invalid_blink() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.blink_all(Duration::from_millis(300), Duration::from_millis(100)),
+            Err(Error::InvalidBlink)
+        ));
+        assert!(rx.try_recv().is_err());
     }
 }
@@ -1,170 +1,1024 @@
-use crate::{display::Display, display::Instruction, LedState, Sync, SyncType};
+use crate::{
+    display::{Display, FrameClock, Instruction, OutputPin},
+    LedState, Sync, SyncType,
+};
 use std::{
-    sync::mpsc::{Receiver, TryRecvError},
-    thread,
-    time::Instant,
+    sync::mpsc::{Receiver, Sender, TryRecvError},
+    time::{Duration, Instant},
 };
 
-use super::animation::Animation;
+use super::animation::{Animation, AnimationId};
+
+/// Longest time to wait for a tick of the frame clock before handling instructions again.
+const FRAME_CLOCK_TIMEOUT: Duration = Duration::from_millis(100);
+/// Number of runs the measured run time is roughly averaged over.
+const RUN_TIME_AVERAGE: f64 = 32.0;
+/// Time between two logs of the measured refresh rate.
+#[cfg(feature = "metrics")]
+const FPS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What ended the handling of queued instructions.
+#[derive(Debug, PartialEq, Eq)]
+enum Drained {
+    /// Every queued instruction was handled.
+    Empty,
+    /// The display was paused and has to wait until it is resumed.
+    Paused,
+    /// The display was stopped or the interface is gone.
+    Stopped,
+}
 
-pub(super) struct DisplayManager<const W: usize, const H: usize> {
-    disp: Display<W, H>,
+pub(super) struct DisplayManager<const W: usize, const H: usize, P: OutputPin> {
+    disp: Display<W, H, P>,
     rx: Receiver<Instruction>,
-    animations: Vec<Animation>,
+    animations: Vec<(AnimationId, Animation)>,
+    clock: Option<(FrameClock, u64)>, // shared clock and the last tick it was run for
+    stepped: bool,                    // only runs when a step is asked for
+    steps: Vec<Sender<()>>,           // notified once the next run has finished
+    animations_paused: bool,          // freezes every animation on its current frame
+    on_finish: Vec<(AnimationId, Sender<()>)>, // notified once the animation has finished
+    animation_speed: f64,             // multiplies the pace of every animation
+    run_time: Option<f64>, // rolling average of the seconds between two runs, none before
+    last_run: Option<Instant>, // start of the previous run, none after a pause
+    #[cfg(feature = "metrics")]
+    fps_window: (Instant, u32), // start of the current fps log interval and the runs since
+    #[cfg(feature = "record")]
+    recording: Option<Recording>,
 }
 
-impl<const W: usize, const H: usize> DisplayManager<W, H> {
+/// Frames recorded of what the display shows.
+#[cfg(feature = "record")]
+struct Recording {
+    interval: Duration, // time between two frames
+    next: Instant,      // time the next frame is recorded at
+    frames: Vec<Vec<Vec<crate::LedColor>>>,
+}
+
+impl<const W: usize, const H: usize, P: OutputPin> DisplayManager<W, H, P> {
     /// Create a new `DisplayManager` with the given `Display` and `Receiver`.
-    pub(super) fn new(disp: Display<W, H>, rx: Receiver<Instruction>) -> Self {
+    pub(super) fn new(disp: Display<W, H, P>, rx: Receiver<Instruction>) -> Self {
         Self {
             disp,
             rx,
             animations: Vec::new(),
+            clock: None,
+            stepped: false,
+            steps: Vec::new(),
+            animations_paused: false,
+            on_finish: Vec::new(),
+            animation_speed: 1.0,
+            run_time: None,
+            last_run: None,
+            #[cfg(feature = "metrics")]
+            fps_window: (Instant::now(), 0),
+            #[cfg(feature = "record")]
+            recording: None,
         }
     }
 
+    /// Wait for a tick of `clock` before every run instead of running freely.
+    pub(super) fn synced(mut self, clock: FrameClock) -> Self {
+        self.clock = Some((clock, 0));
+        self
+    }
+
     /// Start the display.
     pub(super) fn start(&mut self) {
-        'outer: loop {
+        loop {
+            // instructions are still handled while the clock is silent
+            // a stepped display runs on its steps instead of the clock
+            let ticked = match &mut self.clock {
+                Some(_) if self.stepped => true,
+                Some((clock, last)) => clock.wait(last, FRAME_CLOCK_TIMEOUT),
+                None => true,
+            };
+            let waiting = self.stepped && self.steps.is_empty();
             let start_time = std::time::Instant::now();
-            // get new sync instructions
-            'inner: loop {
-                match self.rx.try_recv() {
-                    Ok(msg) => match msg {
-                        Instruction::Pause => {
-                            thread::park();
-                            continue 'outer;
-                        }
-                        Instruction::Stop => break 'outer,
-                        Instruction::Sync(sync_type) => self.disp.sync(sync_type),
-                        Instruction::AddAnimation(animation) => self.animations.push(animation),
-                        Instruction::ClearAnimations => self.animations.clear(),
-                    },
-                    Err(TryRecvError::Empty) => break 'inner,
-                    Err(TryRecvError::Disconnected) => {
-                        log::error!("Display interface disconnected. Stopping thread...");
-                        break 'outer;
-                    }
-                }
+            match self.handle_instructions(waiting) {
+                Drained::Empty => {}
+                Drained::Paused => match self.wait_for_resume() {
+                    true => continue,
+                    false => break,
+                },
+                Drained::Stopped => break,
             }
 
+            if !ticked || (self.stepped && self.steps.is_empty()) {
+                continue;
+            }
+            // a step starts its run once it is asked for, rather than when waiting began
+            let start_time = match waiting {
+                true => std::time::Instant::now(),
+                false => start_time,
+            };
+
+            self.record_run_start(start_time);
+
             // update display with animations
-            // newer animations will override older ones if they affect the same leds
-            // TODO refactor into methods, this is unreadable
-            // TODO remove flicker at end of restarting animations that occurs because last frame is cleared and next frame only gets loaded on cycle later
-            for animation in &mut self.animations {
-                let prev_frame = if animation.activeframe > 0 {
-                    Some(animation.frames[animation.activeframe - 1].clone())
-                } else {
-                    None
-                };
-
-                match animation.frames.get_mut(animation.activeframe) {
-                    Some(frame) => {
-                        // the first time the frame is run a start time is assigned
-                        // the frame is written to the display
-                        if let None = frame.start_time {
-                            frame.start_time = Some(Instant::now());
-
-                            if let Some(frame) = prev_frame {
-                                if frame.rst_after {
-                                    for (x, y, _) in &frame.leds {
-                                        self.disp.sync(SyncType::Single(Sync {
-                                            x: *x,
-                                            y: *y,
-                                            state: LedState::default(),
-                                        }));
-                                    }
-                                }
-                            }
-
-                            for (x, y, state) in &frame.leds {
-                                self.disp.sync(SyncType::Single(Sync {
-                                    x: *x,
-                                    y: *y,
-                                    state: *state,
-                                }));
-                            }
-                        }
-
-                        match frame.finished() {
-                            // if the frame has finished, move on to the next frame
-                            // a frame is finished when start_time + frame_duration > current_time
-                            Ok(finished) if finished => {
-                                // set leds affected by the frame to Off if reset_frame is set to true
-                                // if frame.rst_after {
-                                //     for (x, y, _) in &frame.leds {
-                                //         self.disp.sync(SyncType::Single(Sync {
-                                //             x: *x,
-                                //             y: *y,
-                                //             state: LedState::default(),
-                                //         }));
-                                //     }
-                                // }
-                                animation.next_frame()
-                            }
-                            // if the frame hasn't finished, do nothing
-                            Ok(_) => (),
-                            Err(_) => panic!("No start time exists"),
-                        }
+            self.update_animations();
+
+            // run multiplexing
+            self.disp.run_once(start_time);
+            // a stepped display may wait for its next step indefinitely, and would keep
+            // the last column lit at full duty in the meantime
+            if self.stepped {
+                self.disp.disable_row();
+            }
+
+            #[cfg(feature = "record")]
+            self.record_frame();
+
+            #[cfg(feature = "metrics")]
+            self.log_fps();
+
+            for done in self.steps.drain(..) {
+                // the interface may have stopped waiting for the run
+                let _ = done.send(());
+            }
+        }
+    }
+
+    /// Block until the display is resumed. Returns `false` if it was stopped instead, or the
+    /// interface is gone.
+    fn wait_for_resume(&mut self) -> bool {
+        loop {
+            match self.rx.recv() {
+                Ok(Instruction::Resume) => {
+                    // the pause does not count as time between two runs
+                    self.last_run = None;
+                    return true;
+                }
+                Ok(Instruction::Stop) | Err(_) => return false,
+                // a paused interface can only resume or stop the display
+                Ok(instruction) => log::warn!("Ignoring {instruction:?} while paused"),
+            }
+        }
+    }
+
+    /// Add the time since the start of the previous run to the rolling average.
+    fn record_run_start(&mut self, start_time: Instant) {
+        if let Some(previous) = self.last_run.replace(start_time) {
+            self.record_run(start_time.saturating_duration_since(previous));
+        }
+    }
+
+    /// Add the time between two runs to the rolling average.
+    fn record_run(&mut self, run_time: Duration) {
+        let run_time = run_time.as_secs_f64();
+        self.run_time = Some(match self.run_time {
+            Some(average) => average + (run_time - average) / RUN_TIME_AVERAGE,
+            None => run_time,
+        });
+    }
+
+    /// Record the colors the display shows if a frame of the recording is due.
+    #[cfg(feature = "record")]
+    fn record_frame(&mut self) {
+        if let Some(recording) = &mut self.recording {
+            let now = Instant::now();
+            if now >= recording.next {
+                recording.frames.push(self.disp.shown_colors());
+                // frames that were missed are skipped instead of recorded back to back
+                while recording.next <= now {
+                    recording.next += recording.interval;
+                }
+            }
+        }
+    }
+
+    /// Count a run, and log the measured refresh rate once every [FPS_LOG_INTERVAL].
+    #[cfg(feature = "metrics")]
+    fn log_fps(&mut self) {
+        let (since, runs) = &mut self.fps_window;
+        *runs += 1;
+        let elapsed = since.elapsed();
+        if elapsed >= FPS_LOG_INTERVAL {
+            log::debug!(
+                "measured refresh rate: {:.1} Hz, {} rows overran their time",
+                *runs as f64 / elapsed.as_secs_f64(),
+                self.disp.take_overruns()
+            );
+            self.fps_window = (Instant::now(), 0);
+        }
+    }
+
+    /// Handle every queued instruction, so updates sent between two runs are all shown by
+    /// the next one.
+    ///
+    /// Stops at a pause or stop instruction, instructions queued after a pause are handled
+    /// once the display is resumed. If `wait` is set, the first instruction is waited for.
+    fn handle_instructions(&mut self, mut wait: bool) -> Drained {
+        loop {
+            let received = match wait {
+                true => self.rx.recv().map_err(|_| TryRecvError::Disconnected),
+                false => self.rx.try_recv(),
+            };
+            wait = false;
+            let msg = match received {
+                Ok(msg) => msg,
+                Err(TryRecvError::Empty) => return Drained::Empty,
+                Err(TryRecvError::Disconnected) => {
+                    log::error!("Display interface disconnected. Stopping thread...");
+                    return Drained::Stopped;
+                }
+            };
+            match msg {
+                Instruction::Pause => return Drained::Paused,
+                // the display is not paused
+                Instruction::Resume => (),
+                Instruction::Stop => return Drained::Stopped,
+                Instruction::Sync(sync_type) => self.disp.sync(sync_type),
+                Instruction::AddAnimation(id, animation) => self.add_animation(id, animation),
+                Instruction::PlayAnimation(id, animation, done) => {
+                    self.add_animation(id, animation);
+                    self.on_finish.push((id, done));
+                }
+                Instruction::RemoveAnimation(id) => self.remove_animation(id),
+                Instruction::PauseAnimation(id) => self.set_animation_paused(id, true),
+                Instruction::ResumeAnimation(id) => self.set_animation_paused(id, false),
+                Instruction::PauseAnimations => self.animations_paused = true,
+                Instruction::ResumeAnimations => self.resume_animations(),
+                Instruction::SetAnimationSpeed(speed) => self.animation_speed = speed,
+                Instruction::ClearAnimations => {
+                    self.animations.clear();
+                    self.on_finish.clear();
+                }
+                Instruction::Snapshot(reply) => {
+                    // the interface may have stopped waiting for the reply
+                    let _ = reply.send(self.disp.board());
+                }
+                Instruction::QueryFps(reply) => {
+                    // dropping the reply tells the interface no run has finished yet
+                    if let Some(run_time) = self.run_time {
+                        let _ = reply.send(1.0 / run_time);
+                    }
+                }
+                Instruction::ExportAnimation(id, reply) => {
+                    let text = self
+                        .animations
+                        .iter()
+                        .find(|(animation_id, _)| *animation_id == id)
+                        .map(|(_, animation)| animation.to_string());
+                    // the interface may have stopped waiting for the reply
+                    let _ = reply.send(text);
+                }
+                Instruction::SetPalette(palette) => self.disp.set_palette_map(palette),
+                Instruction::SetBackground(color) => self.disp.set_background(color),
+                Instruction::SetRefresh(refresh) => self.disp.set_refresh(refresh),
+                Instruction::SetGamma(gamma) => self.disp.set_gamma(gamma),
+                Instruction::SyncBlink => self.disp.set_blink_epoch(None),
+                Instruction::SetStepped(stepped) => self.stepped = stepped,
+                Instruction::Step(done) => self.steps.push(done),
+                #[cfg(feature = "record")]
+                Instruction::StartRecording(interval) => {
+                    self.recording = Some(Recording {
+                        interval,
+                        next: Instant::now(),
+                        frames: Vec::new(),
+                    })
+                }
+                #[cfg(feature = "record")]
+                Instruction::StopRecording(reply) => {
+                    if let Some(recording) = self.recording.take() {
+                        // the interface may have stopped waiting for the reply
+                        let _ = reply.send((recording.interval, recording.frames));
+                    }
+                }
+                Instruction::SetBrightness(level) => {
+                    if let Err(e) = self.disp.set_brightness(level) {
+                        log::error!("Failed to set brightness: {e:?}");
                     }
-                    // if no frame is returned, the animation has finished
-                    None => animation.finished = true,
                 }
+            }
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, P: OutputPin> DisplayManager<W, H, P> {
+    /// Advance the animations and write their frames to the display.
+    ///
+    /// Newer animations will override older ones if they affect the same leds.
+    /// A frame that finishes is replaced by the next one in the same call, so the display
+    /// never runs with the leds of a reset frame cleared but the next frame not drawn yet.
+    fn update_animations(&mut self) {
+        if self.animations_paused {
+            return;
+        }
+        for (_, animation) in &mut self.animations {
+            // paused animations keep showing their current frame
+            if animation.paused {
+                continue;
+            }
 
-                if animation.finished
-                    && animation
-                        .frames
-                        .last()
-                        .expect("No frames in animation")
-                        .rst_after
+            match animation.frames.get(animation.activeframe) {
+                // the first time the frame is run it is written to the display
+                Some(frame) if frame.start_time.is_none() => {
+                    start_frame(&mut self.disp, animation, None)
+                }
+                // a frame is finished when start_time + frame_duration > current_time,
+                // or after a single run of a stepped display
+                Some(frame)
+                    if self.stepped || frame.finished(self.animation_speed).unwrap_or(true) =>
                 {
-                    for (x, y, _) in &animation.frames.last().unwrap().leds {
-                        self.disp.sync(SyncType::Single(Sync {
-                            x: *x,
-                            y: *y,
-                            state: LedState::default(),
-                        }));
+                    let prev = animation.activeframe;
+                    match animation.next_frame() {
+                        true => start_frame(&mut self.disp, animation, Some(prev)),
+                        false => end_play(&mut self.disp, animation),
                     }
                 }
+                // if the frame hasn't finished, do nothing
+                Some(_) => (),
+                None => end_play(&mut self.disp, animation),
+            }
+        }
 
-                // remove finished flag for repeating animations
-                match animation.finished {
-                    true if animation.r#loop => animation.rst(),
-                    true if animation.repeats > 0 => animation.rst(),
-
-                    _ => (),
+        // remove finished animations
+        // self.animations.retain(|animation| !animation.finished);
+        self.animations.retain(|(id, animation)| {
+            if animation.finished {
+                notify_finished(&mut self.on_finish, *id);
+            }
+            if animation.finished && animation.keep_last {
+                // the active frame is the last one played
+                for (x, y, state) in &animation
+                    .frames
+                    .get(animation.activeframe)
+                    .expect("No frames in animation")
+                    .leds
+                {
+                    self.disp.sync(SyncType::Single(Sync {
+                        x: *x,
+                        y: *y,
+                        state: *state,
+                    }));
                 }
             }
+            !animation.finished
+        });
+    }
 
-            // remove finished animations
-            // self.animations.retain(|animation| !animation.finished);
-            self.animations.retain(|animation| {
-                if animation.finished && animation.keep_last {
-                    for (x, y, state) in &animation
-                        .frames
-                        .last()
-                        .expect("No frames in animation")
-                        .leds
-                    {
-                        self.disp.sync(SyncType::Single(Sync {
-                            x: *x,
-                            y: *y,
-                            state: *state,
-                        }));
-                    }
+    /// Pause or resume the animation with the given id.
+    ///
+    /// A resumed animation shows its current frame again for the full frame duration.
+    fn set_animation_paused(&mut self, id: AnimationId, paused: bool) {
+        for (_, animation) in self
+            .animations
+            .iter_mut()
+            .filter(|(animation_id, _)| *animation_id == id)
+        {
+            if animation.paused && !paused {
+                if let Some(frame) = animation.frames.get_mut(animation.activeframe) {
+                    frame.start_time = None;
                 }
-                !animation.finished
-            });
+            }
+            animation.paused = paused;
+        }
+    }
 
-            // run multiplexing
-            self.disp.run_once(start_time);
+    /// Continue every animation after they were all paused.
+    ///
+    /// The current frames are shown again for their full duration.
+    fn resume_animations(&mut self) {
+        if !self.animations_paused {
+            return;
         }
+        self.animations_paused = false;
+        for (_, animation) in &mut self.animations {
+            if let Some(frame) = animation.frames.get_mut(animation.activeframe) {
+                frame.start_time = None;
+            }
+        }
+    }
+
+    /// Add an animation, starting at its first frame in playback order.
+    fn add_animation(&mut self, id: AnimationId, mut animation: Animation) {
+        // deserialized animations don't know their first frame yet
+        animation.rst_frame_ctr();
+        self.animations.push((id, animation));
+    }
+
+    /// Remove the animation with the given id, without touching the leds it has set.
+    fn remove_animation(&mut self, id: AnimationId) {
+        self.animations
+            .retain(|(animation_id, _)| *animation_id != id);
+        // whoever waits for it learns that it will not finish
+        self.on_finish
+            .retain(|(animation_id, _)| *animation_id != id);
     }
 }
 
-impl<const W: usize, const H: usize> Drop for DisplayManager<W, H> {
+impl<const W: usize, const H: usize, P: OutputPin> Drop for DisplayManager<W, H, P> {
     fn drop(&mut self) {
-        self.disp.clear_row();
+        if let Err(e) = self.disp.shutdown() {
+            log::error!("Failed to blank display: {e:?}");
+        }
+    }
+}
+
+/// Write the active frame of `animation` to the display and start its timer.
+///
+/// The leds of the frame at index `prev` are turned off first if it resets after it ends.
+fn start_frame<const W: usize, const H: usize, P: OutputPin>(
+    disp: &mut Display<W, H, P>,
+    animation: &mut Animation,
+    prev: Option<usize>,
+) {
+    if let Some(prev) = prev.and_then(|prev| animation.frames.get(prev)) {
+        if prev.rst_after {
+            clear_leds(disp, &prev.leds);
+        }
+    }
+
+    let frame = &mut animation.frames[animation.activeframe];
+    frame.start_time = Some(Instant::now());
+    if let Some(bg) = frame.bg {
+        disp.set_background(bg);
+    }
+    for (x, y, state) in &frame.leds {
+        disp.sync(SyncType::Single(Sync {
+            x: *x,
+            y: *y,
+            state: *state,
+        }));
+    }
+}
+
+/// Finish a play of `animation` after its last frame ended, and start the next play right
+/// away if it loops or has repeats left.
+fn end_play<const W: usize, const H: usize, P: OutputPin>(
+    disp: &mut Display<W, H, P>,
+    animation: &mut Animation,
+) {
+    animation.finished = true;
+
+    // the active frame is the last one played
+    if let Some(last) = animation.frames.get(animation.activeframe) {
+        if last.rst_after {
+            clear_leds(disp, &last.leds);
+        }
+    }
+
+    // repeats counts every play, including the one that just finished
+    if animation.r#loop || animation.repeats > 1 {
+        animation.rst();
+        start_frame(disp, animation, None);
+    }
+}
+
+/// Tell whoever waits for the animation with the given id that it has finished.
+fn notify_finished(on_finish: &mut Vec<(AnimationId, Sender<()>)>, id: AnimationId) {
+    on_finish.retain(|(animation_id, done)| {
+        if *animation_id != id {
+            return true;
+        }
+        // the interface may have stopped waiting
+        let _ = done.send(());
+        false
+    });
+}
+
+/// Turn off the given leds.
+fn clear_leds<const W: usize, const H: usize, P: OutputPin>(
+    disp: &mut Display<W, H, P>,
+    leds: &[(usize, usize, LedState)],
+) {
+    for (x, y, _) in leds {
+        disp.sync(SyncType::Single(Sync {
+            x: *x,
+            y: *y,
+            state: LedState::default(),
+        }));
+    }
+}
+
+mod test_manager {
+    #[allow(unused_imports)]
+    use super::DisplayManager;
+    #[allow(unused_imports)]
+    use crate::{
+        display::{
+            test_output_pin::{null_pins, NullPin},
+            Display, Instruction,
+        },
+        Animation, AnimationFrame,
+    };
+    #[allow(unused_imports)]
+    use std::{
+        sync::mpsc::{channel, Sender},
+        time::Duration,
+    };
+
+    /// A manager of a 7 by 7 display running at 60Hz on pins that are not connected, and
+    /// the sender of its instructions.
+    #[allow(dead_code)]
+    pub(super) fn manager() -> (DisplayManager<7, 7, NullPin>, Sender<Instruction>) {
+        let (tx, rx) = channel();
+        let disp = Display::<7, 7, _>::with_pins(
+            60.0,
+            null_pins(),
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+        (DisplayManager::new(disp, rx), tx)
+    }
+
+    /// An animation moving along the first row forever, one led per millisecond.
+    #[allow(dead_code)]
+    pub(super) fn looping() -> Animation {
+        let frames = (0..4)
+            .map(|x| {
+                AnimationFrame::new(
+                    Duration::from_millis(1),
+                    vec![(x, 0, Default::default())],
+                    false,
+                )
+            })
+            .collect();
+        Animation::new(true, frames, 0, false)
+    }
+}
+
+mod test_remove_animation {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{display::AnimationId, Animation};
+
+    #[test]
+    fn only_removes_given_id() {
+        let (mut manager, _tx) = manager();
+
+        let ids = [
+            AnimationId::next(),
+            AnimationId::next(),
+            AnimationId::next(),
+        ];
+        for id in ids {
+            manager
+                .animations
+                .push((id, Animation::new(true, Vec::new(), 0, false)));
+        }
+
+        manager.remove_animation(ids[1]);
+        let remaining: Vec<AnimationId> = manager.animations.iter().map(|(id, _)| *id).collect();
+        assert_eq!(remaining, [ids[0], ids[2]]);
+
+        // unknown ids are ignored
+        manager.remove_animation(ids[1]);
+        assert_eq!(manager.animations.len(), 2);
+    }
+}
+
+mod test_pause_animation {
+    #[allow(unused_imports)]
+    use super::test_manager::{looping, manager};
+    #[allow(unused_imports)]
+    use crate::display::AnimationId;
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn paused_animation_does_not_advance() {
+        let (mut manager, _tx) = manager();
+        let (paused, running) = (AnimationId::next(), AnimationId::next());
+        manager.animations.push((paused, looping()));
+        manager.animations.push((running, looping()));
+
+        // start the first frame of both
+        manager.update_animations();
+        manager.set_animation_paused(paused, true);
+        let (paused_frame, running_frame) = (
+            manager.animations[0].1.activeframe,
+            manager.animations[1].1.activeframe,
+        );
+        for _ in 0..2 {
+            thread::sleep(Duration::from_millis(2));
+            manager.update_animations();
+        }
+        assert_eq!(manager.animations[0].1.activeframe, paused_frame);
+        assert_ne!(manager.animations[1].1.activeframe, running_frame);
+
+        manager.set_animation_paused(paused, false);
+        assert!(manager.animations[0].1.frames[paused_frame]
+            .start_time
+            .is_none());
+        manager.update_animations();
+        thread::sleep(Duration::from_millis(2));
+        manager.update_animations();
+        assert_ne!(manager.animations[0].1.activeframe, paused_frame);
+    }
+}
+
+mod test_pause_animations {
+    #[allow(unused_imports)]
+    use super::test_manager::{looping, manager};
+    #[allow(unused_imports)]
+    use crate::{
+        display::{AnimationId, Instruction},
+        LedColor, LedState, Sync, SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn frames_do_not_advance() {
+        let (mut manager, tx) = manager();
+        manager.animations.push((AnimationId::next(), looping()));
+        manager.update_animations();
+
+        tx.send(Instruction::PauseAnimations).unwrap();
+        tx.send(Instruction::Sync(SyncType::Single(Sync {
+            x: 6,
+            y: 6,
+            state: LedState::with_color(LedColor::Red),
+        })))
+        .unwrap();
+        manager.handle_instructions(false);
+        let frame = manager.animations[0].1.activeframe;
+        for _ in 0..2 {
+            thread::sleep(Duration::from_millis(2));
+            manager.update_animations();
+        }
+        assert_eq!(manager.animations[0].1.activeframe, frame);
+        assert_eq!(manager.animations.len(), 1);
+        // syncs are still applied
+        assert!(matches!(manager.disp.board()[6][6].color, LedColor::Red));
+
+        tx.send(Instruction::ResumeAnimations).unwrap();
+        manager.handle_instructions(false);
+        manager.update_animations();
+        thread::sleep(Duration::from_millis(2));
+        manager.update_animations();
+        assert_ne!(manager.animations[0].1.activeframe, frame);
+    }
+}
+
+mod test_repeats {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{display::AnimationId, Animation, AnimationFrame, LedState};
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    /// Count how often the last frame of an animation with `repeats` is shown.
+    #[allow(dead_code)]
+    fn plays(repeats: usize) -> usize {
+        let (mut manager, _tx) = manager();
+        let frames = (0..2)
+            .map(|x| {
+                AnimationFrame::new(
+                    Duration::from_millis(1),
+                    vec![(x, 0, LedState::default())],
+                    false,
+                )
+            })
+            .collect();
+        manager.animations.push((
+            AnimationId::next(),
+            Animation::new(false, frames, repeats, false),
+        ));
+
+        let mut plays = 0;
+        while let Some((_, animation)) = manager.animations.first() {
+            let shown = animation.frames[1].start_time.is_some();
+            manager.update_animations();
+            match manager.animations.first() {
+                Some((_, animation)) if !shown && animation.frames[1].start_time.is_some() => {
+                    plays += 1
+                }
+                _ => (),
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        plays
+    }
+
+    #[test]
+    fn plays_repeats_times() {
+        assert_eq!(plays(3), 3);
+        assert_eq!(plays(1), 1);
+        assert_eq!(plays(0), 1);
+    }
+}
+
+mod test_frame_boundary {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{display::AnimationId, Animation, AnimationFrame, LedColor, LedState};
+    #[allow(unused_imports)]
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn never_all_off() {
+        let (mut manager, _tx) = manager();
+        // every frame resets its led, and the animation restarts after the last one
+        let frames = (0..3)
+            .map(|x| {
+                let led = LedState::with_color(LedColor::Green);
+                AnimationFrame::new(Duration::from_millis(1), vec![(x, 0, led)], true)
+            })
+            .collect();
+        manager
+            .animations
+            .push((AnimationId::next(), Animation::new(true, frames, 0, false)));
+
+        for _ in 0..20 {
+            manager.update_animations();
+            let board = manager.disp.board();
+            let lit = board
+                .iter()
+                .flatten()
+                .filter(|led| !matches!(led.color, LedColor::Off))
+                .count();
+            assert_eq!(lit, 1);
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+}
+
+mod test_frame_bg {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{display::AnimationId, Animation, AnimationFrame, LedColor};
+    #[allow(unused_imports)]
+    use std::{str::FromStr, thread, time::Duration};
+
+    #[test]
+    fn applied_when_frame_starts() {
+        let (mut manager, _tx) = manager();
+        let frames = vec![
+            AnimationFrame::from_str("frame\ndur 1\nrst false\n3 3 red").unwrap(),
+            AnimationFrame::from_str("frame\ndur 100\nrst false\nbg blue\n3 3 red").unwrap(),
+        ];
+        manager
+            .animations
+            .push((AnimationId::next(), Animation::new(false, frames, 0, false)));
+
+        // the first frame has no background of its own
+        manager.update_animations();
+        assert_eq!(manager.disp.background(), LedColor::Off);
+
+        thread::sleep(Duration::from_millis(2));
+        manager.update_animations();
+        assert_eq!(manager.animations[0].1.activeframe, 1);
+        assert_eq!(manager.disp.background(), LedColor::Blue);
+    }
+}
+
+mod test_handle_instructions {
+    #[allow(unused_imports)]
+    use super::{test_manager::manager, Drained};
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, LedColor, LedState, Sync, SyncType};
+
+    #[allow(dead_code)]
+    fn red(x: usize) -> Instruction {
+        Instruction::Sync(SyncType::Single(Sync {
+            x,
+            y: 0,
+            state: LedState::with_color(LedColor::Red),
+        }))
+    }
+
+    #[allow(dead_code)]
+    fn red_leds(board: &[Vec<LedState>]) -> usize {
+        board[0]
+            .iter()
+            .filter(|led| matches!(led.color, LedColor::Red))
+            .count()
+    }
+
+    #[test]
+    fn applies_all_queued_syncs() {
+        let (mut manager, tx) = manager();
+
+        for x in 0..3 {
+            tx.send(red(x)).unwrap();
+        }
+        assert_eq!(manager.handle_instructions(false), Drained::Empty);
+        assert_eq!(red_leds(&manager.disp.board()), 3);
+    }
+
+    #[test]
+    fn stops_at_stop() {
+        let (mut manager, tx) = manager();
+
+        tx.send(red(0)).unwrap();
+        tx.send(Instruction::Stop).unwrap();
+        tx.send(red(1)).unwrap();
+        assert_eq!(manager.handle_instructions(false), Drained::Stopped);
+        assert_eq!(red_leds(&manager.disp.board()), 1);
+    }
+
+    #[test]
+    fn stops_at_pause() {
+        let (mut manager, tx) = manager();
+
+        tx.send(red(0)).unwrap();
+        tx.send(Instruction::Pause).unwrap();
+        tx.send(Instruction::Resume).unwrap();
+        tx.send(red(1)).unwrap();
+        assert_eq!(manager.handle_instructions(false), Drained::Paused);
+        assert_eq!(red_leds(&manager.disp.board()), 1);
+
+        // the rest is handled after resuming
+        assert!(manager.wait_for_resume());
+        assert_eq!(manager.handle_instructions(false), Drained::Empty);
+        assert_eq!(red_leds(&manager.disp.board()), 2);
+    }
+
+    #[test]
+    fn stopped_while_paused() {
+        let (mut manager, tx) = manager();
+
+        tx.send(Instruction::Pause).unwrap();
+        tx.send(Instruction::Stop).unwrap();
+        assert_eq!(manager.handle_instructions(false), Drained::Paused);
+        assert!(!manager.wait_for_resume());
+
+        tx.send(Instruction::Pause).unwrap();
+        assert_eq!(manager.handle_instructions(false), Drained::Paused);
+        drop(tx);
+        assert!(!manager.wait_for_resume());
+    }
+
+    #[test]
+    fn disconnected() {
+        let (mut manager, tx) = manager();
+
+        tx.send(red(0)).unwrap();
+        drop(tx);
+        assert_eq!(manager.handle_instructions(false), Drained::Stopped);
+        assert_eq!(red_leds(&manager.disp.board()), 1);
+    }
+}
+
+mod test_play_animation {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{
+        display::{AnimationId, Instruction},
+        Animation, AnimationFrame, LedState,
+    };
+    #[allow(unused_imports)]
+    use std::{sync::mpsc::channel, thread, time::Duration};
+
+    #[allow(dead_code)]
+    fn once() -> Animation {
+        let frame = AnimationFrame::new(
+            Duration::from_millis(1),
+            vec![(0, 0, LedState::default())],
+            false,
+        );
+        Animation::new(false, vec![frame], 0, false)
+    }
+
+    #[test]
+    fn notifies_when_finished() {
+        let (mut manager, tx) = manager();
+
+        let (done_tx, done_rx) = channel();
+        tx.send(Instruction::PlayAnimation(
+            AnimationId::next(),
+            once(),
+            done_tx,
+        ))
+        .unwrap();
+        manager.handle_instructions(false);
+        manager.update_animations();
+        assert!(done_rx.try_recv().is_err());
+
+        thread::sleep(Duration::from_millis(2));
+        manager.update_animations();
+        done_rx.try_recv().unwrap();
+        assert!(manager.on_finish.is_empty());
+    }
+
+    #[test]
+    fn removed_animation_disconnects() {
+        let (mut manager, _tx) = manager();
+
+        let (done_tx, done_rx) = channel();
+        let id = AnimationId::next();
+        manager.animations.push((id, once()));
+        manager.on_finish.push((id, done_tx));
+        manager.remove_animation(id);
+        assert!(done_rx.recv().is_err());
+    }
+}
+
+mod test_set_refresh {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::display::Instruction;
+
+    #[test]
+    fn changes_time_per_led() {
+        let (mut manager, tx) = manager();
+        let before = manager.disp.time_per_led();
+
+        tx.send(Instruction::SetRefresh(120.0)).unwrap();
+        manager.handle_instructions(false);
+        let after = manager.disp.time_per_led();
+        assert!(after < before);
+        assert!((after.as_secs_f64() - 1.0 / (120.0 * 49.0)).abs() < 1e-9);
+    }
+}
+
+mod test_measured_fps {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::display::Instruction;
+    #[allow(unused_imports)]
+    use std::{
+        sync::mpsc::channel,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn rolling_average() {
+        let (mut manager, tx) = manager();
+
+        // no reply before the first run
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Instruction::QueryFps(reply_tx)).unwrap();
+        manager.handle_instructions(false);
+        assert!(reply_rx.recv().is_err());
+
+        manager.record_run(Duration::from_millis(10));
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Instruction::QueryFps(reply_tx)).unwrap();
+        manager.handle_instructions(false);
+        assert!((reply_rx.recv().unwrap() - 100.0).abs() < 1e-9);
+
+        // a single slow run only moves the average a bit
+        manager.record_run(Duration::from_millis(100));
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Instruction::QueryFps(reply_tx)).unwrap();
+        manager.handle_instructions(false);
+        let fps = reply_rx.recv().unwrap();
+        assert!(fps < 100.0 && fps > 50.0, "{fps}");
+    }
+
+    #[test]
+    fn time_between_runs() {
+        let (mut manager, _tx) = manager();
+        let start = Instant::now();
+
+        // the first run has nothing to be measured against
+        manager.record_run_start(start);
+        assert_eq!(manager.run_time, None);
+
+        // however long the run itself took, the time until the next one counts
+        manager.record_run_start(start + Duration::from_millis(20));
+        assert_eq!(manager.run_time, Some(0.02));
+    }
+}
+
+#[cfg(feature = "record")]
+mod test_recording {
+    #[allow(unused_imports)]
+    use super::test_manager::manager;
+    #[allow(unused_imports)]
+    use crate::{display::Instruction, LedColor, LedState, SyncType};
+    #[allow(unused_imports)]
+    use std::{sync::mpsc::channel, time::Duration};
+
+    #[test]
+    fn records_due_frames() {
+        let (mut manager, tx) = manager();
+
+        // nothing is recorded before a recording is started
+        manager.record_frame();
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Instruction::StopRecording(reply_tx)).unwrap();
+        manager.handle_instructions(false);
+        assert!(reply_rx.recv().is_err());
+
+        tx.send(Instruction::StartRecording(Duration::from_secs(60)))
+            .unwrap();
+        tx.send(Instruction::Sync(SyncType::Fill(LedState::with_color(
+            LedColor::Green,
+        ))))
+        .unwrap();
+        manager.handle_instructions(false);
+        // only the first frame is due
+        manager.record_frame();
+        manager.record_frame();
+
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Instruction::StopRecording(reply_tx)).unwrap();
+        manager.handle_instructions(false);
+        let (interval, frames) = reply_rx.recv().unwrap();
+        assert_eq!(interval, Duration::from_secs(60));
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].iter().flatten().all(|&c| c == LedColor::Green));
     }
 }
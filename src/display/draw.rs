@@ -0,0 +1,579 @@
+use super::font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::{
+    display::{Direction, DisplayInterface, LedColor, LedState, Running, Sync, SyncType},
+    DisplayResult, Error,
+};
+
+/// Colors a rainbow cycles through, see [DisplayInterface::rainbow].
+const RAINBOW: [LedColor; 8] = [
+    LedColor::Red,
+    LedColor::Yellow,
+    LedColor::Green,
+    LedColor::Cyan,
+    LedColor::Blue,
+    LedColor::Magenta,
+    LedColor::White,
+    LedColor::Off,
+];
+
+impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
+    /// Draw a straight line of `color` from `start` to `end`, both given as `(x, y)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if either end is out of bounds.
+    pub fn draw_line(
+        &mut self,
+        start: (usize, usize),
+        end: (usize, usize),
+        color: LedColor,
+    ) -> DisplayResult<()> {
+        if [start, end].iter().any(|(x, y)| *x >= W || *y >= H) {
+            return Err(Error::InvalidDim);
+        }
+        self.sync(cells_sync(line_cells(start, end), color))
+    }
+
+    /// Draw the outline of a rectangle of `color`, `w` leds wide and `h` leds high, with its
+    /// top left corner at `origin`.
+    ///
+    /// The parts of the rectangle past the edges of the display are left out, which also
+    /// leaves out the outline on those sides.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the origin is out of bounds.
+    pub fn draw_rect(
+        &mut self,
+        origin: (usize, usize),
+        w: usize,
+        h: usize,
+        color: LedColor,
+    ) -> DisplayResult<()> {
+        let cells = rect_cells::<W, H>(origin, w, h, false)?;
+        self.sync(cells_sync(cells, color))
+    }
+
+    /// Fill a rectangle with `color`, `w` leds wide and `h` leds high, with its top left
+    /// corner at `origin`.
+    ///
+    /// The parts of the rectangle past the edges of the display are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the origin is out of bounds.
+    pub fn fill_rect(
+        &mut self,
+        origin: (usize, usize),
+        w: usize,
+        h: usize,
+        color: LedColor,
+    ) -> DisplayResult<()> {
+        let cells = rect_cells::<W, H>(origin, w, h, true)?;
+        self.sync(cells_sync(cells, color))
+    }
+
+    /// Write `text` in `color` with a 5x7 font, with the top left corner of the first
+    /// character at `x`, `y`.
+    ///
+    /// Characters are spaced one led apart. Only the leds of the characters are set, the
+    /// leds between them are left as they are. Parts of the text past the edges of the display
+    /// are left out, so the position may be negative to scroll text into view. Characters
+    /// without a glyph in the font are drawn as a box.
+    pub fn draw_text(&mut self, text: &str, x: i32, y: i32, color: LedColor) -> DisplayResult<()> {
+        self.sync(cells_sync(text_cells::<W, H>(text, x, y), color))
+    }
+
+    /// Draw a progress bar over the whole width of `row`, with the leds from the left up to
+    /// `fraction` of the width in `filled` and the rest in `empty`.
+    ///
+    /// The fraction is clamped between `0.0` and `1.0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the row is out of bounds.
+    pub fn draw_progress(
+        &mut self,
+        fraction: f64,
+        row: usize,
+        filled: LedColor,
+        empty: LedColor,
+    ) -> DisplayResult<()> {
+        if row >= H {
+            return Err(Error::InvalidDim);
+        }
+        let lit = progress_len::<W>(fraction);
+        self.sync(SyncType::Multi(
+            (0..W)
+                .map(|x| Sync {
+                    x,
+                    y: row,
+                    state: LedState::with_color(match x < lit {
+                        true => filled,
+                        false => empty,
+                    }),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Draw every value as a bar of `color` rising from the bottom of its column, starting
+    /// at the leftmost column.
+    ///
+    /// Values run from `0.0` for an empty column to `1.0` for a full one, and are clamped to
+    /// that range. The leds above every bar are turned off, so the bars can be redrawn with
+    /// new values. Columns past the last value are left as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if there are more values than columns.
+    pub fn draw_bars(&mut self, values: &[f64], color: LedColor) -> DisplayResult<()> {
+        self.sync(bars_sync::<W, H>(values, color)?)
+    }
+
+    /// Fill the display with a gradient from `from` to `to` towards `dir`.
+    ///
+    /// The colors are mixed in rgb, after which every led shows the nearest color, see
+    /// [LedColor::nearest_rgb].
+    pub fn gradient(&mut self, from: LedColor, to: LedColor, dir: Direction) -> DisplayResult<()> {
+        self.sync(gradient_board::<W, H>(from, to, dir).into())
+    }
+
+    /// Fill the display with stripes cycling through every color towards `dir`, starting
+    /// with red.
+    pub fn rainbow(&mut self, dir: Direction) -> DisplayResult<()> {
+        let board = axis_board::<W, H>(dir, |pos, _| RAINBOW[pos % RAINBOW.len()]);
+        self.sync(board.into())
+    }
+}
+
+/// Board going from `from` to `to` towards `dir`, see [DisplayInterface::gradient].
+fn gradient_board<const W: usize, const H: usize>(
+    from: LedColor,
+    to: LedColor,
+    dir: Direction,
+) -> [[LedColor; W]; H] {
+    let (from, to) = (from.to_rgb(), to.to_rgb());
+    let mix = |a: u8, b: u8, t: f64| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    axis_board(dir, |pos, len| {
+        let t = match len {
+            1 => 0.0,
+            len => pos as f64 / (len - 1) as f64,
+        };
+        LedColor::nearest_rgb(
+            mix(from.0, to.0, t),
+            mix(from.1, to.1, t),
+            mix(from.2, to.2, t),
+        )
+    })
+}
+
+/// Board with `color` of every led, given its position along `dir` and the length of that
+/// axis.
+fn axis_board<const W: usize, const H: usize>(
+    dir: Direction,
+    color: impl Fn(usize, usize) -> LedColor,
+) -> [[LedColor; W]; H] {
+    let mut board = [[LedColor::Off; W]; H];
+    for (y, row) in board.iter_mut().enumerate() {
+        for (x, led) in row.iter_mut().enumerate() {
+            *led = match dir {
+                Direction::Right => color(x, W),
+                Direction::Left => color(W - 1 - x, W),
+                Direction::Down => color(y, H),
+                Direction::Up => color(H - 1 - y, H),
+            };
+        }
+    }
+    board
+}
+
+/// Sync drawing `values` as bars of `color` in the columns from the left, see
+/// [DisplayInterface::draw_bars].
+fn bars_sync<const W: usize, const H: usize>(
+    values: &[f64],
+    color: LedColor,
+) -> DisplayResult<SyncType> {
+    if values.len() > W {
+        return Err(Error::InvalidDim);
+    }
+    let mut syncs = Vec::new();
+    for (x, value) in values.iter().enumerate() {
+        let height = progress_len::<H>(*value);
+        for y in 0..H {
+            let lit = y >= H - height;
+            syncs.push(Sync {
+                x,
+                y,
+                state: LedState::with_color(match lit {
+                    true => color,
+                    false => LedColor::Off,
+                }),
+            });
+        }
+    }
+    Ok(SyncType::Multi(syncs))
+}
+
+/// Number of leds of a progress bar `W` leds wide that are filled at `fraction`.
+fn progress_len<const W: usize>(fraction: f64) -> usize {
+    // NaN is clamped to nothing done
+    let fraction = match fraction.is_nan() {
+        true => 0.0,
+        false => fraction.clamp(0.0, 1.0),
+    };
+    (fraction * W as f64).round() as usize
+}
+
+/// Sync turning every cell of `cells` to `color`.
+fn cells_sync(cells: Vec<(usize, usize)>, color: LedColor) -> SyncType {
+    SyncType::Multi(
+        cells
+            .into_iter()
+            .map(|(x, y)| Sync {
+                x,
+                y,
+                state: LedState::with_color(color),
+            })
+            .collect(),
+    )
+}
+
+/// The cells on the line from `start` to `end`, using Bresenham's line algorithm.
+fn line_cells(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+    let (x1, y1) = (end.0 as isize, end.1 as isize);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut err = dx + dy;
+
+    let mut cells = Vec::new();
+    loop {
+        cells.push((x as usize, y as usize));
+        if (x, y) == (x1, y1) {
+            return cells;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// The cells of a rectangle that are on the display, either all of them if `filled` is set
+/// or only those on its outline.
+fn rect_cells<const W: usize, const H: usize>(
+    origin: (usize, usize),
+    w: usize,
+    h: usize,
+    filled: bool,
+) -> DisplayResult<Vec<(usize, usize)>> {
+    let (ox, oy) = origin;
+    if ox >= W || oy >= H {
+        return Err(Error::InvalidDim);
+    }
+
+    let (right, bottom) = (ox.saturating_add(w), oy.saturating_add(h));
+    let mut cells = Vec::new();
+    for y in oy..bottom.min(H) {
+        for x in ox..right.min(W) {
+            let edge = x == ox || x == right - 1 || y == oy || y == bottom - 1;
+            if filled || edge {
+                cells.push((x, y));
+            }
+        }
+    }
+    Ok(cells)
+}
+
+/// The cells lit by `text` written at `x`, `y` that are on the display.
+fn text_cells<const W: usize, const H: usize>(text: &str, x: i32, y: i32) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    for (index, c) in text.chars().enumerate() {
+        let left = x as i64 + index as i64 * (GLYPH_WIDTH as i64 + 1);
+        for (column, bits) in glyph(c).iter().enumerate() {
+            for row in 0..GLYPH_HEIGHT {
+                let (cx, cy) = (left + column as i64, y as i64 + row as i64);
+                if bits >> row & 1 == 1
+                    && (0..W as i64).contains(&cx)
+                    && (0..H as i64).contains(&cy)
+                {
+                    cells.push((cx as usize, cy as usize));
+                }
+            }
+        }
+    }
+    cells
+}
+
+mod test_line_cells {
+    #[allow(unused_imports)]
+    use super::line_cells;
+
+    #[test]
+    fn horizontal() {
+        assert_eq!(line_cells((1, 2), (4, 2)), [(1, 2), (2, 2), (3, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn vertical() {
+        assert_eq!(line_cells((3, 3), (3, 0)), [(3, 3), (3, 2), (3, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn diagonal() {
+        assert_eq!(line_cells((0, 0), (3, 3)), [(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(line_cells((4, 0), (2, 2)), [(4, 0), (3, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn single_cell() {
+        assert_eq!(line_cells((5, 6), (5, 6)), [(5, 6)]);
+    }
+}
+
+mod test_draw_line {
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        Error, LedColor, SyncType,
+    };
+
+    #[test]
+    fn sends_one_sync() {
+        let (mut disp, rx) = interface();
+        disp.draw_line((0, 6), (6, 0), LedColor::Red).unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::Sync(SyncType::Multi(syncs))) => {
+                assert_eq!(syncs.len(), 7);
+                assert!(syncs.iter().all(|sync| sync.x + sync.y == 6));
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.draw_line((0, 0), (7, 0), LedColor::Red),
+            Err(Error::InvalidDim)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+}
+
+mod test_rect_cells {
+    #[allow(unused_imports)]
+    use super::rect_cells;
+    #[allow(unused_imports)]
+    use crate::Error;
+
+    #[test]
+    fn full_board_border() {
+        let cells = rect_cells::<4, 3>((0, 0), 4, 3, false).unwrap();
+        assert_eq!(
+            cells,
+            [
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (0, 1),
+                (3, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (3, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn filled() {
+        let cells = rect_cells::<7, 7>((2, 3), 2, 2, true).unwrap();
+        assert_eq!(cells, [(2, 3), (3, 3), (2, 4), (3, 4)]);
+    }
+
+    #[test]
+    fn clipped() {
+        // the right and bottom edges are past the board, so they are left out
+        let cells = rect_cells::<4, 4>((2, 2), 5, 5, false).unwrap();
+        assert_eq!(cells, [(2, 2), (3, 2), (2, 3)]);
+        assert_eq!(rect_cells::<4, 4>((1, 1), 9, 9, true).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn origin_out_of_bounds() {
+        assert!(matches!(
+            rect_cells::<4, 4>((4, 0), 1, 1, true),
+            Err(Error::InvalidDim)
+        ));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(rect_cells::<4, 4>((1, 1), 0, 3, false).unwrap().is_empty());
+    }
+}
+
+mod test_text_cells {
+    #[allow(unused_imports)]
+    use super::text_cells;
+
+    #[test]
+    fn hi() {
+        let mut cells = text_cells::<12, 7>("HI", 0, 0);
+        cells.sort();
+
+        let mut expected = Vec::new();
+        // H: two full columns joined in the middle
+        expected.extend((0..7).map(|y| (0, y)));
+        expected.extend((1..4).map(|x| (x, 3)));
+        expected.extend((0..7).map(|y| (4, y)));
+        // I: a full column with serifs, one led after the H
+        expected.extend([(7, 0), (7, 6), (9, 0), (9, 6)]);
+        expected.extend((0..7).map(|y| (8, y)));
+        expected.sort();
+
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn clipped() {
+        // only the right column of the H is on the display
+        let cells = text_cells::<7, 7>("H", -4, 0);
+        assert_eq!(cells, (0..7).map(|y| (0, y)).collect::<Vec<_>>());
+        // the top row is above the display
+        assert_eq!(text_cells::<7, 7>("H", 0, -1).len(), 6 + 3 + 6);
+        assert!(text_cells::<7, 7>("HI", 20, 0).is_empty());
+    }
+}
+
+mod test_draw_progress {
+    #[allow(unused_imports)]
+    use super::progress_len;
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        Error, LedColor, SyncType,
+    };
+
+    #[test]
+    fn lengths() {
+        assert_eq!(progress_len::<10>(0.0), 0);
+        assert_eq!(progress_len::<10>(0.5), 5);
+        assert_eq!(progress_len::<10>(1.0), 10);
+        assert_eq!(progress_len::<7>(0.5), 4);
+        assert_eq!(progress_len::<7>(-1.0), 0);
+        assert_eq!(progress_len::<7>(3.0), 7);
+        assert_eq!(progress_len::<7>(f64::NAN), 0);
+    }
+
+    #[test]
+    fn fills_row() {
+        let (mut disp, rx) = interface();
+        for (fraction, lit) in [(0.0, 0), (0.5, 4), (1.0, 7)] {
+            disp.draw_progress(fraction, 6, LedColor::Green, LedColor::Red)
+                .unwrap();
+            match rx.try_recv() {
+                Ok(Instruction::Sync(SyncType::Multi(syncs))) => {
+                    assert_eq!(syncs.len(), 7);
+                    assert!(syncs.iter().all(|sync| sync.y == 6));
+                    let green = syncs
+                        .iter()
+                        .filter(|sync| matches!(sync.state.color, LedColor::Green))
+                        .count();
+                    assert_eq!(green, lit);
+                    assert!(syncs[..lit]
+                        .iter()
+                        .all(|sync| matches!(sync.state.color, LedColor::Green)));
+                }
+                other => panic!("unexpected instruction: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn row_out_of_bounds() {
+        let (mut disp, rx) = interface();
+        assert!(matches!(
+            disp.draw_progress(0.5, 7, LedColor::Green, LedColor::Off),
+            Err(Error::InvalidDim)
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+}
+
+mod test_bars_sync {
+    #[allow(unused_imports)]
+    use super::bars_sync;
+    #[allow(unused_imports)]
+    use crate::{Error, LedColor, SyncType};
+
+    #[test]
+    fn heights() {
+        let syncs = match bars_sync::<4, 4>(&[0.0, 0.5, 1.0], LedColor::Blue).unwrap() {
+            SyncType::Multi(syncs) => syncs,
+            other => panic!("unexpected sync: {other:?}"),
+        };
+        // every column with a value is fully set, the last column is left alone
+        assert_eq!(syncs.len(), 3 * 4);
+        let mut lit: Vec<(usize, usize)> = syncs
+            .iter()
+            .filter(|sync| matches!(sync.state.color, LedColor::Blue))
+            .map(|sync| (sync.x, sync.y))
+            .collect();
+        lit.sort();
+        assert_eq!(lit, [(1, 2), (1, 3), (2, 0), (2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn too_many_values() {
+        assert!(matches!(
+            bars_sync::<2, 4>(&[0.1, 0.2, 0.3], LedColor::Blue),
+            Err(Error::InvalidDim)
+        ));
+    }
+}
+
+mod test_gradient {
+    #[allow(unused_imports)]
+    use super::{axis_board, gradient_board, RAINBOW};
+    #[allow(unused_imports)]
+    use crate::{Direction, LedColor};
+
+    #[test]
+    fn endpoints() {
+        let board = gradient_board::<7, 7>(LedColor::Red, LedColor::Blue, Direction::Right);
+        for row in &board {
+            assert!(matches!(row[0], LedColor::Red));
+            assert!(matches!(row[6], LedColor::Blue));
+        }
+        // the middle mixes both
+        assert!(matches!(board[0][3], LedColor::Magenta));
+
+        let board = gradient_board::<7, 5>(LedColor::Green, LedColor::Off, Direction::Up);
+        assert!(board[4].iter().all(|c| matches!(c, LedColor::Green)));
+        assert!(board[0].iter().all(|c| matches!(c, LedColor::Off)));
+    }
+
+    #[test]
+    fn single_led() {
+        let board = gradient_board::<1, 1>(LedColor::Cyan, LedColor::Red, Direction::Left);
+        assert!(matches!(board[0][0], LedColor::Cyan));
+    }
+
+    #[test]
+    fn rainbow() {
+        let board = axis_board::<10, 2>(Direction::Right, |pos, _| RAINBOW[pos % RAINBOW.len()]);
+        let colors: Vec<u8> = board[1].iter().map(|&c| c as u8).collect();
+        assert_eq!(colors[..8], RAINBOW.map(|c| c as u8));
+        assert_eq!(colors[8..], [LedColor::Red as u8, LedColor::Yellow as u8]);
+    }
+}
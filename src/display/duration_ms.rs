@@ -0,0 +1,13 @@
+//! Serialize a [Duration] as a whole number of milliseconds.
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+pub(super) fn serialize<S: Serializer>(dur: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(dur.as_millis() as u64)
+}
+
+pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+}
@@ -0,0 +1,48 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+/// Shared clock that makes several displays start every run of their scan at the same time.
+///
+/// Displays started with
+/// [DisplayInterface::start_synced](crate::DisplayInterface::start_synced) wait for a
+/// [FrameClock::tick] before every run, which keeps the panels of a video wall in phase
+/// instead of drifting apart and flickering where they meet.
+///
+/// The clock is cheap to clone, every clone ticks the same displays.
+#[derive(Debug, Clone, Default)]
+pub struct FrameClock {
+    ticks: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl FrameClock {
+    /// Create a new clock that has not ticked yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let every display waiting on this clock run once.
+    ///
+    /// This should be called at the refresh rate of the displays, a display that is still
+    /// busy with its previous run when the clock ticks starts its next run right after.
+    pub fn tick(&self) {
+        let (ticks, tick) = &*self.ticks;
+        *ticks.lock().unwrap() += 1;
+        tick.notify_all();
+    }
+
+    /// Wait until the clock has ticked since `last`, for at most `timeout`.
+    ///
+    /// Returns `true` and updates `last` if the clock ticked, ticks that were missed in
+    /// between are skipped.
+    pub(super) fn wait(&self, last: &mut u64, timeout: Duration) -> bool {
+        let (ticks, tick) = &*self.ticks;
+        let (ticks, _) = tick
+            .wait_timeout_while(ticks.lock().unwrap(), timeout, |ticks| *ticks == *last)
+            .unwrap();
+        let ticked = *ticks != *last;
+        *last = *ticks;
+        ticked
+    }
+}
@@ -1,13 +1,44 @@
-use super::{animation::Animation, LedColor, LedState};
+use std::sync::mpsc::Sender;
+#[cfg(feature = "record")]
+use std::time::Duration;
+
+use super::{
+    animation::{Animation, AnimationId},
+    LedColor, LedState, COLOR_BITS,
+};
+use crate::{DisplayResult, Error};
 
 /// The types of message that can be sent to the display thread.
 #[derive(Debug)]
 pub(super) enum Instruction {
     Stop,
-    Pause,
+    Pause, // nothing but a resume or stop is handled until the resume
+    Resume,
     Sync(SyncType),
-    AddAnimation(Animation),
+    AddAnimation(AnimationId, Animation),
+    PlayAnimation(AnimationId, Animation, Sender<()>), // replied to once the animation finished
+    RemoveAnimation(AnimationId),
+    PauseAnimation(AnimationId),
+    ResumeAnimation(AnimationId),
+    PauseAnimations,
+    ResumeAnimations,
     ClearAnimations,
+    SetAnimationSpeed(f64),
+    Snapshot(Sender<Vec<Vec<LedState>>>),
+    QueryFps(Sender<f64>), // not replied to before the first run has finished
+    SetBrightness(f64),
+    SetRefresh(f64),
+    SetGamma(f64),
+    SyncBlink,
+    SetStepped(bool),
+    Step(Sender<()>), // replied to once the next run has finished
+    SetPalette([LedColor; 1 << COLOR_BITS]),
+    SetBackground(LedColor),
+    ExportAnimation(AnimationId, Sender<Option<String>>),
+    #[cfg(feature = "record")]
+    StartRecording(Duration), // time between two recorded frames
+    #[cfg(feature = "record")]
+    StopRecording(Sender<(Duration, Vec<Vec<Vec<LedColor>>>)>), // dropped if not recording
 }
 
 /// Indicates the current state of the `DisplayInterface`.
@@ -15,21 +46,25 @@ pub trait State {}
 
 /// The running state of `DisplayInterface`.
 #[doc(hidden)]
+#[derive(Debug)]
 pub struct Running;
 impl State for Running {}
 
 /// The paused state of `DisplayInterface`.
 #[doc(hidden)]
+#[derive(Debug)]
 pub struct Paused;
 impl State for Paused {}
 
 /// The stopped state of `DisplayInterface`.
 #[doc(hidden)]
+#[derive(Debug)]
 pub struct Stopped;
 impl State for Stopped {}
 
 /// Data struct to change a led's color.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sync {
     /// The x position of the led to be changed.
     pub x: usize,
@@ -41,6 +76,7 @@ pub struct Sync {
 
 /// The amount to rotate.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Rotation {
     /// Rotate 90° clockwise.
     Clockwise,
@@ -50,6 +86,30 @@ pub enum Rotation {
     OneEighty,
 }
 
+/// The direction to shift in.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// Towards the first row.
+    Up,
+    /// Towards the last row.
+    Down,
+    /// Towards the first column.
+    Left,
+    /// Towards the last column.
+    Right,
+}
+
+/// The axis to flip around.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    /// Mirror left and right, reversing every row.
+    Horizontal,
+    /// Mirror top and bottom, reversing the order of the rows.
+    Vertical,
+}
+
 //? additional board manipulation options (rotate, shift, ...)
 /// The synchronization type. Synchronization is used to update which led has which color.
 ///
@@ -59,6 +119,7 @@ pub enum Rotation {
 ///
 /// Use [SyncType::All] to change the color of all leds at once.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SyncType {
     /// Change the color of one led.
     Single(Sync),
@@ -66,14 +127,153 @@ pub enum SyncType {
     Multi(Vec<Sync>),
     /// Change the color of all leds.
     All(Vec<Vec<LedState>>),
+    /// Change all leds to the same state.
+    Fill(LedState),
+    /// Turn all leds off.
+    Clear,
     /// Rotate the entire grid.
     Rotate(Rotation),
+    /// Mirror the entire grid around an axis.
+    ///
+    /// Unlike [SyncType::Rotate] this works for displays of any size.
+    Flip(Axis),
+    /// Move every led `amount` cells in direction `dir`.
+    ///
+    /// Leds moved past the edge come back on the opposite side if `wrap` is set,
+    /// otherwise they are dropped and the vacated cells are turned off.
+    Shift {
+        /// The direction to move the leds in.
+        dir: Direction,
+        /// The number of cells to move the leds by.
+        amount: usize,
+        /// Wrap leds around the edges of the display.
+        wrap: bool,
+    },
+    /// Turn every lit led off and every led that is off to the given color.
+    ///
+    /// The blink and brightness of every led are kept.
+    Invert(LedColor),
 }
 
+/// Colors of every led of a board, created by
+/// [DisplayInterface::sync_template](crate::DisplayInterface::sync_template).
+///
+/// Converts into a [SyncType::All] that shows the board, or can be shown with
+/// [DisplayInterface::apply_template](crate::DisplayInterface::apply_template).
+#[derive(Debug, Clone)]
 pub struct SyncTemplate<const W: usize, const H: usize> {
+    /// The color of every led, by row.
     pub board: [[LedColor; W]; H],
 }
 
-// impl<const W: usize, const H: usize> SyncTemplate<W, H> {
-//     pub fn
-// }
+impl<const W: usize, const H: usize> SyncTemplate<W, H> {
+    /// Set the led at `x`, `y` to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the position is out of bounds.
+    pub fn set(&mut self, x: usize, y: usize, color: LedColor) -> DisplayResult<()> {
+        if x >= W || y >= H {
+            return Err(Error::InvalidDim);
+        }
+        self.board[y][x] = color;
+        Ok(())
+    }
+
+    /// Set every led to `color`.
+    pub fn fill(&mut self, color: LedColor) {
+        self.board = [[color; W]; H];
+    }
+}
+
+impl<const W: usize, const H: usize> From<[[LedState; W]; H]> for SyncType {
+    fn from(board: [[LedState; W]; H]) -> Self {
+        Self::All(board.iter().map(|row| row.to_vec()).collect())
+    }
+}
+
+impl<const W: usize, const H: usize> From<[[LedColor; W]; H]> for SyncType {
+    fn from(board: [[LedColor; W]; H]) -> Self {
+        board.map(|row| row.map(LedState::with_color)).into()
+    }
+}
+
+impl<const W: usize, const H: usize> From<SyncTemplate<W, H>> for SyncType {
+    fn from(template: SyncTemplate<W, H>) -> Self {
+        template.board.into()
+    }
+}
+
+mod test_from_board {
+    #[allow(unused_imports)]
+    use super::{SyncTemplate, SyncType};
+    #[allow(unused_imports)]
+    use crate::{LedColor, LedState};
+
+    #[test]
+    fn from_colors() {
+        let mut board = [[LedColor::Red; 3]; 2];
+        board[1][2] = LedColor::Blue;
+        match SyncType::from(board) {
+            SyncType::All(rows) => {
+                assert_eq!(rows.len(), 2);
+                assert!(rows.iter().all(|row| row.len() == 3));
+                assert!(matches!(rows[0][0].color, LedColor::Red));
+                assert!(matches!(rows[1][2].color, LedColor::Blue));
+                assert!(rows[1][2].blink.is_none());
+            }
+            other => panic!("unexpected sync: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn from_states() {
+        let mut board = [[LedState::default(); 2]; 2];
+        board[0][1].brightness = 10;
+        match board.into() {
+            SyncType::All(rows) => assert_eq!(rows[0][1].brightness, 10),
+            other => panic!("unexpected sync: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn from_template() {
+        let mut template = SyncTemplate::<2, 2> {
+            board: [[LedColor::Off; 2]; 2],
+        };
+        template.board[1][0] = LedColor::Green;
+        match template.into() {
+            SyncType::All(rows) => assert!(matches!(rows[1][0].color, LedColor::Green)),
+            other => panic!("unexpected sync: {other:?}"),
+        }
+    }
+}
+
+mod test_sync_template {
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        DisplayInterface, Error, LedColor, Running, SyncType,
+    };
+
+    #[test]
+    fn build_and_apply() {
+        let mut template = DisplayInterface::<Running, 7, 7>::sync_template();
+        template.fill(LedColor::Blue);
+        template.set(6, 0, LedColor::Red).unwrap();
+        assert!(matches!(
+            template.set(0, 7, LedColor::Red),
+            Err(Error::InvalidDim)
+        ));
+
+        let (mut disp, rx) = interface();
+        disp.apply_template(&template).unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::Sync(SyncType::All(rows))) => {
+                assert!(matches!(rows[0][6].color, LedColor::Red));
+                assert!(matches!(rows[6][0].color, LedColor::Blue));
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+    }
+}
@@ -4,12 +4,42 @@ pub use display::*;
 mod display_interface;
 pub use display_interface::*;
 
+#[cfg(feature = "async")]
+mod async_interface;
+#[cfg(feature = "async")]
+pub use async_interface::*;
+
+mod start_builder;
+pub use start_builder::*;
+
+mod pin_config_builder;
+pub use pin_config_builder::*;
+
+mod draw;
+
+mod canvas;
+pub use canvas::*;
+
+mod pattern;
+pub use pattern::*;
+
+mod font;
+
 mod shift_reg;
 use shift_reg::*;
 
 mod dec;
 use dec::*;
 
+mod pin;
+pub use pin::*;
+
+#[cfg(feature = "simulator")]
+mod simulator;
+
+#[cfg(feature = "net")]
+pub mod net;
+
 mod interface_components;
 pub use interface_components::*;
 
@@ -18,3 +48,11 @@ use display_manager::*;
 
 mod animation;
 pub use animation::*;
+
+mod animation_macro;
+
+mod frame_clock;
+pub use frame_clock::*;
+
+#[cfg(feature = "serde")]
+mod duration_ms;
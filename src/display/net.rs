@@ -0,0 +1,210 @@
+//! Drive a display over the network with newline delimited json commands.
+//!
+//! Every line sent to the server is parsed as a [Command] and answered with a line holding a
+//! [Reply]. For example `{"sync":{"Rotate":"Clockwise"}}`, `{"fill":"Red"}` or `"clear"`,
+//! which are answered with `"ok"` or an error like `{"error":"InvalidDim"}`.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+};
+
+use crate::{Animation, DisplayInterface, LedColor, Running, SyncType};
+
+/// Longest line a client can send in bytes, a client sending a longer line is dropped.
+const MAX_LINE: u64 = 1 << 20;
+
+/// A command sent to the server, mirroring the methods of [DisplayInterface].
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    /// Update the leds, see [DisplayInterface::sync].
+    Sync(SyncType),
+    /// Add an animation, see [DisplayInterface::add_animation].
+    AddAnimation(Animation),
+    /// Remove every animation, see [DisplayInterface::clear_animations].
+    ClearAnimations,
+    /// Turn every led off, see [DisplayInterface::clear].
+    Clear,
+    /// Set every led to a color, see [DisplayInterface::fill].
+    Fill(LedColor),
+}
+
+/// The answer to a line sent to the server.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+    /// The command was sent to the display.
+    Ok,
+    /// The line was not a valid command, or the display rejected it.
+    Error(String),
+}
+
+/// Serve commands for `interface` on `addr`, see the [module](self) documentation.
+///
+/// Clients are served one at a time, in the order they connect. A client that loses its
+/// connection, can not be accepted or sends a line longer than 1 MiB is dropped and the
+/// next one is served.
+///
+/// # Errors
+///
+/// Returns an error if the server could not listen on `addr`.
+pub fn serve<const W: usize, const H: usize>(
+    interface: &mut DisplayInterface<Running, W, H>,
+    addr: impl ToSocketAddrs,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!("Serving display commands on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        // a client failing is no reason to stop serving the others
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept a client: {e}");
+                continue;
+            }
+        };
+        let peer = match stream.peer_addr() {
+            Ok(peer) => peer,
+            Err(e) => {
+                log::warn!("Failed to get the address of a client: {e}");
+                continue;
+            }
+        };
+        log::debug!("Client {peer} connected");
+        let served = stream
+            .try_clone()
+            .and_then(|reader| serve_client(interface, BufReader::new(reader), stream));
+        if let Err(e) = served {
+            log::warn!("Lost connection to client {peer}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Answer every command read from `reader` until it ends.
+///
+/// A line longer than [MAX_LINE] is answered with an error and ends the connection, so a
+/// client can not fill the memory with a line that never ends.
+fn serve_client<const W: usize, const H: usize>(
+    interface: &mut DisplayInterface<Running, W, H>,
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = (&mut reader).take(MAX_LINE).read_line(&mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read as u64 == MAX_LINE && !line.ends_with('\n') {
+            let reply = Reply::Error(format!("line longer than {MAX_LINE} bytes"));
+            let reply = serde_json::to_string(&reply).map_err(io::Error::from)?;
+            writeln!(writer, "{reply}")?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str(&line) {
+            Ok(command) => execute(interface, command),
+            Err(e) => Reply::Error(e.to_string()),
+        };
+        let reply = serde_json::to_string(&reply).map_err(io::Error::from)?;
+        writeln!(writer, "{reply}")?;
+    }
+}
+
+/// Send `command` to the display.
+fn execute<const W: usize, const H: usize>(
+    interface: &mut DisplayInterface<Running, W, H>,
+    command: Command,
+) -> Reply {
+    let result = match command {
+        Command::Sync(sync_type) => interface.sync(sync_type),
+        Command::AddAnimation(animation) => interface.add_animation(animation).map(|_| ()),
+        Command::ClearAnimations => interface.clear_animations(),
+        Command::Clear => interface.clear(),
+        Command::Fill(color) => interface.fill(color),
+    };
+    match result {
+        Ok(()) => Reply::Ok,
+        Err(e) => Reply::Error(format!("{e:?}")),
+    }
+}
+
+mod test_serve_client {
+    #[allow(unused_imports)]
+    use super::{serve_client, Reply, MAX_LINE};
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        LedColor, Rotation, SyncType,
+    };
+
+    /// Serve `input` and return every reply.
+    #[allow(dead_code)]
+    fn replies(input: &str) -> (Vec<Reply>, Vec<Instruction>) {
+        let (mut disp, rx) = interface();
+        let mut output = Vec::new();
+        serve_client(&mut disp, input.as_bytes(), &mut output).unwrap();
+        let replies = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        (replies, rx.try_iter().collect())
+    }
+
+    #[test]
+    fn commands() {
+        let (replies, sent) = replies(concat!(
+            "{\"sync\":{\"Rotate\":\"Clockwise\"}}\n",
+            "{\"fill\":\"Red\"}\n",
+            "\n",
+            "\"clear_animations\"\n",
+            "\"clear\"\n",
+        ));
+        assert_eq!(replies, [Reply::Ok, Reply::Ok, Reply::Ok, Reply::Ok]);
+        assert!(matches!(
+            sent[0],
+            Instruction::Sync(SyncType::Rotate(Rotation::Clockwise))
+        ));
+        assert!(
+            matches!(&sent[1], Instruction::Sync(SyncType::Fill(led)) if led.color == LedColor::Red)
+        );
+        assert!(matches!(sent[2], Instruction::ClearAnimations));
+        assert!(matches!(sent[3], Instruction::Sync(SyncType::Clear)));
+    }
+
+    #[test]
+    fn errors_keep_connection() {
+        let (replies, sent) = replies(concat!(
+            "{\"fill\":\n",
+            "{\"sync\":{\"All\":[]}}\n",
+            "\"clear\"\n",
+        ));
+        assert!(matches!(&replies[0], Reply::Error(_)));
+        assert_eq!(replies[1], Reply::Error("InvalidDim".to_string()));
+        assert_eq!(replies[2], Reply::Ok);
+        assert_eq!(sent.len(), 1);
+    }
+
+    #[test]
+    fn long_line_drops_client() {
+        let (mut disp, rx) = interface();
+        let input = "x".repeat(MAX_LINE as usize + 1) + "\n\"clear\"\n";
+        let mut output = Vec::new();
+        assert!(serve_client(&mut disp, input.as_bytes(), &mut output).is_err());
+
+        let output = String::from_utf8(output).unwrap();
+        let replies: Vec<Reply> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(matches!(&replies[..], [Reply::Error(_)]));
+        assert!(rx.try_recv().is_err());
+    }
+}
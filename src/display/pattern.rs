@@ -0,0 +1,182 @@
+use crate::{
+    display::{
+        Animation, AnimationFrame, DisplayInterface, LedColor, LedState, Running, SUPPORTED_COLORS,
+    },
+    DisplayResult,
+};
+use std::time::Duration;
+
+/// Time every row or column of a sweep is lit.
+const SWEEP_STEP: Duration = Duration::from_millis(250);
+/// Time every color of [TestPattern::AllColors] is shown.
+const COLOR_STEP: Duration = Duration::from_secs(1);
+
+/// Patterns to check the wiring of a display with, see [DisplayInterface::test_pattern].
+#[derive(Debug, Clone, Copy)]
+pub enum TestPattern {
+    /// Alternate the two colors like a checkerboard, starting with the first color in the
+    /// top left corner. This stays on the display.
+    Checkerboard(LedColor, LedColor),
+    /// Light every row in white one after the other, from top to bottom.
+    RowSweep,
+    /// Light every column in white one after the other, from left to right.
+    ColumnSweep,
+    /// Show every color on the whole display for a second each.
+    AllColors,
+}
+
+impl TestPattern {
+    /// The boards shown one after the other by this pattern.
+    fn boards<const W: usize, const H: usize>(&self) -> Vec<[[LedColor; W]; H]> {
+        let board = |color: &dyn Fn(usize, usize) -> LedColor| {
+            let mut board = [[LedColor::Off; W]; H];
+            for (y, row) in board.iter_mut().enumerate() {
+                for (x, led) in row.iter_mut().enumerate() {
+                    *led = color(x, y);
+                }
+            }
+            board
+        };
+        let lit_if = |lit: bool| match lit {
+            true => LedColor::White,
+            false => LedColor::Off,
+        };
+
+        match *self {
+            Self::Checkerboard(first, second) => vec![board(&|x, y| match (x + y) % 2 {
+                0 => first,
+                _ => second,
+            })],
+            Self::RowSweep => (0..H).map(|lit| board(&|_, y| lit_if(y == lit))).collect(),
+            Self::ColumnSweep => (0..W).map(|lit| board(&|x, _| lit_if(x == lit))).collect(),
+            Self::AllColors => SUPPORTED_COLORS[1..]
+                .iter()
+                .map(|&color| [[color; W]; H])
+                .collect(),
+        }
+    }
+
+    /// Time every board of this pattern is shown.
+    fn step(&self) -> Duration {
+        match self {
+            Self::AllColors => COLOR_STEP,
+            _ => SWEEP_STEP,
+        }
+    }
+}
+
+impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Running, W, H> {
+    /// Show `pattern` to check that every row, column and color of the display lights up.
+    ///
+    /// A checkerboard replaces the board, every other pattern is played once as an animation
+    /// that turns the leds it lit off again.
+    pub fn test_pattern(&mut self, pattern: TestPattern) -> DisplayResult<()> {
+        let boards = pattern.boards::<W, H>();
+        match pattern {
+            TestPattern::Checkerboard(..) => self.sync(boards[0].into()),
+            _ => {
+                let frames = boards
+                    .iter()
+                    .map(|board| AnimationFrame::new(pattern.step(), lit_leds(board), true))
+                    .collect();
+                self.add_animation(Animation::new(false, frames, 0, false))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The leds of `board` that are not off.
+fn lit_leds<const W: usize, const H: usize>(
+    board: &[[LedColor; W]; H],
+) -> Vec<(usize, usize, LedState)> {
+    let mut leds = Vec::new();
+    for (y, row) in board.iter().enumerate() {
+        for (x, color) in row.iter().enumerate() {
+            if !matches!(color, LedColor::Off) {
+                leds.push((x, y, LedState::with_color(*color)));
+            }
+        }
+    }
+    leds
+}
+
+mod test_boards {
+    #[allow(unused_imports)]
+    use super::TestPattern;
+    #[allow(unused_imports)]
+    use crate::LedColor;
+
+    /// Encoded colors of `board`.
+    #[allow(dead_code)]
+    fn encoded<const W: usize, const H: usize>(board: &[[LedColor; W]; H]) -> [[u8; W]; H] {
+        board.map(|row| row.map(|color| color as u8))
+    }
+
+    #[test]
+    fn checkerboard() {
+        let boards = TestPattern::Checkerboard(LedColor::Red, LedColor::Blue).boards::<3, 2>();
+        assert_eq!(boards.len(), 1);
+        assert_eq!(encoded(&boards[0]), [[1, 4, 1], [4, 1, 4]]);
+    }
+
+    #[test]
+    fn row_sweep() {
+        let boards = TestPattern::RowSweep.boards::<3, 2>();
+        let encoded: Vec<_> = boards.iter().map(encoded).collect();
+        assert_eq!(encoded, [[[7, 7, 7], [0, 0, 0]], [[0, 0, 0], [7, 7, 7]]]);
+    }
+
+    #[test]
+    fn column_sweep() {
+        let boards = TestPattern::ColumnSweep.boards::<2, 2>();
+        let encoded: Vec<_> = boards.iter().map(encoded).collect();
+        assert_eq!(encoded, [[[7, 0], [7, 0]], [[0, 7], [0, 7]]]);
+    }
+
+    #[test]
+    fn all_colors() {
+        let boards = TestPattern::AllColors.boards::<2, 2>();
+        let colors: Vec<u8> = boards.iter().map(|board| board[1][1] as u8).collect();
+        assert_eq!(colors, [1, 2, 3, 4, 5, 6, 7]);
+    }
+}
+
+mod test_test_pattern {
+    #[allow(unused_imports)]
+    use super::TestPattern;
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        LedColor, SyncType,
+    };
+
+    #[test]
+    fn checkerboard_is_synced() {
+        let (mut disp, rx) = interface();
+        disp.test_pattern(TestPattern::Checkerboard(LedColor::Green, LedColor::Off))
+            .unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::Sync(SyncType::All(rows))) => {
+                assert!(matches!(rows[0][0].color, LedColor::Green));
+                assert!(matches!(rows[0][1].color, LedColor::Off));
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sweep_is_animated() {
+        let (mut disp, rx) = interface();
+        disp.test_pattern(TestPattern::RowSweep).unwrap();
+        match rx.try_recv() {
+            Ok(Instruction::AddAnimation(_, animation)) => {
+                assert_eq!(animation.frames.len(), 7);
+                assert!(animation.frames.iter().all(|frame| frame.rst_after));
+                assert!(animation.frames[3].leds.iter().all(|(_, y, _)| *y == 3));
+                assert_eq!(animation.frames[3].leds.len(), 7);
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+    }
+}
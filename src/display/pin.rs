@@ -0,0 +1,435 @@
+use crate::{error, DisplayPins, PanelConfig, PinConfig};
+
+/// Pin type the display is driven through when it is started with a [PinConfig].
+#[cfg(all(feature = "rppal", not(any(feature = "simulator", feature = "mock"))))]
+pub(super) type ConfigPin = rppal::gpio::OutputPin;
+/// Pin type the display is driven through when it is started with a [PinConfig].
+#[cfg(feature = "simulator")]
+pub(super) type ConfigPin = super::simulator::SimPin;
+/// Pin type the display is driven through when it is started with a [PinConfig].
+#[cfg(all(feature = "mock", not(feature = "simulator")))]
+pub(super) type ConfigPin = MockPin;
+
+/// A digital output pin used to drive the shift registers and decoder.
+///
+/// Implement this to drive the matrix from something other than the gpio header of a
+/// Raspberry Pi, and start the display with
+/// [DisplayInterface::try_start_with_pins](crate::DisplayInterface::try_start_with_pins).
+pub trait OutputPin: Send {
+    /// Set the pin low.
+    fn set_low(&mut self);
+
+    /// Set the pin high.
+    fn set_high(&mut self);
+
+    /// Set the pin high if `high` is true, low otherwise.
+    fn set_level(&mut self, high: bool) {
+        match high {
+            true => self.set_high(),
+            false => self.set_low(),
+        }
+    }
+
+    /// Drive the pin with pwm at `frequency` Hz, high for `duty_cycle` (0.0 - 1.0) of
+    /// every period.
+    ///
+    /// Pins without pwm support stay low unless the duty cycle is `1.0`. For the active low
+    /// enables of a 74HC595 and 74HC138 this shows the display at full brightness instead of
+    /// dimming it. A pin driving an enable configured as active high in [PanelConfig] keeps
+    /// its outputs disabled this way, so it should implement pwm.
+    fn set_pwm_frequency(&mut self, frequency: f64, duty_cycle: f64) -> error::DisplayResult<()> {
+        let _ = frequency;
+        self.set_level(duty_cycle >= 1.0);
+        Ok(())
+    }
+
+    /// Stop driving the pin with pwm.
+    fn clear_pwm(&mut self) -> error::DisplayResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl OutputPin for rppal::gpio::OutputPin {
+    fn set_low(&mut self) {
+        rppal::gpio::OutputPin::set_low(self)
+    }
+
+    fn set_high(&mut self) {
+        rppal::gpio::OutputPin::set_high(self)
+    }
+
+    fn set_pwm_frequency(&mut self, frequency: f64, duty_cycle: f64) -> error::DisplayResult<()> {
+        rppal::gpio::OutputPin::set_pwm_frequency(self, frequency, duty_cycle)?;
+        Ok(())
+    }
+
+    fn clear_pwm(&mut self) -> error::DisplayResult<()> {
+        rppal::gpio::OutputPin::clear_pwm(self)?;
+        Ok(())
+    }
+}
+
+/// Drives the display through a pin implementing `embedded_hal::digital::OutputPin`.
+///
+/// Errors reported by the pin are logged, the display keeps running.
+#[cfg(feature = "embedded-hal")]
+#[derive(Debug)]
+pub struct HalPin<P>(pub P);
+
+#[cfg(feature = "embedded-hal")]
+impl<P: embedded_hal::digital::OutputPin + Send> OutputPin for HalPin<P> {
+    fn set_low(&mut self) {
+        if let Err(e) = self.0.set_low() {
+            log::warn!("Failed to set pin low: {e:?}");
+        }
+    }
+
+    fn set_high(&mut self) {
+        if let Err(e) = self.0.set_high() {
+            log::warn!("Failed to set pin high: {e:?}");
+        }
+    }
+}
+
+/// Pin that is not connected to anything.
+///
+/// With the `mock` feature, and without the `simulator` feature, every display started
+/// with a [PinConfig] is driven through these, which runs the display thread on hosts
+/// without gpio pins, like a laptop or CI machine. It can also be passed to
+/// [DisplayInterface::try_start_with_pins](crate::DisplayInterface::try_start_with_pins)
+/// directly.
+#[cfg(feature = "mock")]
+#[derive(Debug, Default)]
+pub struct MockPin;
+
+#[cfg(feature = "mock")]
+impl OutputPin for MockPin {
+    fn set_low(&mut self) {}
+
+    fn set_high(&mut self) {}
+}
+
+impl PinConfig {
+    /// Get every pin of this configuration through `get`, in the order of the fields.
+    ///
+    /// `get` is given the number of the pin and whether it has to start out high. The
+    /// output enable pins start out at their disabled level and the shift register clear
+    /// asserted, for the polarity given by `panel`, so the panel stays dark from the moment
+    /// they are taken over until the display is set up. Every other pin starts out low.
+    ///
+    /// The first failure is returned together with the role and number of the pin.
+    #[allow(dead_code)]
+    pub(super) fn map_pins<T, E>(
+        &self,
+        panel: &PanelConfig,
+        mut get: impl FnMut(u8, bool) -> Result<T, E>,
+    ) -> Result<DisplayPins<T>, (&'static str, u8, E)> {
+        let mut pin = |role, nr, high| get(nr, high).map_err(|e| (role, nr, e));
+
+        Ok(DisplayPins {
+            sr_serin: pin("sr_serin", self.sr_serin, false)?,
+            sr_srclk: pin("sr_srclk", self.sr_srclk, false)?,
+            sr_rclk: pin("sr_rclk", self.sr_rclk, false)?,
+            sr_srclr: pin("sr_srclr", self.sr_srclr, !panel.srclr_active_low)?,
+            sr_oe: pin("sr_oe", self.sr_oe, panel.oe_active_low)?,
+            dec_a0: pin("dec_a0", self.dec_a0, false)?,
+            dec_a1: pin("dec_a1", self.dec_a1, false)?,
+            dec_a2: pin("dec_a2", self.dec_a2, false)?,
+            dec_a3: self.dec_a3.map(|nr| pin("dec_a3", nr, false)).transpose()?,
+            dec_le: pin("dec_le", self.dec_le, false)?,
+            dec_e1: pin("dec_e1", self.dec_e1, panel.e1_active_low)?,
+        })
+    }
+}
+
+#[cfg(all(feature = "rppal", not(any(feature = "simulator", feature = "mock"))))]
+impl PinConfig {
+    /// Acquire the gpio pins of this configuration.
+    pub(super) fn acquire(
+        &self,
+        panel: &PanelConfig,
+    ) -> error::DisplayResult<DisplayPins<ConfigPin>> {
+        self.validate()?;
+        // every pin is taken from one handle, which opens the gpio memory only once
+        let gpio = rppal::gpio::Gpio::new()?;
+        self.map_pins(panel, |nr, high| {
+            let pin = gpio.get(nr)?;
+            Ok(match high {
+                true => pin.into_output_high(),
+                false => pin.into_output_low(),
+            })
+        })
+        .map_err(|(role, pin, source)| {
+            log::error!("Failed to acquire gpio pin {pin} ({role}): {source:?}");
+            error::Error::PinInit { role, pin, source }
+        })
+    }
+}
+
+#[cfg(feature = "simulator")]
+impl PinConfig {
+    /// Create simulated pins, the pin numbers are ignored.
+    pub(super) fn acquire(
+        &self,
+        _panel: &PanelConfig,
+    ) -> error::DisplayResult<DisplayPins<ConfigPin>> {
+        use super::simulator::SimPin;
+
+        self.validate()?;
+
+        Ok(DisplayPins {
+            sr_serin: SimPin,
+            sr_srclk: SimPin,
+            sr_rclk: SimPin,
+            sr_srclr: SimPin,
+            sr_oe: SimPin,
+            dec_a0: SimPin,
+            dec_a1: SimPin,
+            dec_a2: SimPin,
+            dec_a3: self.dec_a3.map(|_| SimPin),
+            dec_le: SimPin,
+            dec_e1: SimPin,
+        })
+    }
+}
+
+#[cfg(all(feature = "mock", not(feature = "simulator")))]
+impl PinConfig {
+    /// Create mock pins, the pin numbers are ignored.
+    pub(super) fn acquire(
+        &self,
+        panel: &PanelConfig,
+    ) -> error::DisplayResult<DisplayPins<ConfigPin>> {
+        self.validate()?;
+        self.map_pins(panel, |_, _| Ok::<_, std::convert::Infallible>(MockPin))
+            .map_err(|(_, _, never)| match never {})
+    }
+}
+
+pub(super) mod test_output_pin {
+    #[allow(unused_imports)]
+    use super::OutputPin;
+    #[allow(unused_imports)]
+    use crate::DisplayPins;
+    #[allow(unused_imports)]
+    use std::sync::{Arc, Mutex};
+
+    /// Pin that is not connected to anything.
+    #[allow(dead_code)]
+    pub(crate) struct NullPin;
+
+    impl OutputPin for NullPin {
+        fn set_low(&mut self) {}
+        fn set_high(&mut self) {}
+    }
+
+    /// Pins that are not connected to anything.
+    #[allow(dead_code)]
+    pub(crate) fn null_pins() -> DisplayPins<NullPin> {
+        DisplayPins {
+            sr_serin: NullPin,
+            sr_srclk: NullPin,
+            sr_rclk: NullPin,
+            sr_srclr: NullPin,
+            sr_oe: NullPin,
+            dec_a0: NullPin,
+            dec_a1: NullPin,
+            dec_a2: NullPin,
+            dec_a3: None,
+            dec_le: NullPin,
+            dec_e1: NullPin,
+        }
+    }
+
+    /// Every level set on a group of [RecordingPin]s in order, with the index of the pin.
+    /// `true` is high.
+    #[allow(dead_code)]
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct Trace(Arc<Mutex<Vec<(u8, bool)>>>);
+
+    /// Pin that adds every level it is set to to a [Trace].
+    #[allow(dead_code)]
+    #[derive(Debug, Clone)]
+    pub(crate) struct RecordingPin {
+        index: u8,
+        trace: Trace,
+    }
+
+    impl OutputPin for RecordingPin {
+        fn set_low(&mut self) {
+            self.trace.0.lock().unwrap().push((self.index, false));
+        }
+
+        fn set_high(&mut self) {
+            self.trace.0.lock().unwrap().push((self.index, true));
+        }
+    }
+
+    #[allow(dead_code)]
+    impl Trace {
+        // index of every pin of [Trace::pins], in the order of the fields of [DisplayPins]
+        pub(crate) const SR_SERIN: u8 = 0;
+        pub(crate) const SR_SRCLK: u8 = 1;
+        pub(crate) const SR_RCLK: u8 = 2;
+        pub(crate) const SR_SRCLR: u8 = 3;
+        pub(crate) const SR_OE: u8 = 4;
+        pub(crate) const DEC_A0: u8 = 5;
+        pub(crate) const DEC_A1: u8 = 6;
+        pub(crate) const DEC_A2: u8 = 7;
+        pub(crate) const DEC_A3: u8 = 8;
+        pub(crate) const DEC_LE: u8 = 9;
+        pub(crate) const DEC_E1: u8 = 10;
+
+        /// A pin recording to this trace as pin `index`.
+        pub(crate) fn pin(&self, index: u8) -> RecordingPin {
+            RecordingPin {
+                index,
+                trace: self.clone(),
+            }
+        }
+
+        /// Pins of a display with 8 rows recording to this trace, indexed by the constants
+        /// above.
+        pub(crate) fn pins(&self) -> DisplayPins<RecordingPin> {
+            DisplayPins {
+                sr_serin: self.pin(Self::SR_SERIN),
+                sr_srclk: self.pin(Self::SR_SRCLK),
+                sr_rclk: self.pin(Self::SR_RCLK),
+                sr_srclr: self.pin(Self::SR_SRCLR),
+                sr_oe: self.pin(Self::SR_OE),
+                dec_a0: self.pin(Self::DEC_A0),
+                dec_a1: self.pin(Self::DEC_A1),
+                dec_a2: self.pin(Self::DEC_A2),
+                dec_a3: None,
+                dec_le: self.pin(Self::DEC_LE),
+                dec_e1: self.pin(Self::DEC_E1),
+            }
+        }
+
+        /// Every level set so far.
+        pub(crate) fn events(&self) -> Vec<(u8, bool)> {
+            self.0.lock().unwrap().clone()
+        }
+
+        /// Every level pin `index` was set to so far.
+        pub(crate) fn levels(&self, index: u8) -> Vec<bool> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(pin, _)| *pin == index)
+                .map(|(_, high)| *high)
+                .collect()
+        }
+
+        /// The level pin `index` was set to last, if it was set at all.
+        pub(crate) fn level(&self, index: u8) -> Option<bool> {
+            self.levels(index).last().copied()
+        }
+
+        /// How often pin `index` was set high so far.
+        pub(crate) fn count_high(&self, index: u8) -> usize {
+            self.levels(index).into_iter().filter(|high| *high).count()
+        }
+
+        /// Forget every level set so far.
+        pub(crate) fn clear(&self) {
+            self.0.lock().unwrap().clear();
+        }
+    }
+
+    #[test]
+    fn set_level() {
+        let trace = Trace::default();
+        let mut pin = trace.pin(0);
+        pin.set_level(true);
+        assert_eq!(trace.level(0), Some(true));
+        pin.set_level(false);
+        assert_eq!(trace.level(0), Some(false));
+    }
+
+    #[test]
+    fn pwm_fallback() {
+        let trace = Trace::default();
+        let mut pin = trace.pin(0);
+        pin.set_pwm_frequency(2_400.0, 1.0).unwrap();
+        assert_eq!(trace.level(0), Some(true));
+        pin.set_pwm_frequency(2_400.0, 0.9).unwrap();
+        assert_eq!(trace.level(0), Some(false));
+        pin.clear_pwm().unwrap();
+        assert_eq!(trace.levels(0), [true, false]);
+    }
+}
+
+mod test_map_pins {
+    #[allow(unused_imports)]
+    use crate::{PanelConfig, PinConfig};
+
+    #[test]
+    fn all_pins() {
+        let pins = PinConfig::default()
+            .map_pins(&Default::default(), |nr, _| Ok::<u8, ()>(nr))
+            .unwrap();
+        assert_eq!(pins.sr_serin, 17);
+        assert_eq!(pins.dec_e1, 10);
+    }
+
+    #[test]
+    fn reports_failing_pin() {
+        let mut tried = Vec::new();
+        let result = PinConfig::default().map_pins(&Default::default(), |nr, _| {
+            tried.push(nr);
+            match nr {
+                23 => Err("busy"),
+                nr => Ok(nr),
+            }
+        });
+        assert!(matches!(result, Err(("sr_rclk", 23, "busy"))));
+        // pins after the failing one are not acquired
+        assert_eq!(tried, [17, 22, 23]);
+    }
+
+    #[test]
+    fn outputs_start_disabled() {
+        let high = |panel: PanelConfig| {
+            PinConfig::default()
+                .map_pins(&panel, |_, high| Ok::<bool, ()>(high))
+                .unwrap()
+        };
+        let pins = high(PanelConfig::default());
+        assert!(pins.sr_oe && pins.dec_e1);
+        assert!(!pins.sr_serin && !pins.sr_srclr && !pins.dec_le);
+
+        // an active high output enable is disabled while low
+        let pins = high(PanelConfig {
+            oe_active_low: false,
+            ..PanelConfig::default()
+        });
+        assert!(!pins.sr_oe && pins.dec_e1);
+        let pins = high(PanelConfig {
+            e1_active_low: false,
+            ..PanelConfig::default()
+        });
+        assert!(pins.sr_oe && !pins.dec_e1);
+
+        // an active high clear is asserted while high
+        let pins = high(PanelConfig {
+            srclr_active_low: false,
+            ..PanelConfig::default()
+        });
+        assert!(pins.sr_srclr && pins.sr_oe && pins.dec_e1);
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn acquire_rejects_duplicate_pins() {
+        // edited directly, so the builder never checked it
+        let mut pins = PinConfig::default();
+        pins.dec_e1 = pins.sr_oe;
+        assert!(matches!(
+            pins.acquire(&Default::default()),
+            Err(crate::Error::DuplicatePin(27))
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{DisplayResult, PinConfig};
+
+/// Builds a [PinConfig], created by [PinConfig::builder].
+///
+/// Every pin starts at its number in [PinConfig::default], so only the pins that are wired
+/// differently have to be set.
+///
+/// # Example
+///
+/// ```
+/// use c4_display::PinConfig;
+///
+/// let pins = PinConfig::builder().sr_oe(4).dec_e1(12).build().unwrap();
+/// assert_eq!(pins.sr_oe, 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PinConfigBuilder {
+    pins: PinConfig,
+}
+
+impl PinConfig {
+    /// Build a pin configuration, see [PinConfigBuilder].
+    pub fn builder() -> PinConfigBuilder {
+        PinConfigBuilder {
+            pins: Self::default(),
+        }
+    }
+}
+
+impl PinConfigBuilder {
+    /// Set the serial input pin of the shift register.
+    pub fn sr_serin(mut self, pin: u8) -> Self {
+        self.pins.sr_serin = pin;
+        self
+    }
+
+    /// Set the serial clock pin of the shift register.
+    pub fn sr_srclk(mut self, pin: u8) -> Self {
+        self.pins.sr_srclk = pin;
+        self
+    }
+
+    /// Set the register clock pin of the shift register.
+    pub fn sr_rclk(mut self, pin: u8) -> Self {
+        self.pins.sr_rclk = pin;
+        self
+    }
+
+    /// Set the serial clear pin of the shift register.
+    pub fn sr_srclr(mut self, pin: u8) -> Self {
+        self.pins.sr_srclr = pin;
+        self
+    }
+
+    /// Set the output enable pin of the shift register.
+    pub fn sr_oe(mut self, pin: u8) -> Self {
+        self.pins.sr_oe = pin;
+        self
+    }
+
+    /// Set the first (least significant) decoder bit.
+    pub fn dec_a0(mut self, pin: u8) -> Self {
+        self.pins.dec_a0 = pin;
+        self
+    }
+
+    /// Set the second decoder bit.
+    pub fn dec_a1(mut self, pin: u8) -> Self {
+        self.pins.dec_a1 = pin;
+        self
+    }
+
+    /// Set the third (most significant) decoder bit.
+    pub fn dec_a2(mut self, pin: u8) -> Self {
+        self.pins.dec_a2 = pin;
+        self
+    }
+
+    /// Set the fourth decoder bit, needed for displays with more than 8 rows.
+    pub fn dec_a3(mut self, pin: u8) -> Self {
+        self.pins.dec_a3 = Some(pin);
+        self
+    }
+
+    /// Set the latch enable pin of the decoder.
+    pub fn dec_le(mut self, pin: u8) -> Self {
+        self.pins.dec_le = pin;
+        self
+    }
+
+    /// Set the output enable pin of the decoder.
+    pub fn dec_e1(mut self, pin: u8) -> Self {
+        self.pins.dec_e1 = pin;
+        self
+    }
+
+    /// Finish the pin configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::DuplicatePin` with the pin number if a pin is used
+    /// for more than one role.
+    pub fn build(self) -> DisplayResult<PinConfig> {
+        self.pins.validate()?;
+        Ok(self.pins)
+    }
+}
+
+mod test_pin_config_builder {
+    #[allow(unused_imports)]
+    use crate::{Error, PinConfig};
+
+    #[test]
+    fn defaults() {
+        let pins = PinConfig::builder().build().unwrap();
+        assert_eq!(pins.sr_serin, 17);
+        assert_eq!(pins.dec_e1, 10);
+    }
+
+    #[test]
+    fn custom_pins() {
+        let pins = PinConfig::builder().sr_serin(2).dec_a2(3).build().unwrap();
+        assert_eq!(pins.sr_serin, 2);
+        assert_eq!(pins.dec_a2, 3);
+        assert_eq!(pins.sr_srclk, PinConfig::default().sr_srclk);
+    }
+
+    #[test]
+    fn duplicate_pin() {
+        // 22 is the default shift register clock
+        assert!(matches!(
+            PinConfig::builder().dec_le(22).build(),
+            Err(Error::DuplicatePin(22))
+        ));
+        assert!(matches!(
+            PinConfig::builder().sr_oe(4).dec_e1(4).build(),
+            Err(Error::DuplicatePin(4))
+        ));
+        assert!(matches!(
+            PinConfig::builder().dec_a3(25).build(),
+            Err(Error::DuplicatePin(25))
+        ));
+        assert!(PinConfig::builder().dec_a3(4).build().is_ok());
+    }
+}
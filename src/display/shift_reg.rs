@@ -1,72 +1,201 @@
-use rppal::gpio::{Gpio, OutputPin};
-
-use super::LedColor;
-use crate::pins::{OePinNr, RclkPinNr, SerinPinNr, SrclkPinNr, SrclrPinNr};
-use crate::{error, spin_wait, PSWT};
+use super::{ColorOrder, LedColor, OutputPin};
+use crate::{error, spin_wait};
+use std::time::Duration;
 
+/// One or more daisy-chained 74HC595 shift registers holding the colors of a row.
+///
+/// Every led takes [COLOR_BITS](super::COLOR_BITS) bits, so a row of `W` leds spans `W * 3 / 8` registers,
+/// rounded up. All bits of a row are shifted through the chain before they are pushed to
+/// the outputs at once.
 #[derive(Debug)]
 #[allow(dead_code)]
-pub(super) struct ShiftReg {
+pub(super) struct ShiftReg<P> {
     /// Serial input pin. Active high.
-    serin: OutputPin,
+    serin: P,
     /// Serial clock pin. Active high.
-    srclk: OutputPin,
+    srclk: P,
     /// Register clock pin. Active high.
-    rclk: OutputPin,
-    /// Serial clear pin. Active high.
-    srclr: OutputPin,
-    /// Output enable pin. Active low.
-    oe: OutputPin,
+    rclk: P,
+    /// Serial clear pin. Active low unless configured otherwise.
+    srclr: P,
+    /// Output enable pin. Active low unless configured otherwise.
+    oe: P,
+    /// Fraction of time the outputs are enabled.
+    brightness: f64,
+    /// Frequency the output enable pin is driven at while dimmed.
+    pwm_frequency: f64,
+    /// Whether the output enable pin is currently driven with pwm.
+    pwm_running: bool,
+    /// Time for a pin to switch state.
+    pswt: Duration,
+    /// Order in which the color bits of a led are shifted in.
+    color_order: ColorOrder,
+    /// Whether the output enable pin is active low.
+    oe_active_low: bool,
+    /// Whether the serial clear pin is active low.
+    srclr_active_low: bool,
 }
 
-impl ShiftReg {
+impl<P: OutputPin> ShiftReg<P> {
     /// create new shift register instance
     ///
     /// pin order:
     ///
-    /// 1: SerinPinNr (u8)
+    /// 1: serial input
+    ///
+    /// 2: serial clock
+    ///
+    /// 3: register clock
     ///
-    /// 2: SrclkPinNr (u8)
+    /// 4: serial clear
     ///
-    /// 3: RclkPinNr (u8)
+    /// 5: output enable
     ///
-    /// 4: SrclrPinNr (u8)
+    /// The outputs are disabled and the register is held clear before anything else,
+    /// so the panel stays dark while the pins are set up.
     ///
-    /// 5: OePinNr (u8)
+    /// Every pin switch is followed by a wait of `pswt`, and the color bits of every led are
+    /// shifted in the order of `color_order`. While dimmed, the output enable pin is driven
+    /// with pwm at `pwm_frequency` Hz. `active_low` holds whether the output enable and
+    /// serial clear pins are active low.
     pub(super) fn new(
-        pins: (SerinPinNr, SrclkPinNr, RclkPinNr, SrclrPinNr, OePinNr),
-    ) -> error::DisplayResult<Self> {
+        pins: (P, P, P, P, P),
+        pswt: Duration,
+        pwm_frequency: f64,
+        color_order: ColorOrder,
+        active_low: (bool, bool),
+    ) -> Self {
+        let (serin, srclk, rclk, srclr, oe) = pins;
+        let (oe_active_low, srclr_active_low) = active_low;
+
         let mut sr = Self {
-            serin: Gpio::new()?.get(pins.0)?.into_output(),
-            srclk: Gpio::new()?.get(pins.1)?.into_output(),
-            rclk: Gpio::new()?.get(pins.2)?.into_output(),
-            srclr: Gpio::new()?.get(pins.3)?.into_output(),
-            oe: Gpio::new()?.get(pins.4)?.into_output(),
-        }
-        ._clear();
+            serin,
+            srclk,
+            rclk,
+            srclr,
+            oe,
+            brightness: 1.0,
+            pwm_frequency,
+            pwm_running: false,
+            pswt,
+            color_order,
+            oe_active_low,
+            srclr_active_low,
+        };
+        sr.set_oe(false);
+        sr.set_srclr(true);
+
+        let mut sr = sr._clear();
         sr.serin.set_low();
         sr.srclk.set_low();
         sr.rclk.set_low();
-        sr.srclr.set_high();
-        sr.oe.set_low();
-        // sr.oe.set_pwm_frequency(100_000.0, 0.5).unwrap();
-        Ok(sr)
+        sr.set_srclr(false);
+        sr.set_oe(true);
+        sr
+    }
+
+    /// Drive the output enable pin so the outputs are enabled or not.
+    fn set_oe(&mut self, enabled: bool) {
+        self.oe.set_level(enabled != self.oe_active_low);
+    }
+
+    /// Drive the serial clear pin so the register is cleared or not.
+    fn set_srclr(&mut self, clear: bool) {
+        self.srclr.set_level(clear != self.srclr_active_low);
+    }
+
+    /// Duty cycle of the output enable pwm for the outputs to be enabled `on` of the time.
+    fn oe_duty(&self, on: f64) -> f64 {
+        match self.oe_active_low {
+            true => 1.0 - on,
+            false => on,
+        }
+    }
+
+    /// Set the brightness of the outputs, from `0.0` to `1.0`.
+    ///
+    /// Below full brightness the output enable pin is driven with pwm.
+    /// At full brightness the pwm is cleared again.
+    pub(super) fn set_brightness(&mut self, level: f64) -> error::DisplayResult<()> {
+        self.brightness = level.clamp(0.0, 1.0);
+        match (self.brightness < 1.0, self.pwm_running) {
+            // the outputs are lit, so the new duty cycle applies right away
+            (true, true) => self.start_pwm()?,
+            // the next enable starts the pwm
+            (true, false) => (),
+            (false, _) => self.stop_pwm()?,
+        }
+        Ok(())
+    }
+
+    /// Drive the output enable pin with pwm at `frequency` Hz while dimmed.
+    pub(super) fn set_pwm_frequency(&mut self, frequency: f64) -> error::DisplayResult<()> {
+        self.pwm_frequency = frequency;
+        match self.pwm_running {
+            true => self.start_pwm(),
+            false => Ok(()),
+        }
+    }
+
+    /// Drive the output enable pin with pwm at the brightness as duty cycle.
+    fn start_pwm(&mut self) -> error::DisplayResult<()> {
+        self.oe
+            .set_pwm_frequency(self.pwm_frequency, self.oe_duty(self.brightness))?;
+        self.pwm_running = true;
+        Ok(())
+    }
+
+    /// Stop driving the output enable pin with pwm.
+    fn stop_pwm(&mut self) -> error::DisplayResult<()> {
+        self.pwm_running = false;
+        self.oe.clear_pwm()
     }
 
     /// Enable the shift register
     ///
+    /// While dimmed, this starts the pwm unless it is running already.
+    ///
     /// This function takes at least 1 microsecond
     pub(super) fn enable(&mut self) {
-        self.oe.set_low();
-        spin_wait(PSWT);
+        match self.brightness < 1.0 {
+            true if self.pwm_running => (),
+            true => {
+                if let Err(e) = self.start_pwm() {
+                    log::warn!("Failed to start output enable pwm: {e:?}");
+                }
+            }
+            false => self.set_oe(true),
+        }
+        spin_wait(self.pswt);
     }
 
     /// Disable the shift register
     ///
     /// This function takes at least 1 microsecond
     pub(super) fn disable(&mut self) {
-        self.oe.set_high();
-        spin_wait(PSWT);
+        if self.pwm_running {
+            if let Err(e) = self.stop_pwm() {
+                log::warn!("Failed to stop output enable pwm: {e:?}");
+            }
+        }
+        self.set_oe(false);
+        spin_wait(self.pswt);
+    }
+
+    /// Disable the shift register while the column is switched, so the leds of one column
+    /// do not light up in the next.
+    ///
+    /// While dimmed the pwm is left running instead, since setting it up again for every
+    /// column would take longer than the switch itself. Returns `true` in that case, the
+    /// outputs are still enabled and the column has to be turned off instead.
+    ///
+    /// This function takes at least 1 microsecond
+    pub(super) fn disable_for_switch(&mut self) -> bool {
+        match self.pwm_running {
+            true => spin_wait(self.pswt),
+            false => self.disable(),
+        }
+        self.pwm_running
     }
 
     /// Push the input register to the output register
@@ -74,17 +203,17 @@ impl ShiftReg {
     /// This function takes at least 2x `PinSwitchTime`
     pub(super) fn push(&mut self) {
         self.rclk.set_high();
-        spin_wait(PSWT);
+        spin_wait(self.pswt);
         self.rclk.set_low();
-        spin_wait(PSWT);
+        spin_wait(self.pswt);
     }
 
     /// Shift a [LedColor] into the shift register.
     ///
     /// This function takes at least 9x `PinSwitchTime`.
     pub(super) fn shift_color(&mut self, color: &LedColor) {
-        for c_bit in 0..3 {
-            self.shift((*color as usize >> c_bit & 1) != 0);
+        for bit in self.color_order.bits(*color) {
+            self.shift(bit);
         }
     }
 
@@ -95,19 +224,19 @@ impl ShiftReg {
         match bit {
             true => {
                 self.serin.set_high();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
                 self.srclk.set_high();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
                 self.srclk.set_low();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
             }
             false => {
                 self.serin.set_low();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
                 self.srclk.set_high();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
                 self.srclk.set_low();
-                spin_wait(PSWT);
+                spin_wait(self.pswt);
             }
         }
     }
@@ -116,30 +245,219 @@ impl ShiftReg {
     ///
     /// This function takes at least 4x `PinSwitchTime`.
     pub(super) fn clear(&mut self) {
-        self.srclr.set_low();
-        spin_wait(PSWT);
-        self.srclr.set_high();
-        spin_wait(PSWT);
+        self.set_srclr(true);
+        spin_wait(self.pswt);
+        self.set_srclr(false);
+        spin_wait(self.pswt);
     }
 
     /// Clear the register
     ///
     /// This function takes at least 4x `PinSwitchTime`.
     fn _clear(mut self) -> Self {
-        self.srclr.set_high();
-        spin_wait(PSWT);
-        self.srclr.set_low();
-        spin_wait(PSWT);
+        self.set_srclr(false);
+        spin_wait(self.pswt);
+        self.set_srclr(true);
+        spin_wait(self.pswt);
         self.rclk.set_high();
-        spin_wait(PSWT);
+        spin_wait(self.pswt);
         self.rclk.set_low();
-        spin_wait(PSWT);
+        spin_wait(self.pswt);
         self
     }
 }
 
-// impl Drop for ShiftReg {
-//     fn drop(&mut self) {
-//         self.oe.clear_pwm().unwrap();
-//     }
-// }
+mod test_color_order {
+    #[allow(unused_imports)]
+    use super::ShiftReg;
+    #[allow(unused_imports)]
+    use crate::{display::test_output_pin::Trace, ColorOrder, LedColor};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    /// The bits shifted in for `color` with the given order.
+    #[allow(dead_code)]
+    fn shifted_bits(color: LedColor, order: ColorOrder) -> Vec<bool> {
+        let trace = Trace::default();
+        let mut sr = ShiftReg::new(
+            (
+                trace.pin(Trace::SR_SERIN),
+                trace.pin(Trace::SR_SRCLK),
+                trace.pin(Trace::SR_RCLK),
+                trace.pin(Trace::SR_SRCLR),
+                trace.pin(Trace::SR_OE),
+            ),
+            Duration::ZERO,
+            2_400.0,
+            order,
+            (true, true),
+        );
+        trace.clear();
+        sr.shift_color(&color);
+        trace.levels(Trace::SR_SERIN)
+    }
+
+    #[test]
+    fn default_order() {
+        assert_eq!(
+            shifted_bits(LedColor::Green, ColorOrder::default()),
+            [false, true, false]
+        );
+    }
+
+    #[test]
+    fn swapped_green_and_blue() {
+        assert_eq!(
+            shifted_bits(LedColor::Green, ColorOrder::Rbg),
+            [false, false, true]
+        );
+        assert_eq!(
+            shifted_bits(LedColor::Yellow, ColorOrder::Rbg),
+            [true, false, true]
+        );
+        assert_eq!(
+            shifted_bits(LedColor::Blue, ColorOrder::Bgr),
+            [true, false, false]
+        );
+    }
+}
+
+mod test_polarity {
+    #[allow(unused_imports)]
+    use super::ShiftReg;
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{RecordingPin, Trace},
+        ColorOrder,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    /// A shift register with the given polarities, and the trace of its pins.
+    #[allow(dead_code)]
+    fn shift_reg(active_low: (bool, bool)) -> (ShiftReg<RecordingPin>, Trace) {
+        let trace = Trace::default();
+        let sr = ShiftReg::new(
+            (
+                trace.pin(Trace::SR_SERIN),
+                trace.pin(Trace::SR_SRCLK),
+                trace.pin(Trace::SR_RCLK),
+                trace.pin(Trace::SR_SRCLR),
+                trace.pin(Trace::SR_OE),
+            ),
+            Duration::ZERO,
+            2_400.0,
+            ColorOrder::default(),
+            active_low,
+        );
+        (sr, trace)
+    }
+
+    #[test]
+    fn active_low() {
+        let (mut sr, trace) = shift_reg((true, true));
+        // disabled and cleared first, released and enabled last
+        assert_eq!(trace.levels(Trace::SR_OE), [true, false]);
+        assert_eq!(trace.levels(Trace::SR_SRCLR), [false, true, false, true]);
+
+        trace.clear();
+        sr.disable();
+        sr.enable();
+        sr.clear();
+        assert_eq!(trace.levels(Trace::SR_OE), [true, false]);
+        assert_eq!(trace.levels(Trace::SR_SRCLR), [false, true]);
+    }
+
+    #[test]
+    fn active_high() {
+        let (mut sr, trace) = shift_reg((false, false));
+        assert_eq!(trace.levels(Trace::SR_OE), [false, true]);
+        assert_eq!(trace.levels(Trace::SR_SRCLR), [true, false, true, false]);
+
+        trace.clear();
+        sr.disable();
+        sr.enable();
+        sr.clear();
+        assert_eq!(trace.levels(Trace::SR_OE), [false, true]);
+        assert_eq!(trace.levels(Trace::SR_SRCLR), [true, false]);
+    }
+}
+
+mod test_brightness {
+    #[allow(unused_imports)]
+    use super::ShiftReg;
+    #[allow(unused_imports)]
+    use crate::{
+        display::test_output_pin::{RecordingPin, Trace},
+        ColorOrder,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[allow(dead_code)]
+    fn shift_reg() -> (ShiftReg<RecordingPin>, Trace) {
+        let trace = Trace::default();
+        let sr = ShiftReg::new(
+            (
+                trace.pin(Trace::SR_SERIN),
+                trace.pin(Trace::SR_SRCLK),
+                trace.pin(Trace::SR_RCLK),
+                trace.pin(Trace::SR_SRCLR),
+                trace.pin(Trace::SR_OE),
+            ),
+            Duration::ZERO,
+            2_400.0,
+            ColorOrder::default(),
+            (true, true),
+        );
+        trace.clear();
+        (sr, trace)
+    }
+
+    #[test]
+    fn pwm_is_set_up_once() {
+        let (mut sr, trace) = shift_reg();
+        sr.set_brightness(0.5).unwrap();
+        assert!(!sr.pwm_running);
+        sr.enable();
+        assert!(sr.pwm_running);
+
+        // switching columns leaves the pwm running
+        trace.clear();
+        for _ in 0..8 {
+            sr.disable_for_switch();
+            sr.enable();
+        }
+        assert!(sr.pwm_running);
+        assert!(trace.levels(Trace::SR_OE).is_empty());
+
+        // a new frequency or brightness is applied right away
+        sr.set_pwm_frequency(3_000.0).unwrap();
+        assert_eq!(sr.pwm_frequency, 3_000.0);
+        sr.set_brightness(0.25).unwrap();
+        assert_eq!(trace.levels(Trace::SR_OE).len(), 2);
+    }
+
+    #[test]
+    fn disable_stops_pwm() {
+        let (mut sr, trace) = shift_reg();
+        sr.set_brightness(0.5).unwrap();
+        sr.enable();
+        trace.clear();
+
+        sr.disable();
+        assert!(!sr.pwm_running);
+        assert_eq!(trace.levels(Trace::SR_OE), [true]);
+    }
+
+    #[test]
+    fn full_brightness_switches_every_column() {
+        let (mut sr, trace) = shift_reg();
+        for _ in 0..2 {
+            sr.disable_for_switch();
+            sr.enable();
+        }
+        assert!(!sr.pwm_running);
+        assert_eq!(trace.levels(Trace::SR_OE), [true, false, true, false]);
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::Write;
+
+use super::{LedColor, OutputPin};
+
+/// Pin that is not connected to anything, used to run the display without hardware.
+#[derive(Debug)]
+pub(crate) struct SimPin;
+
+impl OutputPin for SimPin {
+    fn set_low(&mut self) {}
+
+    fn set_high(&mut self) {}
+}
+
+/// Clear the terminal the display is drawn in.
+pub(super) fn clear_screen() {
+    print!("\x1b[2J");
+}
+
+/// Draw the colors shown by the leds to stdout, on top of the previous drawing.
+pub(super) fn render<const W: usize, const H: usize>(colors: &[[LedColor; W]; H]) {
+    let mut stdout = std::io::stdout().lock();
+    // the display is only drawn, failing to do so is not worth stopping it for
+    let _ = write!(stdout, "\x1b[H{}", ansi_board(colors));
+    let _ = stdout.flush();
+}
+
+/// Draw every led as a block colored with an ansi truecolor escape code, one line per row.
+fn ansi_board<const W: usize, const H: usize>(colors: &[[LedColor; W]; H]) -> String {
+    let mut board = String::new();
+    for row in colors {
+        for color in row {
+            let (r, g, b) = rgb(color);
+            board.push_str(&format!("\x1b[38;2;{r};{g};{b}m██"));
+        }
+        board.push_str("\x1b[0m\n");
+    }
+    board
+}
+
+/// The color a led is drawn in. Leds that are off are drawn dark gray so the grid stays visible.
+fn rgb(color: &LedColor) -> (u8, u8, u8) {
+    match color {
+        LedColor::Off => (40, 40, 40),
+        _ => color.to_rgb(),
+    }
+}
+
+mod test_ansi_board {
+    #[allow(unused_imports)]
+    use super::{ansi_board, LedColor};
+
+    #[test]
+    fn one_line_per_row() {
+        let mut colors = [[LedColor::Off; 3]; 2];
+        colors[1][2] = LedColor::Red;
+
+        let board = ansi_board(&colors);
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].matches("██").count(), 3);
+        assert!(lines[1].ends_with("\x1b[38;2;255;0;0m██\x1b[0m"));
+        assert!(!lines[0].contains("255;0;0"));
+    }
+}
@@ -0,0 +1,304 @@
+#[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+use crate::PinConfig;
+use crate::{
+    display::{
+        display_interface::{validate_animation, validate_sync},
+        Animation, DisplayInterface, LedColor, LedState, OutputPin, Running, Stopped, SyncType,
+    },
+    DisplayPins, DisplayResult, Error, PanelConfig, Timing,
+};
+
+/// Settings to start a display with, created by [DisplayInterface::configure].
+///
+/// Only the refresh rate and, when starting on gpio pins, the pin configuration are
+/// required. Everything else is optional and validated before the display is started.
+///
+/// # Example
+///
+#[cfg_attr(
+    any(feature = "rppal", feature = "simulator", feature = "mock"),
+    doc = "```no_run"
+)]
+#[cfg_attr(
+    not(any(feature = "rppal", feature = "simulator", feature = "mock")),
+    doc = "```ignore"
+)]
+/// use c4_display::{DisplayInterface, LedColor, PinConfig};
+///
+/// let display = DisplayInterface::<_, 7, 7>::new("id")
+///     .configure()
+///     .refresh(60.0)
+///     .pins(PinConfig::default())
+///     .brightness(0.5)
+///     .background(LedColor::Blue)
+///     .build_and_start()
+///     .unwrap();
+/// ```
+pub struct StartBuilder<'d, const W: usize, const H: usize> {
+    interface: DisplayInterface<'d, Stopped, W, H>,
+    refresh: Option<f64>,
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pins: Option<PinConfig>,
+    settings: StartSettings<W, H>,
+}
+
+/// Settings applied to a display right after it has started.
+#[derive(Debug)]
+struct StartSettings<const W: usize, const H: usize> {
+    brightness: f64,
+    background: LedColor,
+    board: Option<[[LedState; W]; H]>,
+    splash: Option<Animation>,
+}
+
+impl<'d, const W: usize, const H: usize> DisplayInterface<'d, Stopped, W, H> {
+    /// Configure how the display is started, see [StartBuilder].
+    pub fn configure(self) -> StartBuilder<'d, W, H> {
+        StartBuilder {
+            interface: self,
+            refresh: None,
+            #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+            pins: None,
+            settings: StartSettings {
+                brightness: 1.0,
+                background: LedColor::Off,
+                board: None,
+                splash: None,
+            },
+        }
+    }
+}
+
+impl<'d, const W: usize, const H: usize> StartBuilder<'d, W, H> {
+    /// Run the display at `refresh` Hz.
+    pub fn refresh(mut self, refresh: f64) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// Drive the display through the gpio pins of `pins`.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn pins(mut self, pins: PinConfig) -> Self {
+        self.pins = Some(pins);
+        self
+    }
+
+    /// Wait for the shift registers and decoder as given by `timing`, see
+    /// [DisplayInterface::with_timing].
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.interface = self.interface.with_timing(timing);
+        self
+    }
+
+    /// Drive a panel wired and mounted as given by `panel`, see [DisplayInterface::with_panel].
+    pub fn panel(mut self, panel: PanelConfig) -> Self {
+        self.interface = self.interface.with_panel(panel);
+        self
+    }
+
+    /// Let at most `capacity` instructions wait for the display thread, see
+    /// [DisplayInterface::with_channel_capacity].
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.interface = self.interface.with_channel_capacity(capacity);
+        self
+    }
+
+    /// Start at the given brightness, from `0.0` to `1.0`, see
+    /// [DisplayInterface::set_brightness].
+    pub fn brightness(mut self, level: f64) -> Self {
+        self.settings.brightness = level;
+        self
+    }
+
+    /// Show `color` on leds that are off, see [DisplayInterface::set_background].
+    pub fn background(mut self, color: LedColor) -> Self {
+        self.settings.background = color;
+        self
+    }
+
+    /// Show `board` as soon as the display starts.
+    pub fn board(mut self, board: [[LedState; W]; H]) -> Self {
+        self.settings.board = Some(board);
+        self
+    }
+
+    /// Play `animation` as soon as the display starts, on top of the board.
+    pub fn splash(mut self, animation: Animation) -> Self {
+        self.settings.splash = Some(animation);
+        self
+    }
+
+    /// Start the display on the gpio pins with the configured settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Uninitiated` if no refresh rate or pins are set.
+    ///
+    /// Returns a `c4_display::error::Error::Gpio` if any of the pins could not be acquired.
+    ///
+    /// Returns every error of [StartBuilder::build_and_start_with_pins] for invalid settings.
+    /// Every setting is checked before the pins are acquired.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub fn build_and_start(mut self) -> DisplayResult<DisplayInterface<'d, Running, W, H>> {
+        let refresh = self.validate()?;
+        let pins = self.pins.take().ok_or(Error::Uninitiated)?;
+
+        let mut disp = self.interface.try_start(refresh, pins)?;
+        self.settings.apply(&mut disp)?;
+        Ok(disp)
+    }
+
+    /// Start the display driven through the given output pins with the configured settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::Uninitiated` if no refresh rate is set, and a
+    /// `c4_display::error::Error::InvalidRefresh` if it is not a positive number.
+    ///
+    /// Returns a `c4_display::error::Error::InvalidBrightness` if the brightness is not a
+    /// number from `0.0` to `1.0`.
+    ///
+    /// Returns a `c4_display::error::Error::InvalidDim` if the splash animation does not fit
+    /// the display, and a `c4_display::error::Error::InvalidBlink` if any led of the board
+    /// or splash animation has a zero blink interval or a blink duration larger than its
+    /// blink interval.
+    pub fn build_and_start_with_pins<P: OutputPin + 'static>(
+        self,
+        pins: DisplayPins<P>,
+    ) -> DisplayResult<DisplayInterface<'d, Running, W, H>> {
+        // nothing is started unless every setting can be applied
+        let refresh = self.validate()?;
+
+        let mut disp = self.interface.try_start_with_pins(refresh, pins)?;
+        self.settings.apply(&mut disp)?;
+        Ok(disp)
+    }
+
+    /// Check every setting, returns the refresh rate.
+    fn validate(&self) -> DisplayResult<f64> {
+        let refresh = self.refresh.ok_or(Error::Uninitiated)?;
+        if !(refresh.is_finite() && refresh > 0.0) {
+            return Err(Error::InvalidRefresh);
+        }
+        if !(0.0..=1.0).contains(&self.settings.brightness) {
+            return Err(Error::InvalidBrightness);
+        }
+        if let Some(board) = &self.settings.board {
+            validate_sync::<W, H>(&board_sync(board))?;
+        }
+        if let Some(splash) = &self.settings.splash {
+            validate_animation::<W, H>(splash)?;
+        }
+        Ok(refresh)
+    }
+}
+
+impl<const W: usize, const H: usize> StartSettings<W, H> {
+    /// Send the settings to a started display.
+    fn apply(self, disp: &mut DisplayInterface<Running, W, H>) -> DisplayResult<()> {
+        disp.set_brightness(self.brightness)?;
+        disp.set_background(self.background)?;
+        if let Some(board) = &self.board {
+            disp.sync(board_sync(board))?;
+        }
+        if let Some(splash) = self.splash {
+            disp.add_animation(splash)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sync replacing the entire display with `board`.
+fn board_sync<const W: usize, const H: usize>(board: &[[LedState; W]; H]) -> SyncType {
+    SyncType::All(board.iter().map(|row| row.to_vec()).collect())
+}
+
+mod test_start_builder {
+    #[allow(unused_imports)]
+    use crate::{
+        display::{display_interface::test_interface::interface, Instruction},
+        Animation, AnimationFrame, BlinkInfo, DisplayInterface, Error, LedColor, LedState,
+        SyncType,
+    };
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    #[allow(dead_code)]
+    fn splash() -> Animation {
+        let frame = AnimationFrame::new(
+            Duration::from_millis(100),
+            vec![(3, 3, LedState::with_color(LedColor::Red))],
+            true,
+        );
+        Animation::new(false, vec![frame], 0, false)
+    }
+
+    #[test]
+    fn settings_are_sent() {
+        let builder = DisplayInterface::<'static, _, 7, 7>::new("id")
+            .configure()
+            .refresh(120.0)
+            .brightness(0.5)
+            .background(LedColor::Blue)
+            .board([[LedState::with_color(LedColor::Green); 7]; 7])
+            .splash(splash());
+        assert_eq!(builder.validate().unwrap(), 120.0);
+
+        let (mut disp, rx) = interface();
+        builder.settings.apply(&mut disp).unwrap();
+        let sent: Vec<Instruction> = rx.try_iter().collect();
+        assert!(matches!(sent[0], Instruction::SetBrightness(level) if level == 0.5));
+        assert!(matches!(
+            sent[1],
+            Instruction::SetBackground(LedColor::Blue)
+        ));
+        match &sent[2] {
+            Instruction::Sync(SyncType::All(board)) => {
+                assert!(matches!(board[6][6].color, LedColor::Green))
+            }
+            other => panic!("unexpected instruction: {other:?}"),
+        }
+        assert!(matches!(sent[3], Instruction::AddAnimation(_, _)));
+        assert_eq!(sent.len(), 4);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let configure = || DisplayInterface::<'static, _, 7, 7>::new("id").configure();
+        assert!(matches!(configure().validate(), Err(Error::Uninitiated)));
+        assert!(matches!(
+            configure().refresh(0.0).validate(),
+            Err(Error::InvalidRefresh)
+        ));
+        assert!(matches!(
+            configure().refresh(f64::NAN).validate(),
+            Err(Error::InvalidRefresh)
+        ));
+        for level in [f64::NAN, -0.1, 1.5] {
+            assert!(matches!(
+                configure().refresh(60.0).brightness(level).validate(),
+                Err(Error::InvalidBrightness)
+            ));
+        }
+
+        let mut blinking = LedState::with_color(LedColor::Red);
+        blinking.blink = Some(BlinkInfo {
+            dur: Duration::from_millis(200),
+            int: Duration::from_millis(100),
+        });
+        assert!(matches!(
+            configure()
+                .refresh(60.0)
+                .board([[blinking; 7]; 7])
+                .validate(),
+            Err(Error::InvalidBlink)
+        ));
+
+        let mut splash = splash();
+        splash.frames[0].leds[0].0 = 7;
+        assert!(matches!(
+            configure().refresh(60.0).splash(splash).validate(),
+            Err(Error::InvalidDim)
+        ));
+    }
+}
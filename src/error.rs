@@ -6,13 +6,63 @@ pub enum Error {
     /// The provided dimensions do not match or exceed the dimensions of the display.
     InvalidDim,
     /// GPIO error return by rppal.
+    #[cfg(feature = "rppal")]
     Gpio(rppal::gpio::Error),
+    /// A gpio pin of the display could not be acquired.
+    #[cfg(feature = "rppal")]
+    PinInit {
+        /// Role of the pin, named like the field of [PinConfig](crate::PinConfig).
+        role: &'static str,
+        /// Number of the pin.
+        pin: u8,
+        /// Error returned by rppal.
+        source: rppal::gpio::Error,
+    },
     /// A necessary variable is not initiated.
     Uninitiated,
     /// The given file could not be found.
     FileNotFound,
+    /// A file could not be read or written.
+    Io(std::io::Error),
     /// The animation could not be parsed from string.
     ParseError(AnimationParseError),
+    /// The blink interval of a led is zero, or its blink duration is larger than the interval.
+    InvalidBlink,
+    /// No active animation has the given id.
+    UnknownAnimation,
+    /// The refresh rate is not a positive number.
+    InvalidRefresh,
+    /// The gamma is not a positive number.
+    InvalidGamma,
+    /// The brightness is not a number from `0.0` to `1.0`.
+    InvalidBrightness,
+    /// The queue of instructions for the display thread is full.
+    QueueFull,
+    /// The display thread has stopped, so it no longer receives instructions.
+    Disconnected,
+    /// The animation loops forever, so waiting for it to finish would never return.
+    LoopingAnimation,
+    /// The display did not finish in the given time.
+    Timeout,
+    /// The gpio pin with this number is used for more than one role.
+    DuplicatePin(u8),
+    /// The duration is zero, while it has to last for some time.
+    InvalidDuration,
+    /// Image error returned by image.
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+}
+
+impl Error {
+    /// Check if this is an error of the gpio pins.
+    #[cfg(any(feature = "rppal", feature = "simulator", feature = "mock"))]
+    pub(crate) fn is_gpio(&self) -> bool {
+        match self {
+            #[cfg(feature = "rppal")]
+            Self::Gpio(_) | Self::PinInit { .. } => true,
+            _ => false,
+        }
+    }
 }
 
 /// Result used by functions in this crate.
@@ -22,12 +72,74 @@ impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        match self {
+            Self::InvalidDim => write!(f, "dimensions do not fit the display"),
+            #[cfg(feature = "rppal")]
+            Self::Gpio(e) => write!(f, "gpio error: {e}"),
+            #[cfg(feature = "rppal")]
+            Self::PinInit { role, pin, source } => {
+                write!(f, "failed to acquire pin {pin} as {role}: {source}")
+            }
+            Self::Uninitiated => write!(f, "a necessary value is not initiated yet"),
+            Self::FileNotFound => write!(f, "file not found"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::ParseError(e) => write!(f, "failed to parse animation: {e:?}"),
+            Self::InvalidBlink => write!(f, "invalid blink interval or duration"),
+            Self::UnknownAnimation => write!(f, "no active animation has this id"),
+            Self::InvalidRefresh => write!(f, "refresh rate is not a positive number"),
+            Self::InvalidGamma => write!(f, "gamma is not a positive number"),
+            Self::InvalidBrightness => write!(f, "brightness is not from 0.0 to 1.0"),
+            Self::QueueFull => write!(f, "instruction queue of the display is full"),
+            Self::Disconnected => write!(f, "display thread has stopped"),
+            Self::LoopingAnimation => write!(f, "animation loops forever"),
+            Self::Timeout => write!(f, "display did not finish in time"),
+            Self::DuplicatePin(pin) => write!(f, "pin {pin} is used for more than one role"),
+            Self::InvalidDuration => write!(f, "duration is zero"),
+            #[cfg(feature = "image")]
+            Self::Image(e) => write!(f, "image error: {e}"),
+        }
     }
 }
 
+#[cfg(feature = "rppal")]
 impl From<rppal::gpio::Error> for Error {
     fn from(e: rppal::gpio::Error) -> Self {
         Self::Gpio(e)
     }
 }
+
+impl From<AnimationParseError> for Error {
+    fn from(e: AnimationParseError) -> Self {
+        Self::ParseError(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+mod test_display {
+    #[allow(unused_imports)]
+    use super::Error;
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            Error::DuplicatePin(4).to_string(),
+            "pin 4 is used for more than one role"
+        );
+        assert_eq!(
+            Error::Io(std::io::Error::other("gone")).to_string(),
+            "io error: gone"
+        );
+    }
+}
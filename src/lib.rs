@@ -1,23 +1,40 @@
 // // #![allow(dead_code)]
 //! Library to more easily drive the led matrix.
-// TODO add logging
-// TODO ability to change dimming
 
 #![warn(missing_docs)]
 use std::time::{Duration, Instant};
+pub mod c4;
 mod display;
 mod error;
+pub mod life;
 
 // Crate API exports
+#[cfg(feature = "net")]
+pub use display::net;
+#[cfg(feature = "async")]
+pub use display::AsyncDisplayInterface;
+#[cfg(feature = "embedded-hal")]
+pub use display::HalPin;
+#[cfg(feature = "mock")]
+pub use display::MockPin;
 pub use display::{
-    Animation, AnimationFrame, BlinkInfo, DisplayInterface, LedColor, LedState, Paused, Rotation,
-    Running, State, Stopped, Sync, SyncType,
+    board_diff, Animation, AnimationFrame, AnimationId, Axis, BlinkInfo, Canvas, ColorOrder,
+    Direction, DisplayInterface, FrameClock, LedColor, LedState, Orientation, OutputPin, Paused,
+    PinConfigBuilder, Playback, Rotation, Running, Snapshot, StartBuilder, State, Stopped, Sync,
+    SyncTemplate, SyncType, TestPattern, BLUE_YELLOW_SAFE_PALETTE, COLOR_BITS, DEFAULT_GAMMA,
+    RED_GREEN_SAFE_PALETTE, SHOW_ONCE_DURATION,
 };
 pub use error::{DisplayResult, Error};
 
-/// Time for gpio pins to switch state
+/// Time for gpio pins to switch state, used by [Timing::default].
 const PSWT: std::time::Duration = std::time::Duration::from_nanos(100);
 
+/// Waits longer than this sleep for all but this last part, which is spun for precision.
+///
+/// A sleep can wake up late by the timer slack of the thread, `50µs` by default on linux,
+/// plus the time it takes to be scheduled again. This leaves room for both.
+const SPIN_THRESHOLD: std::time::Duration = std::time::Duration::from_micros(200);
+
 #[allow(dead_code)]
 pub(self) mod pins {
     pub type SerinPinNr = u8;
@@ -28,6 +45,7 @@ pub(self) mod pins {
     pub type A0PinNr = u8;
     pub type A1PinNr = u8;
     pub type A2PinNr = u8;
+    pub type A3PinNr = u8;
     pub type LEPinNr = u8;
     pub type E1PinNr = u8;
 }
@@ -36,9 +54,16 @@ pub(self) mod pins {
 ///
 /// Pins starting with sr_ are used by the shift register,
 /// whereas pins starting with dec_ are used by to the decoder.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PinConfig {
     /// Serial input pin of the shift register
+    ///
+    /// Every led takes three bits, so rows wider than two leds need several shift
+    /// registers. These are daisy-chained: this pin goes to the serial input of the first
+    /// register and the serial output (QH') of every register to the serial input of the
+    /// next, while all registers share the other shift register pins. The first led of a row
+    /// is shifted in first and ends up at the end of the chain.
     pub sr_serin: pins::SerinPinNr, // shift register serial input
 
     /// Serial clock pin of the shift register
@@ -59,9 +84,18 @@ pub struct PinConfig {
     /// Second decoder bit. This is the second least significant bit, equivalent to 2.
     pub dec_a1: pins::A1PinNr, // decoder pin 1
 
-    /// Third decoder bit. This is the most significant bit, equivalent to 4.
+    /// Third decoder bit, equivalent to 4.
     pub dec_a2: pins::A2PinNr, // decoder pin 2
 
+    /// Fourth decoder bit, equivalent to 8. Only needed for displays with more than 8 rows.
+    ///
+    /// A single decoder selects one of 8 rows, so taller displays use a second decoder
+    /// sharing every other decoder pin. This pin selects between them: it should disable
+    /// the first decoder and enable the second while it is high, for example by wiring it
+    /// to an active low enable of the first and an active high enable of the second.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dec_a3: Option<pins::A3PinNr>, // decoder pin 3
+
     /// Decoder Latch Enable.
     /// If enabled the changes to the input of the decoder will nog affect the output.
     pub dec_le: pins::LEPinNr,
@@ -71,66 +105,340 @@ pub struct PinConfig {
     pub dec_e1: pins::E1PinNr, // decoder output enable (active low)
 }
 
+impl Default for PinConfig {
+    /// The pins the display is wired to on the reference board.
+    fn default() -> Self {
+        Self {
+            sr_serin: 17,
+            sr_srclk: 22,
+            sr_rclk: 23,
+            sr_srclr: 24,
+            sr_oe: 27,
+            dec_a0: 25,
+            dec_a1: 11,
+            dec_a2: 5,
+            dec_a3: None,
+            dec_le: 6,
+            dec_e1: 10,
+        }
+    }
+}
+
+impl PinConfig {
+    /// Check that no pin number is used for more than one role.
+    ///
+    /// Done when the pins are acquired, so a configuration whose fields were edited directly
+    /// is checked as well as one made by [PinConfigBuilder].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `c4_display::error::Error::DuplicatePin` with the first pin number that is
+    /// used more than once.
+    pub fn validate(&self) -> DisplayResult<()> {
+        let pins: Vec<u8> = [
+            self.sr_serin,
+            self.sr_srclk,
+            self.sr_rclk,
+            self.sr_srclr,
+            self.sr_oe,
+            self.dec_a0,
+            self.dec_a1,
+            self.dec_a2,
+            self.dec_le,
+            self.dec_e1,
+        ]
+        .into_iter()
+        .chain(self.dec_a3)
+        .collect();
+        match pins
+            .iter()
+            .enumerate()
+            .find(|(i, pin)| pins[i + 1..].contains(pin))
+        {
+            Some((_, pin)) => Err(Error::DuplicatePin(*pin)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Output pins to use for shift registers and decoders, see [PinConfig] for the role of each pin.
+///
+/// Used to drive the display through any [OutputPin] implementation.
+#[derive(Debug)]
+#[allow(missing_docs)]
+pub struct DisplayPins<P> {
+    pub sr_serin: P,
+    pub sr_srclk: P,
+    pub sr_rclk: P,
+    pub sr_srclr: P,
+    pub sr_oe: P,
+    pub dec_a0: P,
+    pub dec_a1: P,
+    pub dec_a2: P,
+    pub dec_a3: Option<P>,
+    pub dec_le: P,
+    pub dec_e1: P,
+}
+
+/// How the panel is wired and mounted, apart from the pins it is driven through.
+///
+/// Passed next to the pins when the display is started, see [DisplayInterface::with_panel].
+/// The defaults suit a panel wired straight to a 74HC595 shift register.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PanelConfig {
+    /// Order in which the color bits of a led are wired to the shift register outputs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub color_order: ColorOrder,
+
+    /// How the panel is mounted, see [Orientation].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub orientation: Orientation,
+
+    /// Whether the output enable line of the shift register is active low, as on a 74HC595.
+    /// Clear this if the line is inverted on its way to the register, for example by a buffer.
+    #[cfg_attr(feature = "serde", serde(default = "active_low"))]
+    pub oe_active_low: bool,
+
+    /// Whether the serial clear line of the shift register is active low, as on a 74HC595.
+    /// Clear this if the line is inverted on its way to the register, for example by a buffer.
+    #[cfg_attr(feature = "serde", serde(default = "active_low"))]
+    pub srclr_active_low: bool,
+
+    /// Whether the output enable line E1 of the decoder is active low, as on a 74HC138.
+    /// Clear this if the line is inverted on its way to the decoder, for example by a buffer.
+    #[cfg_attr(feature = "serde", serde(default = "active_low"))]
+    pub e1_active_low: bool,
+}
+
+impl Default for PanelConfig {
+    /// Rgb leds on a panel mounted upright, with active low shift register and decoder lines.
+    fn default() -> Self {
+        Self {
+            color_order: ColorOrder::Rgb,
+            orientation: Orientation::default(),
+            oe_active_low: true,
+            srclr_active_low: true,
+            e1_active_low: true,
+        }
+    }
+}
+
+/// Lines are active low by default, used as serde default for the polarities of [PanelConfig].
+#[cfg(feature = "serde")]
+fn active_low() -> bool {
+    true
+}
+
+/// How often to try acquiring the gpio pins before giving up.
+///
+/// The gpio chip can briefly be busy, for example while other services start at boot.
+#[derive(Debug, Clone, Copy)]
+pub struct GpioRetry {
+    /// Total number of attempts. `0` is treated as `1`.
+    pub attempts: usize,
+    /// Time to wait between attempts.
+    pub delay: Duration,
+}
+
+impl Default for GpioRetry {
+    /// Only try once.
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+/// How long to wait for the shift registers and decoder after switching their pins.
+///
+/// The defaults suit a 74HC595 shift register and 74HC138 decoder on short wires. Slower
+/// parts, long wires or a fast Raspberry Pi can need longer delays. If they are too short,
+/// bits are clocked in before the input has settled and leds light up in the wrong color
+/// or column, or ghost into the neighbouring column. Longer delays lower the highest
+/// refresh rate the display can reach.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// Time for a pin of the shift register or the latch of the decoder to switch state.
+    pub pin_switch: Duration,
+    /// Time for the decoder outputs to settle after selecting another column.
+    pub decoder_settle: Duration,
+}
+
+impl Default for Timing {
+    /// Wait 100ns after every pin switch.
+    fn default() -> Self {
+        Self {
+            pin_switch: PSWT,
+            decoder_settle: PSWT,
+        }
+    }
+}
+
 #[inline]
 /// Wait for the given duration `dur`
+///
+/// Long waits, like the ones between columns, sleep until `200µs` before the end and only
+/// spin the rest, so the thread does not keep a core busy. Shorter waits, like the ones
+/// after switching a pin, are spun entirely and keep their precision.
 pub fn spin_wait(dur: Duration) {
     let t = Instant::now();
+    if dur > SPIN_THRESHOLD {
+        std::thread::sleep(dur - SPIN_THRESHOLD);
+    }
     while t.elapsed() < dur {
         std::hint::spin_loop();
     }
-    // std::thread::sleep(dur);
-}
-
-// /// Stops code execution until an enter is received from `stdin`.
-// ///
-// /// Can be passed a string that will be logged with debug level.
-// #[macro_export]
-// macro_rules! breakpoint {
-//     () => {
-//         #[cfg(feature = "breakpoints")]
-//         {
-//             use std::io::{stdin, stdout, Write};
-
-//             log::debug!("breakpoint!");
-//             stdout().flush().unwrap();
-//             stdin().read_line(&mut String::new()).unwrap();
-//         }
-//     };
-//     ($($arg:tt)*) => {
-//         #[cfg(feature = "breakpoints")]
-//         {
-//             use std::io::{stdin, stdout, Write};
-
-//             log::debug!("breakpoint!\t{}", format_args!($($arg)*));
-//             stdout().flush().unwrap();
-//             stdin().read_line(&mut String::new()).unwrap();
-//         }
-//     };
-// }
-
-// /// Stops code execution until an enter is received from `stdin`.
-// ///
-// /// Can be passed a string that will be logged with debug level.
-// #[macro_export]
-// macro_rules! breakpoint_sbs {
-//     () => {
-//         #[cfg(feature = "sbs_debug")]
-//         {
-//             use std::io::{stdin, stdout, Write};
-
-//             log::debug!("breakpoint!");
-//             stdout().flush().unwrap();
-//             stdin().read_line(&mut String::new()).unwrap();
-//         }
-//     };
-//     ($($arg:tt)*) => {
-//         #[cfg(feature = "sbs_debug")]
-//         {
-//             use std::io::{stdin, stdout, Write};
-
-//             log::debug!("breakpoint!\t{}", format_args!($($arg)*));
-//             stdout().flush().unwrap();
-//             stdin().read_line(&mut String::new()).unwrap();
-//         }
-//     };
-// }
+}
+
+/// Stops code execution until an enter is received from `stdin`.
+///
+/// Can be passed a string that will be logged with debug level.
+///
+/// Only stops with the `breakpoints` feature, otherwise it compiles to nothing.
+#[cfg(feature = "breakpoints")]
+#[macro_export]
+macro_rules! breakpoint {
+    () => {{
+        use std::io::{stdin, stdout, Write};
+
+        log::debug!("breakpoint!");
+        stdout().flush().unwrap();
+        stdin().read_line(&mut String::new()).unwrap();
+    }};
+    ($($arg:tt)*) => {{
+        use std::io::{stdin, stdout, Write};
+
+        log::debug!("breakpoint!\t{}", format_args!($($arg)*));
+        stdout().flush().unwrap();
+        stdin().read_line(&mut String::new()).unwrap();
+    }};
+}
+
+/// Stops code execution until an enter is received from `stdin`.
+///
+/// Can be passed a string that will be logged with debug level.
+///
+/// Only stops with the `breakpoints` feature, otherwise it compiles to nothing.
+#[cfg(not(feature = "breakpoints"))]
+#[macro_export]
+macro_rules! breakpoint {
+    ($($arg:tt)*) => {};
+}
+
+/// Stops code execution until an enter is received from `stdin`, for stepping through
+/// the display one row at a time.
+///
+/// Can be passed a string that will be logged with debug level.
+///
+/// Only stops with the `sbs_debug` feature, otherwise it compiles to nothing.
+#[cfg(feature = "sbs_debug")]
+#[macro_export]
+macro_rules! breakpoint_sbs {
+    ($($arg:tt)*) => {
+        $crate::breakpoint!($($arg)*)
+    };
+}
+
+/// Stops code execution until an enter is received from `stdin`, for stepping through
+/// the display one row at a time.
+///
+/// Can be passed a string that will be logged with debug level.
+///
+/// Only stops with the `sbs_debug` feature, otherwise it compiles to nothing.
+#[cfg(not(feature = "sbs_debug"))]
+#[macro_export]
+macro_rules! breakpoint_sbs {
+    ($($arg:tt)*) => {};
+}
+
+mod test_spin_wait {
+    #[allow(unused_imports)]
+    use super::{spin_wait, PSWT, SPIN_THRESHOLD};
+    #[allow(unused_imports)]
+    use std::time::{Duration, Instant};
+
+    /// The least time a few waits for `dur` took longer than `dur`, so a single wait that
+    /// lost the cpu to another thread does not count.
+    #[allow(dead_code)]
+    fn overshoot(dur: Duration) -> Duration {
+        (0..5)
+            .map(|_| {
+                let start = Instant::now();
+                spin_wait(dur);
+                let elapsed = start.elapsed();
+                assert!(elapsed >= dur);
+                elapsed - dur
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn short_wait() {
+        assert!(overshoot(PSWT) < Duration::from_micros(20));
+    }
+
+    #[test]
+    fn long_wait() {
+        // a plain sleep regularly wakes up later than this
+        let overshoot = overshoot(SPIN_THRESHOLD * 10);
+        assert!(overshoot < Duration::from_micros(20), "{overshoot:?}");
+    }
+
+    #[test]
+    fn zero_wait() {
+        spin_wait(Duration::ZERO);
+    }
+}
+
+#[cfg(feature = "serde")]
+mod test_serde {
+    #[allow(unused_imports)]
+    use super::{PanelConfig, PinConfig};
+
+    #[test]
+    fn pin_config_round_trip() {
+        let pins = PinConfig {
+            sr_serin: 17,
+            sr_srclk: 22,
+            sr_rclk: 23,
+            sr_srclr: 24,
+            sr_oe: 27,
+            dec_a0: 25,
+            dec_a1: 11,
+            dec_a2: 5,
+            dec_a3: None,
+            dec_le: 6,
+            dec_e1: 10,
+        };
+        let json = serde_json::to_string(&pins).unwrap();
+        let parsed: PinConfig = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.sr_srclk, 22);
+        assert_eq!(parsed.dec_e1, 10);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn panel_config_defaults() {
+        let panel: PanelConfig = serde_json::from_str("{}").unwrap();
+        assert!(panel.oe_active_low && panel.srclr_active_low && panel.e1_active_low);
+
+        let panel: PanelConfig = serde_json::from_str(r#"{"oe_active_low":false}"#).unwrap();
+        assert!(!panel.oe_active_low && panel.srclr_active_low);
+    }
+}
+
+#[cfg(not(feature = "breakpoints"))]
+mod test_breakpoints {
+    #[test]
+    fn no_op_without_features() {
+        // these would wait for stdin if they did anything
+        crate::breakpoint!();
+        crate::breakpoint!("row {}", 3);
+        crate::breakpoint_sbs!();
+        crate::breakpoint_sbs!("row {}", 3);
+    }
+}
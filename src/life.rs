@@ -0,0 +1,223 @@
+//! Conway's Game of Life, for an ambient display.
+
+use std::time::Duration;
+
+use crate::{Animation, AnimationFrame, LedColor, LedState, SyncType};
+
+/// Offsets of the eight neighbours of a cell.
+const NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// How cells on the edge of the board see their neighbours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Edges {
+    /// Everything past the edge of the board is dead.
+    #[default]
+    Dead,
+    /// The board wraps around, the left edge neighbours the right and the top the bottom.
+    Wrap,
+}
+
+/// A generation of Game of Life on a `W` by `H` board, following the B3/S23 rules.
+#[derive(Debug, Clone)]
+pub struct Life<const W: usize, const H: usize> {
+    cells: [[bool; W]; H],
+    edges: Edges,
+}
+
+impl<const W: usize, const H: usize> Life<W, H> {
+    /// Create a new game seeded with the alive `cells`.
+    pub fn new(cells: [[bool; W]; H], edges: Edges) -> Self {
+        Self { cells, edges }
+    }
+
+    /// Create a new game seeded from `board`, where every led that is not off is alive.
+    ///
+    /// A snapshot of the display can be used as seed, see
+    /// [DisplayInterface::snapshot](crate::DisplayInterface::snapshot).
+    pub fn from_board(board: &[[LedState; W]; H], edges: Edges) -> Self {
+        let mut cells = [[false; W]; H];
+        for (y, row) in board.iter().enumerate() {
+            for (x, led) in row.iter().enumerate() {
+                cells[y][x] = !matches!(led.color, LedColor::Off);
+            }
+        }
+        Self::new(cells, edges)
+    }
+
+    /// The alive cells of the current generation.
+    pub fn cells(&self) -> &[[bool; W]; H] {
+        &self.cells
+    }
+
+    /// Advance to the next generation.
+    ///
+    /// A dead cell with exactly three alive neighbours is born, an alive cell with two or
+    /// three alive neighbours survives, every other cell is dead.
+    pub fn step(&mut self) {
+        let mut next = [[false; W]; H];
+        for (y, row) in next.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = matches!(
+                    (self.cells[y][x], self.neighbours(x, y)),
+                    (_, 3) | (true, 2)
+                );
+            }
+        }
+        self.cells = next;
+    }
+
+    /// Sync showing the current generation, with alive cells in `color` and dead cells off.
+    pub fn to_sync(&self, color: LedColor) -> SyncType {
+        SyncType::All(
+            self.cells
+                .iter()
+                .map(|row| row.iter().map(|&alive| cell_led(alive, color)).collect())
+                .collect(),
+        )
+    }
+
+    /// Create a looping animation of the current generation followed by the next
+    /// `generations`, each shown for `step` with alive cells in `color`.
+    pub fn animation(&self, generations: usize, color: LedColor, step: Duration) -> Animation {
+        let mut life = self.clone();
+        let frames = (0..=generations)
+            .map(|i| {
+                if i > 0 {
+                    life.step();
+                }
+                let leds = (0..H)
+                    .flat_map(|y| (0..W).map(move |x| (x, y)))
+                    .map(|(x, y)| (x, y, cell_led(life.cells[y][x], color)))
+                    .collect();
+                AnimationFrame::new(step, leds, false)
+            })
+            .collect();
+        Animation::new(true, frames, 0, false)
+    }
+
+    /// Number of alive neighbours of the cell at `x`, `y`.
+    fn neighbours(&self, x: usize, y: usize) -> usize {
+        NEIGHBOURS
+            .iter()
+            .filter_map(|&(dx, dy)| self.neighbour(x as isize + dx, y as isize + dy))
+            .filter(|&alive| alive)
+            .count()
+    }
+
+    /// Whether the cell at `x`, `y` is alive, or [None] if it is past a dead edge.
+    fn neighbour(&self, x: isize, y: isize) -> Option<bool> {
+        let (x, y) = match self.edges {
+            Edges::Wrap => (x.rem_euclid(W as isize), y.rem_euclid(H as isize)),
+            Edges::Dead => (x, y),
+        };
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) if x < W && y < H => Some(self.cells[y][x]),
+            _ => None,
+        }
+    }
+}
+
+/// Led showing a cell, lit in `color` when alive.
+fn cell_led(alive: bool, color: LedColor) -> LedState {
+    match alive {
+        true => LedState::with_color(color),
+        false => LedState::default(),
+    }
+}
+
+mod test_life {
+    #[allow(unused_imports)]
+    use super::{Edges, Life};
+    #[allow(unused_imports)]
+    use crate::{LedColor, LedState, SyncType};
+    #[allow(unused_imports)]
+    use std::time::Duration;
+
+    /// Board with the given cells alive.
+    #[allow(dead_code)]
+    fn board<const W: usize, const H: usize>(alive: &[(usize, usize)]) -> [[bool; W]; H] {
+        let mut cells = [[false; W]; H];
+        for &(x, y) in alive {
+            cells[y][x] = true;
+        }
+        cells
+    }
+
+    #[test]
+    fn blinker() {
+        let horizontal = board::<5, 5>(&[(1, 2), (2, 2), (3, 2)]);
+        let vertical = board::<5, 5>(&[(2, 1), (2, 2), (2, 3)]);
+        let mut life = Life::new(horizontal, Edges::Dead);
+
+        life.step();
+        assert_eq!(life.cells(), &vertical);
+        life.step();
+        assert_eq!(life.cells(), &horizontal);
+    }
+
+    #[test]
+    fn glider() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut life = Life::new(board::<6, 6>(&glider), Edges::Wrap);
+
+        // every four generations the glider moves one cell down and to the right
+        for _ in 0..4 {
+            life.step();
+        }
+        let moved: Vec<_> = glider.iter().map(|&(x, y)| (x + 1, y + 1)).collect();
+        assert_eq!(life.cells(), &board::<6, 6>(&moved));
+
+        // and wraps back to where it started after crossing the board
+        for _ in 0..20 {
+            life.step();
+        }
+        assert_eq!(life.cells(), &board::<6, 6>(&glider));
+    }
+
+    #[test]
+    fn edges() {
+        // a blinker on the edge only survives when the board wraps
+        let edge = board::<5, 5>(&[(0, 0), (1, 0), (2, 0)]);
+        let mut dead = Life::new(edge, Edges::Dead);
+        let mut wrap = Life::new(edge, Edges::Wrap);
+        dead.step();
+        wrap.step();
+        assert_eq!(dead.cells(), &board::<5, 5>(&[(1, 0), (1, 1)]));
+        assert_eq!(wrap.cells(), &board::<5, 5>(&[(1, 4), (1, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn from_board_and_render() {
+        let mut leds = [[LedState::default(); 3]; 3];
+        leds[1][2] = LedState::with_color(LedColor::Blue);
+        let life = Life::from_board(&leds, Edges::Dead);
+        assert_eq!(life.cells(), &board::<3, 3>(&[(2, 1)]));
+
+        match life.to_sync(LedColor::Green) {
+            SyncType::All(rows) => {
+                assert!(matches!(rows[1][2].color, LedColor::Green));
+                assert!(matches!(rows[0][0].color, LedColor::Off));
+            }
+            other => panic!("unexpected sync: {other:?}"),
+        }
+
+        // the lonely cell dies after the first frame
+        let animation = life
+            .animation(2, LedColor::Green, Duration::from_millis(100))
+            .to_string();
+        let frames: Vec<&str> = animation.split("frame\n").skip(1).collect();
+        assert!(animation.starts_with("animation\nloop true\n"));
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].contains("2 1 green"));
+        assert!(!frames[1].contains("green"));
+    }
+}
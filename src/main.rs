@@ -1,8 +1,7 @@
 use std::str::FromStr;
 
 use c4_display::{
-    Animation, DisplayInterface, LedColor, LedState, PinConfig, Rotation, Running, Stopped,
-    SyncType,
+    Animation, DisplayInterface, LedColor, PinConfig, Rotation, Running, Stopped, SyncType,
 };
 
 const W: usize = 7;
@@ -13,22 +12,8 @@ fn main() {
         .filter_level(log::LevelFilter::Debug)
         .init();
 
-    let mut disp: DisplayInterface<Running, W, H> = DisplayInterface::<Stopped, W, H>::new("id")
-        .start(
-            60.0,
-            PinConfig {
-                sr_serin: 17,
-                sr_srclk: 22,
-                sr_rclk: 23,
-                sr_srclr: 24,
-                sr_oe: 27,
-                dec_a0: 25,
-                dec_a1: 11,
-                dec_a2: 5,
-                dec_le: 6,
-                dec_e1: 10,
-            },
-        );
+    let mut disp: DisplayInterface<Running, W, H> =
+        DisplayInterface::<Stopped, W, H>::new("id").start(60.0, PinConfig::default());
 
     println!("started");
 
@@ -47,21 +32,15 @@ fn main() {
                 disp.sync(SyncType::Rotate(Rotation::Clockwise)).unwrap()
             }
             "180" => disp.sync(SyncType::Rotate(Rotation::OneEighty)).unwrap(),
-            "circle" => disp
-                .add_animation(Animation::from_file("./animations/circle.mtxani").unwrap())
-                .unwrap(),
-            "ca" => disp.clear_animations(),
-            color if LedColor::from_str(color).is_ok() => disp
-                .sync(SyncType::All(vec![
-                    vec![
-                        LedState::with_color(
-                            LedColor::from_str(color).unwrap()
-                        );
-                        W
-                    ];
-                    H
-                ]))
-                .unwrap(),
+            "circle" => {
+                disp.add_animation(Animation::from_file("./animations/circle.mtxani").unwrap())
+                    .unwrap();
+            }
+            "ca" => disp.clear_animations().unwrap(),
+            "clear" | "c" => disp.clear().unwrap(),
+            color if LedColor::from_str(color).is_ok() => {
+                disp.fill(LedColor::from_str(color).unwrap()).unwrap()
+            }
             _ => println!("Invalid: {}", input.trim()),
         }
     }